@@ -10,7 +10,9 @@ pub mod bril_grammar;
 pub mod cli;
 use std::fs::File;
 
-use bril_rs::{AbstractProgram, ColRow, Position};
+use bril_rs::conversion::PositionalConversionError;
+use bril_rs::{AbstractProgram, ColRow, Position, Program};
+use thiserror::Error;
 
 /// A helper function for processing the accepted Bril characters from their text representation
 #[must_use]
@@ -147,3 +149,44 @@ pub fn parse_abstract_program(
 
     parse_abstract_program_from_read(input, use_pos, with_end, file_name)
 }
+
+/// The errors from [`parse_bril_text`]
+// Having the #[error(...)] for all variants derives the Display trait as well
+#[derive(Error, Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub enum ParseError {
+    /// The text isn't well-formed Bril
+    #[error("{0}")]
+    Syntax(String),
+    /// The text is well-formed, but isn't a valid [`Program`], like when an instruction is missing its type
+    #[error(transparent)]
+    Conversion(#[from] PositionalConversionError),
+}
+
+/// Parses the Bril text in `src` into a [`Program`] without source code positions, like `bril2json` without any flags, but returning an error instead of panicking when `src` isn't well-formed.
+///
+/// Printing a [`Program`] gives Bril text that parses back into the same program:
+/// ```
+/// use bril2json::parse_bril_text;
+/// use bril_rs::load_program_from_read;
+/// let json = r#"{"functions": [{"name": "main", "args": [{"name": "n", "type": "int"}], "instrs": [
+///     {"op": "const", "dest": "one", "type": "int", "value": 1},
+///     {"label": "loop"},
+///     {"op": "sub", "dest": "n", "type": "int", "args": ["n", "one"]},
+///     {"op": "gt", "dest": "more", "type": "bool", "args": ["n", "one"]},
+///     {"op": "br", "args": ["more"], "labels": ["loop", "done"]},
+///     {"label": "done"},
+///     {"op": "print", "args": ["n"]}
+/// ]}]}"#;
+/// let program = load_program_from_read(json.as_bytes());
+/// assert_eq!(parse_bril_text(&program.to_string()).unwrap(), program);
+/// assert!(parse_bril_text("@main { x: int = const; }").is_err());
+/// ```
+/// # Errors
+/// If `src` isn't well-formed Bril text, or can't be converted into a [`Program`]
+pub fn parse_bril_text(src: &str) -> Result<Program, ParseError> {
+    let program = bril_grammar::AbstractProgramParser::new()
+        .parse(&Lines::new(src, false, false, None), src)
+        .map_err(|e| ParseError::Syntax(e.to_string()))?;
+    Ok(Program::try_from(program)?)
+}
@@ -0,0 +1,103 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use bril_rs::{AbstractProgram, Program};
+use brillvm::llvm::{create_module_from_program, CodegenOptions};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use inkwell::{context::Context, module::Module};
+
+// The benchmark suites that brillvm supports, the same as in the Makefile
+const SUITES: [&str; 4] = ["core", "float", "mem", "mixed"];
+
+// Converts each benchmark program to JSON using `bril2json`, which is expected to be on the path like for the turnt tests
+fn load_benchmarks() -> Vec<String> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../benchmarks");
+    let mut files: Vec<PathBuf> = SUITES
+        .iter()
+        .flat_map(|suite| std::fs::read_dir(dir.join(suite)).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "bril"))
+        .collect();
+    files.sort();
+    files
+        .iter()
+        .map(|file| {
+            let output = Command::new("bril2json")
+                .stdin(File::open(file).unwrap())
+                .output()
+                .expect("bril2json needs to be installed to run the benchmarks");
+            String::from_utf8(output.stdout).unwrap()
+        })
+        .collect()
+}
+
+// Each phase is measured over the whole set of benchmark programs
+fn program_compilation(c: &mut Criterion) {
+    let json = load_benchmarks();
+    let abstract_programs: Vec<AbstractProgram> = json
+        .iter()
+        .map(|j| serde_json::from_str(j).unwrap())
+        .collect();
+    let programs: Vec<Program> = abstract_programs
+        .iter()
+        .cloned()
+        .map(|p| p.try_into().unwrap())
+        .collect();
+    let runtime_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("rt.bc");
+    let context = Context::create();
+
+    let mut group = c.benchmark_group("program_compilation");
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            json.iter()
+                .map(|j| serde_json::from_str::<AbstractProgram>(j).unwrap())
+                .collect::<Vec<_>>()
+        });
+    });
+    // Resolves opcodes and types, which is the only validation that happens before codegen
+    group.bench_function("convert", |b| {
+        b.iter_batched(
+            || abstract_programs.clone(),
+            |abstract_programs| {
+                abstract_programs
+                    .into_iter()
+                    .map(|p| Program::try_from(p).unwrap())
+                    .collect::<Vec<_>>()
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.bench_function("codegen", |b| {
+        b.iter_batched(
+            || {
+                programs
+                    .iter()
+                    .map(|_| Module::parse_bitcode_from_path(&runtime_path, &context).unwrap())
+                    .collect::<Vec<_>>()
+            },
+            |runtime_modules| {
+                programs
+                    .iter()
+                    .zip(runtime_modules)
+                    .for_each(|(program, runtime_module)| {
+                        black_box(
+                            create_module_from_program(
+                                &context,
+                                program,
+                                runtime_module,
+                                CodegenOptions::default(),
+                                false,
+                            )
+                            .unwrap(),
+                        );
+                    });
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.finish();
+}
+
+criterion_group!(benches, program_compilation);
+criterion_main!(benches);
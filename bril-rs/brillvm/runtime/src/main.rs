@@ -7,7 +7,65 @@ use std::mem::size_of; */
 
 use core::ffi::{c_char, CStr};
 
-use libc_print::std_name::{print, println};
+use libc_print::std_name::{eprintln, print, println};
+
+extern "C" {
+    fn exit(status: i32) -> !;
+    fn realloc(ptr: *mut u8, size: usize) -> *mut u8;
+    fn malloc(size: usize) -> *mut u8;
+    fn free(ptr: *mut u8);
+}
+
+// A growable list of pairs of addresses and sizes, since there is no allocator for `Vec`
+struct Allocations {
+    data: *mut (usize, usize),
+    len: usize,
+    cap: usize,
+}
+
+impl Allocations {
+    const fn new() -> Self {
+        Self {
+            data: core::ptr::null_mut(),
+            len: 0,
+            cap: 0,
+        }
+    }
+
+    fn get(&self, i: usize) -> (usize, usize) {
+        unsafe { *self.data.add(i) }
+    }
+
+    fn push(&mut self, entry: (usize, usize)) {
+        if self.len == self.cap {
+            self.cap = (self.cap * 2).max(16);
+            self.data = unsafe {
+                realloc(
+                    self.data.cast(),
+                    self.cap * core::mem::size_of::<(usize, usize)>(),
+                )
+            }
+            .cast();
+        }
+        unsafe { self.data.add(self.len).write(entry) };
+        self.len += 1;
+    }
+
+    // Removes the entry at `i` by moving the last one into its place
+    fn swap_remove(&mut self, i: usize) {
+        self.len -= 1;
+        unsafe { self.data.add(i).write(*self.data.add(self.len)) };
+    }
+}
+
+// Every allocation made by `alloc`, in order, as its address and size in bytes. They are only recorded when the program prints a pointer
+static mut ALLOCATIONS: Allocations = Allocations::new();
+
+// The allocations that haven't been freed, as their address and size in bytes, for `--check-memory` and `--check-frees`
+static mut LIVE: Allocations = Allocations::new();
+
+// The pointers that `ptradd` has moved outside of every live allocation, as their address and the address of the allocation they came from, for `--check-memory`. Any other pointer belongs to the live allocation that it points into
+static mut ESCAPED: Allocations = Allocations::new();
 
 #[no_mangle]
 pub extern "C" fn _bril_print_int(i: i64) {
@@ -38,6 +96,174 @@ pub extern "C" fn _bril_print_float(f: f64) {
     }
 }
 
+#[no_mangle]
+pub extern "C" fn _bril_print_char(c: u32) {
+    print!("{}", char::from_u32(c).unwrap());
+}
+
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn _bril_record_alloc(p: *const u8, bytes: i64) {
+    let allocs = unsafe { &mut *core::ptr::addr_of_mut!(ALLOCATIONS) };
+    allocs.push((p as usize, bytes as usize));
+}
+
+// Prints a pointer the same way as `brilirs`, as the index of the allocation it points into and its offset in elements of `size` bytes, so that it is the same on every run. Null is never in an allocation, and a pointer that is in none of them is printed as its address
+#[no_mangle]
+pub extern "C" fn _bril_print_ptr(p: *const u8, size: i64) {
+    let addr = p as usize;
+    if p.is_null() {
+        print!("Pointer {{ base: {}, offset: 0 }}", usize::MAX);
+        return;
+    }
+    let allocs = unsafe { &*core::ptr::addr_of!(ALLOCATIONS) };
+    // The most recent allocation wins when `malloc` has reused the memory of a freed one
+    for base in (0..allocs.len).rev() {
+        let (start, bytes) = allocs.get(base);
+        if start <= addr && addr <= start + bytes {
+            let offset = (addr - start) as i64 / size;
+            print!("Pointer {{ base: {}, offset: {} }}", base, offset);
+            return;
+        }
+    }
+    print!("{:p}", p);
+}
+
+#[no_mangle]
+pub extern "C" fn _bril_mem_register(p: *const u8, bytes: i64) {
+    let live = unsafe { &mut *core::ptr::addr_of_mut!(LIVE) };
+    live.push((p as usize, bytes as usize));
+}
+
+// Forgets the allocation at `p` along with the pointers that escaped from it
+#[no_mangle]
+pub extern "C" fn _bril_mem_unregister(p: *const u8) {
+    let start = p as usize;
+    let live = unsafe { &mut *core::ptr::addr_of_mut!(LIVE) };
+    if let Some(i) = (0..live.len).find(|&i| live.get(i).0 == start) {
+        live.swap_remove(i);
+    }
+    let escaped = unsafe { &mut *core::ptr::addr_of_mut!(ESCAPED) };
+    for i in (0..escaped.len).rev() {
+        if escaped.get(i).1 == start {
+            escaped.swap_remove(i);
+        }
+    }
+}
+
+// The address and size in bytes of the live allocation that the pointer at `addr` came from. A pointer just past the end of an allocation still belongs to it, and the most recent allocation wins when `malloc` has reused the memory of a freed one. A pointer that `ptradd` moved into a different allocation counts as pointing into that one, since it has the same address as the pointers that really do
+fn allocation_of(addr: usize) -> Option<(usize, usize)> {
+    let live = unsafe { &*core::ptr::addr_of!(LIVE) };
+    let escaped = unsafe { &*core::ptr::addr_of!(ESCAPED) };
+    (0..live.len)
+        .rev()
+        .map(|i| live.get(i))
+        .find(|&(start, bytes)| start <= addr && addr <= start + bytes)
+        .or_else(|| {
+            let (_, start) = (0..escaped.len)
+                .map(|i| escaped.get(i))
+                .find(|&(a, _)| a == addr)?;
+            (0..live.len)
+                .map(|i| live.get(i))
+                .find(|&(s, _)| s == start)
+        })
+}
+
+// `malloc` for `--check-frees`, which keeps track of the allocation until it is freed
+#[no_mangle]
+pub extern "C" fn _bril_malloc(bytes: i64) -> *mut u8 {
+    let p = unsafe { malloc(bytes as usize) };
+    if !p.is_null() {
+        _bril_mem_register(p, bytes);
+    }
+    p
+}
+
+// `free` for `--check-frees`, which exits with an error unless `p` is the start of an allocation of values of `size` bytes that hasn't been freed yet
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn _bril_free(p: *mut u8, size: i64) {
+    let addr = p as usize;
+    let live = unsafe { &*core::ptr::addr_of!(LIVE) };
+    if (0..live.len).any(|i| live.get(i).0 == addr) {
+        _bril_mem_unregister(p);
+        unsafe { free(p) };
+        return;
+    }
+    match allocation_of(addr) {
+        Some((start, _)) => {
+            let index = (addr as i64 - start as i64) / size;
+            eprintln!(
+                "error: free of a pointer to index {} of an allocation instead of its start",
+                index
+            );
+        }
+        None => eprintln!("error: double free"),
+    }
+    unsafe { exit(2) }
+}
+
+// Exits with an error when the allocations haven't all been freed by the time `main` returns, for `--check-frees`
+#[no_mangle]
+pub extern "C" fn _bril_check_leaks() {
+    let live = unsafe { &*core::ptr::addr_of!(LIVE) };
+    if live.len > 0 {
+        let allocations = if live.len == 1 {
+            "allocation has"
+        } else {
+            "allocations have"
+        };
+        eprintln!(
+            "error: {} {} not been freed by the end of execution",
+            live.len, allocations
+        );
+        unsafe { exit(2) }
+    }
+}
+
+// Records which allocation the result `q` of a `ptradd` on `p` belongs to when it is outside of it, so that moving it back in later still counts as the same allocation
+#[no_mangle]
+pub extern "C" fn _bril_mem_ptradd(p: *const u8, q: *const u8) {
+    let Some((start, bytes)) = allocation_of(p as usize) else {
+        return;
+    };
+    let addr = q as usize;
+    let escaped = unsafe { &mut *core::ptr::addr_of_mut!(ESCAPED) };
+    if let Some(i) = (0..escaped.len).find(|&i| escaped.get(i).0 == addr) {
+        escaped.swap_remove(i);
+    }
+    if addr < start || addr > start + bytes {
+        escaped.push((addr, start));
+    }
+}
+
+// Exits with an error unless the `count` values of `size` bytes starting at `p` are all inside of the allocation that `p` came from
+#[no_mangle]
+pub extern "C" fn _bril_mem_check(p: *const u8, size: i64, count: i64) {
+    let Some((start, bytes)) = allocation_of(p as usize) else {
+        eprintln!("error: out of bounds access to memory that isn't allocated");
+        unsafe { exit(2) }
+    };
+    let offset = (p as i64 - start as i64) / size;
+    let len = bytes as i64 / size;
+    if offset < 0 || offset + count > len {
+        // The first index that is out of bounds, which is past the end when `copy` or `fill` starts inside
+        let index = if offset < 0 { offset } else { offset.max(len) };
+        eprintln!(
+            "error: out of bounds access at index {} of an allocation of {} entries",
+            index, len
+        );
+        unsafe { exit(2) }
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn _bril_print_str(s: *const c_char) {
+    let s = unsafe { CStr::from_ptr(s) }.to_str().unwrap();
+    print!("{}", s);
+}
+
 #[no_mangle]
 pub extern "C" fn _bril_print_sep() {
     print!(" ");
@@ -72,6 +298,42 @@ pub unsafe extern "C" fn _bril_parse_float(arg: *const c_char) -> f64 {
     r_str.parse::<f64>().unwrap()
 }
 
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn _bril_parse_char(arg: *const c_char) -> u32 {
+    let c_str = unsafe { CStr::from_ptr(arg) };
+    let r_str = c_str.to_str().unwrap();
+    r_str.chars().next().unwrap().into()
+}
+
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn _bril_abort(func: *const c_char) -> ! {
+    let name = unsafe { CStr::from_ptr(func) }.to_str().unwrap();
+    eprintln!("error: reached abort in @{}", name);
+    unsafe { exit(3) }
+}
+
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn _bril_error(message: *const c_char) -> ! {
+    let message = unsafe { CStr::from_ptr(message) }.to_str().unwrap();
+    eprintln!("error: {}", message);
+    unsafe { exit(2) }
+}
+
+#[no_mangle]
+pub extern "C" fn _bril_alloc_error(count: i64) -> ! {
+    eprintln!("error: cannot allocate {} entries", count);
+    unsafe { exit(2) }
+}
+
+#[no_mangle]
+pub extern "C" fn _bril_nested_speculation() -> ! {
+    eprintln!("error: nested speculation is not supported by brillvm");
+    unsafe { exit(2) }
+}
+
 #[cfg(not(test))]
 #[panic_handler]
 fn my_panic(_info: &core::panic::PanicInfo) -> ! {
@@ -1,14 +1,21 @@
-use crate::llvm::create_module_from_program;
+use crate::llvm::{
+    compile_function_to_object, create_module_from_program, dump_function_ir, to_llvm_type_name,
+    verify_calling_conventions, write_object_file, BrilError, CodegenOptions,
+};
 use bril_rs::load_program_from_read;
+use bril_rs::repro::{Outcome, Repro};
 use clap::Parser;
 use inkwell::{
     context::Context,
     module::Module,
     targets::{InitializationConfig, Target},
 };
-use std::io::Read;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
 
 #[derive(Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)]
 #[command(about, version, author)] // keeps the cli synced with Cargo.toml
 #[command(allow_hyphen_values(true))] // allows for negative numbers
 pub struct Cli {
@@ -27,12 +34,64 @@ pub struct Cli {
     #[arg(short, long, action)]
     pub interpreter: bool,
 
+    /// Use the fastcc calling convention for every function except main
+    #[arg(long, action)]
+    pub fastcc: bool,
+
+    /// Exit with an error when add, sub, or mul overflows instead of wrapping around
+    #[arg(long, action)]
+    pub trap_overflow: bool,
+
+    /// Don't check for division by zero, for measuring performance. Dividing by zero is then undefined behavior
+    #[arg(long, action)]
+    pub unchecked_division: bool,
+
+    /// Exit with an error when load, store, copy, or fill accesses memory outside of the allocation that the pointer came from
+    #[arg(long, action, conflicts_with = "library")]
+    pub check_memory: bool,
+
+    /// Exit with an error on a double free, on freeing a pointer into the middle of an allocation, or when main returns without freeing everything
+    #[arg(long, action, conflicts_with = "library")]
+    pub check_frees: bool,
+
+    /// Keep every alloc on the heap instead of putting the ones that never leave their function on the stack
+    #[arg(long, action)]
+    pub no_stack_promotion: bool,
+
+    /// Guarantee that a function calling itself in tail position reuses its stack frame, with LLVM's musttail
+    #[arg(long, action)]
+    pub strict_tail_calls: bool,
+
+    /// Output the LLVM IR of only this Bril function instead of the whole module
+    #[arg(long, action, value_name = "FUNCTION")]
+    pub dump_function: Option<String>,
+
+    /// Compile the program as a library to link with C code, without a C `main` entry point and with every Bril function keeping its name
+    #[arg(long, action, conflicts_with_all = ["interpreter", "fastcc"])]
+    pub library: bool,
+
+    /// Print the LLVM signature of each Bril function to stderr
+    #[arg(short, long, action)]
+    pub verbose: bool,
+
+    /// Compile each Bril function and the runtime library to their own object files in this directory
+    #[arg(long, action, value_name = "DIR")]
+    pub split_objects: Option<String>,
+
+    /// After running, write the program, its arguments and its output to this file so that the run can be replayed
+    #[arg(long, action, value_name = "FILE")]
+    pub record_repro: Option<String>,
+
+    /// Run a file written by --record-repro and report whether the output still matches
+    #[arg(long, action, value_name = "FILE")]
+    pub replay: Option<String>,
+
     /// Arguments for the main function
     #[arg(action)]
     pub args: Vec<String>,
 }
 
-pub fn run(args: &Cli) -> String {
+fn read_source(args: &Cli) -> String {
     let mut src = String::new();
     if let Some(f) = &args.file {
         let path = std::fs::canonicalize(f).unwrap();
@@ -43,17 +102,56 @@ pub fn run(args: &Cli) -> String {
     } else {
         std::io::stdin().read_to_string(&mut src).unwrap();
     };
+    src
+}
+
+/// Compiles the program like the command line asks, returning what brillvm should print
+/// # Errors
+/// If the program can't be compiled
+pub fn run(args: &Cli) -> Result<String, BrilError> {
+    let src = read_source(args);
     let prog = load_program_from_read(src.as_bytes());
 
+    if args.verbose {
+        for f in &prog.functions {
+            let params: Vec<_> = f
+                .args
+                .iter()
+                .map(|a| format!("{} %{}", to_llvm_type_name(&a.arg_type), a.name))
+                .collect();
+            let ret = f.return_type.as_ref().map_or("void", to_llvm_type_name);
+            eprintln!("{ret} @{}({})", f.name, params.join(", "));
+        }
+    }
+
     let context = Context::create();
     let runtime_path = args.runtime.as_ref().map_or("rt.bc", |f| f);
+
+    if let Some(dir) = &args.split_objects {
+        split_objects(&context, &prog, runtime_path, Path::new(dir))?;
+        return Ok(String::new());
+    }
+
     // create a module from the runtime library for functions like printing/parsing
     let runtime_module = Module::parse_bitcode_from_path(runtime_path, &context).unwrap();
-    let llvm_prog = create_module_from_program(&context, &prog, runtime_module);
+    let options = CodegenOptions {
+        fastcc: args.fastcc,
+        trap_overflow: args.trap_overflow,
+        unchecked_division: args.unchecked_division,
+        check_memory: args.check_memory,
+        check_frees: args.check_frees,
+        no_stack_promotion: args.no_stack_promotion,
+        strict_tail_calls: args.strict_tail_calls,
+    };
+    let llvm_prog =
+        create_module_from_program(&context, &prog, runtime_module, options, args.library)?;
 
     //println!("{}", prog);
     //llvm_prog.print_to_file("tmp.ll").unwrap();
-    llvm_prog.verify().unwrap();
+    llvm_prog
+        .verify()
+        .map_err(|e| BrilError::Verify(e.to_string()))?;
+    verify_calling_conventions(&llvm_prog).map_err(BrilError::Verify)?;
 
     if args.interpreter {
         Target::initialize_native(&InitializationConfig::default())
@@ -68,8 +166,150 @@ pub fn run(args: &Cli) -> String {
         unsafe {
             engine.run_function_as_main(llvm_prog.get_function("main").unwrap(), &args);
         }
-        String::new()
+        Ok(String::new())
+    } else if let Some(name) = &args.dump_function {
+        let llvm_name = if name == "main" && !args.library {
+            "_main"
+        } else {
+            name
+        };
+        dump_function_ir(&llvm_prog, llvm_name).ok_or_else(|| BrilError::UndefinedFunction {
+            name: name.clone(),
+            suggestion: None,
+        })
     } else {
-        llvm_prog.to_string()
+        Ok(llvm_prog.to_string())
+    }
+}
+
+// Writes <fn>.o for each function of `prog` and rt.o for the runtime into `dir`, which can then be linked with `cc dir/*.o`
+fn split_objects(
+    context: &Context,
+    prog: &bril_rs::Program,
+    runtime_path: &str,
+    dir: &Path,
+) -> Result<(), BrilError> {
+    std::fs::create_dir_all(dir).unwrap();
+    for f in &prog.functions {
+        let runtime_module = Module::parse_bitcode_from_path(runtime_path, context).unwrap();
+        let output = dir.join(format!("{}.o", f.name));
+        compile_function_to_object(context, prog, &f.name, runtime_module, &output)?;
+    }
+    let runtime_module = Module::parse_bitcode_from_path(runtime_path, context).unwrap();
+    write_object_file(&runtime_module, &dir.join("rt.o"))
+}
+
+impl Cli {
+    // The options that change what brillvm outputs, in the form that they are recorded by --record-repro
+    fn repro_options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if self.interpreter {
+            options.push("--interpreter".to_string());
+        }
+        if self.fastcc {
+            options.push("--fastcc".to_string());
+        }
+        if self.trap_overflow {
+            options.push("--trap-overflow".to_string());
+        }
+        if self.unchecked_division {
+            options.push("--unchecked-division".to_string());
+        }
+        if self.check_memory {
+            options.push("--check-memory".to_string());
+        }
+        if self.check_frees {
+            options.push("--check-frees".to_string());
+        }
+        if self.no_stack_promotion {
+            options.push("--no-stack-promotion".to_string());
+        }
+        if self.strict_tail_calls {
+            options.push("--strict-tail-calls".to_string());
+        }
+        options
+    }
+}
+
+// The JIT prints straight to this process's stdout, so brillvm runs another copy of itself to capture what a program prints
+fn run_in_subprocess(
+    src: &str,
+    runtime: Option<&String>,
+    options: &[String],
+    args: &[String],
+) -> Outcome {
+    let mut command = Command::new(std::env::current_exe().unwrap());
+    if let Some(runtime) = runtime {
+        command.arg("--runtime").arg(runtime);
+    }
+    let mut child = command
+        .args(options)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(src.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    Outcome {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        exit_code: output.status.code().unwrap_or(-1),
+    }
+}
+
+/// Compiles and runs the program like normal while keeping a copy of what it prints, then writes everything to a .brilrepro file at `path`
+/// # Panics
+/// If the program is not valid Bril JSON or the file can't be written
+pub fn record(args: &Cli, path: &str) {
+    let src = read_source(args);
+    let options = args.repro_options();
+    let expected = run_in_subprocess(&src, args.runtime.as_ref(), &options, &args.args);
+    print!("{}", expected.stdout);
+
+    let repro = Repro {
+        program: serde_json::from_str(&src).unwrap(),
+        tool: "brillvm".to_string(),
+        options,
+        args: args.args.clone(),
+        stdin: None,
+        seed: None,
+        expected,
+    };
+    repro.write(std::fs::File::create(path).unwrap()).unwrap();
+}
+
+/// Runs the .brilrepro file at `path` and prints whether it did the same thing as when it was recorded. Returns the exit code for brillvm
+/// # Panics
+/// If the file can't be read
+#[must_use]
+pub fn replay(args: &Cli, path: &str) -> i32 {
+    let repro = Repro::from_read(std::fs::File::open(path).unwrap()).unwrap();
+    if repro.tool != "brillvm" {
+        eprintln!(
+            "error: {path} was recorded with {}, not brillvm",
+            repro.tool
+        );
+        return 2;
+    }
+    if repro.stdin.is_some() || repro.seed.is_some() {
+        eprintln!("error: {path} needs stdin or a random seed, which brillvm does not support");
+        return 2;
+    }
+    let src = serde_json::to_string(&repro.program).unwrap();
+    let actual = run_in_subprocess(&src, args.runtime.as_ref(), &repro.options, &repro.args);
+    match repro.check(&actual) {
+        Ok(()) => {
+            println!("pass");
+            0
+        }
+        Err(mismatch) => {
+            print!("fail\n{mismatch}");
+            1
+        }
     }
 }
@@ -1,19 +1,28 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
 
 use inkwell::{
     basic_block::BasicBlock,
-    builder::Builder,
+    builder::{Builder, BuilderError},
     context::Context,
-    module::Module,
+    intrinsics::Intrinsic,
+    module::{Linkage, Module},
+    targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine},
     types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, FunctionType},
     values::{
-        AsValueRef, BasicValue, BasicValueEnum, FloatValue, FunctionValue, IntValue, PointerValue,
+        AnyValue, AsValueRef, BasicMetadataValueEnum, BasicValue, BasicValueEnum, CallSiteValue,
+        FloatValue, FunctionValue, IntValue, LLVMTailCallKind, PhiValue, PointerValue,
     },
-    AddressSpace, FloatPredicate, IntPredicate,
+    AddressSpace, FloatPredicate, IntPredicate, OptimizationLevel,
 };
 
 use bril_rs::{
-    Argument, Code, ConstOps, EffectOps, Function, Instruction, Literal, Program, Type, ValueOps,
+    align_of_type,
+    analysis::{check_use_before_def, ControlFlowGraph, UseBeforeDefError},
+    check::{type_check_function, validate_arity, ArityError, TypeError},
+    passes::ssa::UNDEFINED,
+    size_of_type, Argument, Code, ConstOps, EffectOps, ExternFunc, Function, GlobalVar,
+    Instruction, Literal, Program, Type, ValueOps, VECTOR_WIDTH,
 };
 
 /// A helper function for performing operations over LLVM types
@@ -25,7 +34,60 @@ where
         Type::Int => fn_map(context.i64_type().into()),
         Type::Bool => fn_map(context.bool_type().into()),
         Type::Float => fn_map(context.f64_type().into()),
+        // A Unicode scalar value
+        Type::Char => fn_map(context.i32_type().into()),
         Type::Pointer(_) => fn_map(context.ptr_type(AddressSpace::default()).into()),
+        // `check_program` only lets through vectors of `int` and `float`
+        #[allow(clippy::cast_possible_truncation)]
+        Type::Vector(ty) => fn_map(if **ty == Type::Float {
+            context.f64_type().vec_type(VECTOR_WIDTH as u32).into()
+        } else {
+            context.i64_type().vec_type(VECTOR_WIDTH as u32).into()
+        }),
+    }
+}
+
+// Like `llvm_type_map`, but for values stored in memory through a Bril pointer. Bools take up a whole `i8` there, so that the size of an allocation always agrees with the stride of `ptradd`
+fn llvm_memory_type_map<'ctx, A, F>(context: &'ctx Context, ty: &Type, mut fn_map: F) -> A
+where
+    F: for<'a> FnMut(BasicTypeEnum<'ctx>) -> A,
+{
+    match ty {
+        Type::Bool => fn_map(context.i8_type().into()),
+        _ => llvm_type_map(context, ty, fn_map),
+    }
+}
+
+/// The name of the LLVM type that brillvm uses for a Bril type, like `i64` for `int`. All pointers are the opaque `ptr`
+#[must_use]
+pub const fn to_llvm_type_name(ty: &Type) -> &'static str {
+    match ty {
+        Type::Int => "i64",
+        Type::Bool => "i1",
+        Type::Float => "double",
+        Type::Char => "i32",
+        Type::Pointer(_) => "ptr",
+        Type::Vector(ty) => match **ty {
+            Type::Float => "<4 x double>",
+            _ => "<4 x i64>",
+        },
+    }
+}
+
+/// A C declaration of the variable `var_name` with the C type matching a Bril type, like `int64_t x` for `int` or `double *p` for `ptr<float>`. Vectors use the GCC `vector_size` attribute
+#[must_use]
+pub fn to_c_declaration(ty: &Type, var_name: &str) -> String {
+    match ty {
+        Type::Int => format!("int64_t {var_name}"),
+        Type::Bool => format!("bool {var_name}"),
+        Type::Float => format!("double {var_name}"),
+        Type::Char => format!("uint32_t {var_name}"),
+        Type::Pointer(ty) => to_c_declaration(ty, &format!("*{var_name}")),
+        Type::Vector(elem) => format!(
+            "{} __attribute__((vector_size({})))",
+            to_c_declaration(elem, var_name),
+            size_of_type(ty)
+        ),
     }
 }
 
@@ -36,6 +98,13 @@ fn unwrap_bril_ptrtype(ty: &Type) -> &Type {
     }
 }
 
+fn unwrap_bril_vectortype(ty: &Type) -> &Type {
+    match ty {
+        Type::Vector(ty) => ty,
+        _ => unreachable!(),
+    }
+}
+
 /// Converts a Bril function signature into an LLVM function type
 fn build_functiontype<'a>(
     context: &'a Context,
@@ -53,17 +122,44 @@ fn build_functiontype<'a>(
     }
 }
 
+// Variables of type `bool` are stored as an `i8`, like they are behind a Bril pointer, and are truncated to an `i1` when they're loaded
 fn build_load<'a>(
     context: &'a Context,
     builder: &'a Builder,
     ptr: &WrappedPointer<'a>,
     name: &str,
-) -> BasicValueEnum<'a> {
-    llvm_type_map(context, &ptr.ty, |pointee_ty| {
-        builder.build_load(pointee_ty, ptr.ptr, name).unwrap()
+) -> Result<BasicValueEnum<'a>, BrilError> {
+    let value = llvm_memory_type_map(context, &ptr.ty, |pointee_ty| {
+        builder.build_load(pointee_ty, ptr.ptr, name)
+    })?;
+    Ok(if ptr.ty == Type::Bool {
+        builder
+            .build_int_truncate(value.into_int_value(), context.bool_type(), name)?
+            .into()
+    } else {
+        value
     })
 }
 
+// Stores `value` into the stack slot of a variable, extending an `i1` to the `i8` that `build_load` expects for a `bool`
+fn build_store<'a>(
+    context: &'a Context,
+    builder: &'a Builder,
+    ptr: &WrappedPointer<'a>,
+    value: impl Into<BasicValueEnum<'a>>,
+) -> Result<(), BrilError> {
+    let value = value.into();
+    let value = if ptr.ty == Type::Bool {
+        builder
+            .build_int_z_extend(value.into_int_value(), context.i8_type(), "")?
+            .into()
+    } else {
+        value
+    };
+    builder.build_store(ptr.ptr, value)?;
+    Ok(())
+}
+
 // Type information is needed for cases like Bool which is modelled as an int and is as far as I can tell indistinguishable.
 #[derive(Debug, Clone)]
 struct WrappedPointer<'a> {
@@ -72,23 +168,37 @@ struct WrappedPointer<'a> {
 }
 
 impl<'a> WrappedPointer<'a> {
-    fn new(builder: &'a Builder, context: &'a Context, name: &str, ty: &Type) -> Self {
-        Self {
+    fn new(
+        builder: &'a Builder,
+        context: &'a Context,
+        name: &str,
+        ty: &Type,
+    ) -> Result<Self, BrilError> {
+        Ok(Self {
             ty: ty.clone(),
-            ptr: llvm_type_map(context, ty, |ty| builder.build_alloca(ty, name).unwrap()),
-        }
+            ptr: llvm_memory_type_map(context, ty, |ty| builder.build_alloca(ty, name))?,
+        })
     }
 }
 
 #[derive(Default)]
 struct Heap<'a, 'b> {
+    // The Bril function that the variables are in, for reporting ones that are missing
+    func: &'b str,
     // Map variable names in Bril to their type and location on the stack.
     map: HashMap<&'b String, WrappedPointer<'a>>,
+    // A second stack slot for each variable, where `speculate` saves its value so that a failed `guard` can roll it back.
+    snapshot: HashMap<&'b String, WrappedPointer<'a>>,
+    // Whether the function is currently speculating, since there is only room for one snapshot
+    speculating: Option<PointerValue<'a>>,
 }
 
 impl<'a, 'b> Heap<'a, 'b> {
-    fn new() -> Self {
-        Self::default()
+    fn new(func: &'b str) -> Self {
+        Self {
+            func,
+            ..Self::default()
+        }
     }
 
     fn add(
@@ -97,15 +207,78 @@ impl<'a, 'b> Heap<'a, 'b> {
         context: &'a Context,
         name: &'b String,
         ty: &Type,
-    ) -> WrappedPointer<'a> {
+    ) -> Result<WrappedPointer<'a>, BrilError> {
+        if let Some(ptr) = self.map.get(name) {
+            return Ok(ptr.clone());
+        }
+        let ptr = WrappedPointer::new(builder, context, name, ty)?;
+        self.map.insert(name, ptr.clone());
+        Ok(ptr)
+    }
+
+    // The stack slot of a variable, which is missing when nothing in the function assigns to it
+    fn get(&self, name: &String) -> Result<WrappedPointer<'a>, BrilError> {
         self.map
-            .entry(name)
-            .or_insert_with(|| WrappedPointer::new(builder, context, name, ty))
-            .clone()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| BrilError::UndefinedVariable {
+                name: name.clone(),
+                func: self.func.to_string(),
+            })
+    }
+
+    // Allocates the snapshot slots, which must happen in the entry block after every variable has been added
+    fn add_snapshot(
+        &mut self,
+        builder: &'a Builder,
+        context: &'a Context,
+    ) -> Result<(), BrilError> {
+        for (name, ptr) in &self.map {
+            self.snapshot.insert(
+                name,
+                WrappedPointer::new(builder, context, &format!("{name}.spec"), &ptr.ty)?,
+            );
+        }
+        let speculating = builder.build_alloca(context.bool_type(), "speculating")?;
+        builder.build_store(speculating, context.bool_type().const_zero())?;
+        self.speculating = Some(speculating);
+        Ok(())
+    }
+
+    fn set_speculating(
+        &self,
+        builder: &'a Builder,
+        context: &'a Context,
+        speculating: bool,
+    ) -> Result<(), BrilError> {
+        builder.build_store(
+            self.speculating.unwrap(),
+            context.bool_type().const_int(speculating.into(), false),
+        )?;
+        Ok(())
     }
 
-    fn get(&self, name: &String) -> WrappedPointer<'a> {
-        self.map.get(name).unwrap().clone()
+    // Copies every variable into its snapshot slot, or back out of it when `restore` is set
+    fn copy_snapshot(
+        &self,
+        builder: &'a Builder,
+        context: &'a Context,
+        fresh: &mut Fresh,
+        restore: bool,
+    ) -> Result<(), BrilError> {
+        // Sorted so that the generated code doesn't depend on the order of the HashMap
+        let mut names: Vec<_> = self.snapshot.keys().collect();
+        names.sort();
+        for name in names {
+            let (from, to) = if restore {
+                (&self.snapshot[name], &self.map[name])
+            } else {
+                (&self.map[name], &self.snapshot[name])
+            };
+            let v = build_load(context, builder, from, &fresh.fresh_var())?;
+            build_store(context, builder, to, v)?;
+        }
+        Ok(())
     }
 }
 
@@ -138,19 +311,16 @@ fn build_op<'a, 'b>(
     builder: &'a Builder,
     heap: &Heap<'a, 'b>,
     fresh: &mut Fresh,
-    op: impl Fn(Vec<BasicValueEnum<'a>>) -> BasicValueEnum<'a>,
+    op: impl Fn(Vec<BasicValueEnum<'a>>) -> Result<BasicValueEnum<'a>, BrilError>,
     args: &'b [String],
     dest: &'b String,
-) {
-    builder
-        .build_store(
-            heap.get(dest).ptr,
-            op(args
-                .iter()
-                .map(|n| build_load(context, builder, &heap.get(n), &fresh.fresh_var()))
-                .collect()),
-        )
-        .unwrap();
+) -> Result<(), BrilError> {
+    let value = op(args
+        .iter()
+        .map(|n| build_load(context, builder, &heap.get(n)?, &fresh.fresh_var()))
+        .collect::<Result<_, _>>()?)?;
+    build_store(context, builder, &heap.get(dest)?, value)?;
+    Ok(())
 }
 
 // Like `build_op` but where there is no return value
@@ -159,13 +329,121 @@ fn build_effect_op<'a, 'b>(
     builder: &'a Builder,
     heap: &Heap<'a, 'b>,
     fresh: &mut Fresh,
-    op: impl Fn(Vec<BasicValueEnum<'a>>),
+    op: impl Fn(Vec<BasicValueEnum<'a>>) -> Result<(), BrilError>,
     args: &'b [String],
-) {
+) -> Result<(), BrilError> {
     op(args
         .iter()
-        .map(|n| build_load(context, builder, &heap.get(n), &fresh.fresh_var()))
-        .collect());
+        .map(|n| build_load(context, builder, &heap.get(n)?, &fresh.fresh_var()))
+        .collect::<Result<_, _>>()?)
+}
+
+// Looks up an LLVM intrinsic by name and declares it in the module for the given overloaded types
+fn get_intrinsic<'a>(
+    module: &Module<'a>,
+    name: &str,
+    types: &[BasicTypeEnum<'a>],
+) -> Result<FunctionValue<'a>, BrilError> {
+    Intrinsic::find(name)
+        .and_then(|i| i.get_declaration(module, types))
+        .ok_or_else(|| BrilError::UnsupportedOp(format!("the LLVM intrinsic {name}")))
+}
+
+// Looks up a function of the runtime library, which is missing when `rt.bc` was built from an older runtime
+fn get_runtime_function<'a>(
+    module: &Module<'a>,
+    name: &str,
+) -> Result<FunctionValue<'a>, BrilError> {
+    module
+        .get_function(name)
+        .ok_or_else(|| BrilError::MissingRuntimeFunction(name.to_string()))
+}
+
+// Branches to a new block that calls the runtime function `error`, which never returns, with `error_args` when `condition` is true, and leaves the builder at the end of the block where the program continues otherwise
+fn build_runtime_check<'a>(
+    context: &'a Context,
+    builder: &'a Builder,
+    llvm_func: FunctionValue<'a>,
+    fresh: &mut Fresh,
+    condition: IntValue<'a>,
+    error: FunctionValue<'a>,
+    error_args: &[BasicMetadataValueEnum<'a>],
+) -> Result<(), BrilError> {
+    let error_block = context.append_basic_block(llvm_func, &fresh.fresh_label());
+    let continue_block = context.append_basic_block(llvm_func, &fresh.fresh_label());
+    builder.build_conditional_branch(condition, error_block, continue_block)?;
+
+    builder.position_at_end(error_block);
+    builder.build_call(error, error_args, "")?;
+    builder.build_unreachable()?;
+
+    builder.position_at_end(continue_block);
+    Ok(())
+}
+
+// Like `build_runtime_check` for `_bril_error`, which prints `message`
+fn build_error_check<'a>(
+    context: &'a Context,
+    module: &'a Module,
+    builder: &'a Builder,
+    llvm_func: FunctionValue<'a>,
+    fresh: &mut Fresh,
+    condition: IntValue<'a>,
+    message: &str,
+) -> Result<(), BrilError> {
+    let error = get_runtime_function(module, "_bril_error")?;
+    let message = builder.build_global_string_ptr(message, &fresh.fresh_var())?;
+    build_runtime_check(
+        context,
+        builder,
+        llvm_func,
+        fresh,
+        condition,
+        error,
+        &[message.as_pointer_value().into()],
+    )
+}
+
+// Like `build_error_check` for when the `int` variable `count` is negative, for `copy` and `fill`
+#[allow(clippy::too_many_arguments)]
+fn build_negative_count_check<'a>(
+    context: &'a Context,
+    module: &'a Module,
+    builder: &'a Builder,
+    llvm_func: FunctionValue<'a>,
+    heap: &Heap<'a, '_>,
+    fresh: &mut Fresh,
+    count: &String,
+    message: &str,
+) -> Result<(), BrilError> {
+    let count = build_load(context, builder, &heap.get(count)?, &fresh.fresh_var())?;
+    let negative = builder.build_int_compare(
+        IntPredicate::SLT,
+        count.into_int_value(),
+        context.i64_type().const_zero(),
+        &fresh.fresh_var(),
+    )?;
+    build_error_check(
+        context, module, builder, llvm_func, fresh, negative, message,
+    )
+}
+
+// Calls `_bril_mem_check` to make sure that the `count` values of type `ty` starting at `ptr` are in the allocation that `ptr` came from, for `--check-memory`
+fn build_memory_check<'a>(
+    context: &'a Context,
+    module: &'a Module,
+    builder: &'a Builder,
+    ptr: PointerValue<'a>,
+    ty: &Type,
+    count: IntValue<'a>,
+) -> Result<(), BrilError> {
+    let size = llvm_memory_type_map(context, ty, |ty| ty.size_of().unwrap());
+    builder.build_call(
+        get_runtime_function(module, "_bril_mem_check")?,
+        &[ptr.into(), size.into(), count.into()],
+        "",
+    )?;
+    Ok(())
 }
 
 // Handles the map of labels to LLVM Basicblocks and creates a new one when it doesn't exist
@@ -180,6 +458,108 @@ fn block_map_get<'a>(
         .or_insert_with(|| context.append_basic_block(llvm_func, name))
 }
 
+// The LLVM constant for `value` as a Bril value of type `ty`
+fn build_literal<'a>(context: &'a Context, ty: &Type, value: &Literal) -> BasicValueEnum<'a> {
+    #[allow(clippy::cast_precision_loss, clippy::cast_sign_loss)]
+    match (ty, value) {
+        (Type::Float, Literal::Int(i)) => context.f64_type().const_float(*i as f64).into(),
+        (_, Literal::Int(i)) => context.i64_type().const_int(*i as u64, true).into(),
+        (_, Literal::Bool(b)) => context.bool_type().const_int((*b).into(), false).into(),
+        (_, Literal::Float(f)) => context.f64_type().const_float(*f).into(),
+        (_, Literal::Char(c)) => context.i32_type().const_int(u64::from(*c), false).into(),
+        (_, Literal::Null) => context
+            .ptr_type(AddressSpace::default())
+            .const_null()
+            .into(),
+    }
+}
+
+// The LLVM name of a Bril function. `main` becomes `_main` to make room for the C `main` that parses the command line arguments, except in a library, which has no entry point
+fn llvm_function_name(name: &str, library: bool) -> &str {
+    if name == "main" && !library {
+        "_main"
+    } else {
+        name
+    }
+}
+
+// Whether the call at `index` of `instrs` is in tail position: it is followed in the same block by a `ret` of its result, or, for a call without a result, by a `ret` of nothing or the end of a function that doesn't return anything
+fn is_tail_call(instrs: &[Code], index: usize, returns_value: bool) -> bool {
+    let next = instrs.get(index + 1);
+    match &instrs[index] {
+        Code::Instruction(Instruction::Value {
+            op: ValueOps::Call,
+            dest,
+            ..
+        }) => matches!(next, Some(Code::Instruction(Instruction::Effect {
+            op: EffectOps::Return,
+            args,
+            ..
+        })) if args.len() == 1 && args[0] == *dest),
+        Code::Instruction(Instruction::Effect {
+            op: EffectOps::Call,
+            ..
+        }) => match next {
+            Some(Code::Instruction(Instruction::Effect {
+                op: EffectOps::Return,
+                args,
+                ..
+            })) => args.is_empty(),
+            None => !returns_value,
+            Some(_) => false,
+        },
+        _ => false,
+    }
+}
+
+// Builds a call that `is_tail_call` found, marked so that LLVM can reuse the caller's stack frame for it. A call with a result returns it right away, since `musttail` has to come just before a `ret` of its result, so the `ret` after the call must be skipped. A call without one is already followed by a `ret void`
+#[allow(clippy::too_many_arguments)]
+fn build_tail_call<'a, 'b>(
+    instr: &'b Instruction,
+    context: &'a Context,
+    module: &'a Module,
+    builder: &'a Builder,
+    heap: &Heap<'a, 'b>,
+    llvm_func: FunctionValue<'a>,
+    fresh: &mut Fresh,
+    library: bool,
+    options: CodegenOptions,
+) -> Result<(), BrilError> {
+    let (Instruction::Value { args, funcs, .. } | Instruction::Effect { args, funcs, .. }) = instr
+    else {
+        unreachable!()
+    };
+    let function = module
+        .get_function(llvm_function_name(&funcs[0], library))
+        .ok_or_else(|| BrilError::UndefinedFunction {
+            name: funcs[0].clone(),
+            suggestion: None,
+        })?;
+    let args = args
+        .iter()
+        .map(|n| Ok(build_load(context, builder, &heap.get(n)?, &fresh.fresh_var())?.into()))
+        .collect::<Result<Vec<_>, BrilError>>()?;
+    let call = builder.build_call(function, &args, &fresh.fresh_var())?;
+    call.set_call_convention(function.get_call_conventions());
+    // Only a call of the function itself is sure to have the same signature and calling convention as its caller, which `musttail` needs
+    let kind = if options.strict_tail_calls && function == llvm_func {
+        LLVMTailCallKind::LLVMTailCallKindMustTail
+    } else {
+        LLVMTailCallKind::LLVMTailCallKindTail
+    };
+    call.set_tail_call_kind(kind);
+    if let Instruction::Value { .. } = instr {
+        let value = call.try_as_basic_value().left().ok_or_else(|| {
+            BrilError::UnsupportedOp(format!(
+                "using the result of @{}, which doesn't return anything",
+                funcs[0]
+            ))
+        })?;
+        builder.build_return(Some(&value))?;
+    }
+    Ok(())
+}
+
 // The workhorse of converting a Bril Instruction to an LLVM Instruction
 #[allow(clippy::too_many_arguments)]
 fn build_instruction<'a, 'b>(
@@ -191,7 +571,10 @@ fn build_instruction<'a, 'b>(
     block_map: &mut HashMap<String, BasicBlock<'a>>,
     llvm_func: FunctionValue<'a>,
     fresh: &mut Fresh,
-) {
+    library: bool,
+    track_allocations: bool,
+    options: CodegenOptions,
+) -> Result<(), BrilError> {
     match i {
         // Special case where Bril casts integers to floats
         Instruction::Constant {
@@ -201,12 +584,12 @@ fn build_instruction<'a, 'b>(
             value: Literal::Int(i),
         } => {
             #[allow(clippy::cast_precision_loss)]
-            builder
-                .build_store(
-                    heap.get(dest).ptr,
-                    context.f64_type().const_float(*i as f64),
-                )
-                .unwrap();
+            build_store(
+                context,
+                builder,
+                &heap.get(dest)?,
+                context.f64_type().const_float(*i as f64),
+            )?;
         }
         Instruction::Constant {
             dest,
@@ -215,12 +598,12 @@ fn build_instruction<'a, 'b>(
             value: Literal::Int(i),
         } => {
             #[allow(clippy::cast_sign_loss)]
-            builder
-                .build_store(
-                    heap.get(dest).ptr,
-                    context.i64_type().const_int(*i as u64, true),
-                )
-                .unwrap();
+            build_store(
+                context,
+                builder,
+                &heap.get(dest)?,
+                context.i64_type().const_int(*i as u64, true),
+            )?;
         }
         Instruction::Constant {
             dest,
@@ -228,12 +611,12 @@ fn build_instruction<'a, 'b>(
             const_type: _,
             value: Literal::Bool(b),
         } => {
-            builder
-                .build_store(
-                    heap.get(dest).ptr,
-                    context.bool_type().const_int((*b).into(), false),
-                )
-                .unwrap();
+            build_store(
+                context,
+                builder,
+                &heap.get(dest)?,
+                context.bool_type().const_int((*b).into(), false),
+            )?;
         }
         Instruction::Constant {
             dest,
@@ -241,9 +624,74 @@ fn build_instruction<'a, 'b>(
             const_type: _,
             value: Literal::Float(f),
         } => {
-            builder
-                .build_store(heap.get(dest).ptr, context.f64_type().const_float(*f))
-                .unwrap();
+            build_store(
+                context,
+                builder,
+                &heap.get(dest)?,
+                context.f64_type().const_float(*f),
+            )?;
+        }
+        Instruction::Constant {
+            dest,
+            op: ConstOps::Const,
+            const_type: _,
+            value: Literal::Char(c),
+        } => {
+            build_store(
+                context,
+                builder,
+                &heap.get(dest)?,
+                context.i32_type().const_int(u64::from(*c), false),
+            )?;
+        }
+        Instruction::Constant {
+            dest,
+            op: ConstOps::Const,
+            const_type: _,
+            value: Literal::Null,
+        } => {
+            build_store(
+                context,
+                builder,
+                &heap.get(dest)?,
+                context.ptr_type(AddressSpace::default()).const_null(),
+            )?;
+        }
+        // The intrinsics return the wrapped result along with whether it overflowed, which branches to `_bril_error`
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: op @ (ValueOps::Add | ValueOps::Sub | ValueOps::Mul),
+            op_type: _,
+        } if options.trap_overflow => {
+            let name = match op {
+                ValueOps::Add => "llvm.sadd.with.overflow.i64",
+                ValueOps::Sub => "llvm.ssub.with.overflow.i64",
+                ValueOps::Mul => "llvm.smul.with.overflow.i64",
+                _ => unreachable!(),
+            };
+            let intrinsic = get_intrinsic(module, name, &[module.get_context().i64_type().into()])?;
+            let lhs = build_load(context, builder, &heap.get(&args[0])?, &fresh.fresh_var())?;
+            let rhs = build_load(context, builder, &heap.get(&args[1])?, &fresh.fresh_var())?;
+            let pair = builder
+                .build_call(intrinsic, &[lhs.into(), rhs.into()], &fresh.fresh_var())?
+                .try_as_basic_value()
+                .unwrap_left()
+                .into_struct_value();
+            let value = builder.build_extract_value(pair, 0, &fresh.fresh_var())?;
+            let overflowed = builder.build_extract_value(pair, 1, &fresh.fresh_var())?;
+            build_error_check(
+                context,
+                module,
+                builder,
+                llvm_func,
+                fresh,
+                overflowed.into_int_value(),
+                "integer overflow",
+            )?;
+            build_store(context, builder, &heap.get(dest)?, value)?;
         }
         Instruction::Value {
             args,
@@ -260,18 +708,17 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
+                    Ok(builder
                         .build_int_add::<IntValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
@@ -288,18 +735,17 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
+                    Ok(builder
                         .build_int_sub::<IntValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
@@ -316,18 +762,84 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
+                    Ok(builder
                         .build_int_mul::<IntValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
+        }
+        // Dividing by zero exits with the same error as `brili`, instead of being undefined behavior that usually crashes with SIGFPE
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: op @ (ValueOps::Div | ValueOps::Rem | ValueOps::Udiv | ValueOps::Urem),
+            op_type: _,
+        } if !options.unchecked_division => {
+            let i64_type = context.i64_type();
+            let lhs = build_load(context, builder, &heap.get(&args[0])?, &fresh.fresh_var())?
+                .into_int_value();
+            let rhs = build_load(context, builder, &heap.get(&args[1])?, &fresh.fresh_var())?
+                .into_int_value();
+            let is_zero = builder.build_int_compare(
+                IntPredicate::EQ,
+                rhs,
+                i64_type.const_zero(),
+                &fresh.fresh_var(),
+            )?;
+            build_error_check(
+                context,
+                module,
+                builder,
+                llvm_func,
+                fresh,
+                is_zero,
+                "division by zero",
+            )?;
+            let ret_name = fresh.fresh_var();
+            let value = match op {
+                ValueOps::Udiv => builder.build_int_unsigned_div(lhs, rhs, &ret_name)?,
+                ValueOps::Urem => builder.build_int_unsigned_rem(lhs, rhs, &ret_name)?,
+                _ => {
+                    // `i64::MIN / -1` overflows, which x86 traps on, so -1 divides like 1 and the quotient is negated, which wraps around like `brilirs`. The remainder is 0 either way
+                    let is_neg_one = builder.build_int_compare(
+                        IntPredicate::EQ,
+                        rhs,
+                        i64_type.const_all_ones(),
+                        &fresh.fresh_var(),
+                    )?;
+                    let divisor = builder
+                        .build_select(
+                            is_neg_one,
+                            i64_type.const_int(1, false),
+                            rhs,
+                            &fresh.fresh_var(),
+                        )?
+                        .into_int_value();
+                    if *op == ValueOps::Div {
+                        let quotient =
+                            builder.build_int_signed_div(lhs, divisor, &fresh.fresh_var())?;
+                        let negated = builder.build_int_sub(
+                            i64_type.const_zero(),
+                            lhs,
+                            &fresh.fresh_var(),
+                        )?;
+                        builder
+                            .build_select(is_neg_one, negated, quotient, &ret_name)?
+                            .into_int_value()
+                    } else {
+                        builder.build_int_signed_rem(lhs, divisor, &ret_name)?
+                    }
+                }
+            };
+            build_store(context, builder, &heap.get(dest)?, value)?;
         }
         Instruction::Value {
             args,
@@ -344,25 +856,24 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
+                    Ok(builder
                         .build_int_signed_div::<IntValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Eq,
+            op: ValueOps::Rem,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -372,26 +883,24 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_int_compare::<IntValue>(
-                            IntPredicate::EQ,
+                    Ok(builder
+                        .build_int_signed_rem::<IntValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Lt,
+            op: ValueOps::Udiv,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -401,26 +910,24 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_int_compare::<IntValue>(
-                            IntPredicate::SLT,
+                    Ok(builder
+                        .build_int_unsigned_div::<IntValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Gt,
+            op: ValueOps::Urem,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -430,28 +937,32 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_int_compare::<IntValue>(
-                            IntPredicate::SGT,
+                    Ok(builder
+                        .build_int_unsigned_rem::<IntValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Le,
+            op: op @ (ValueOps::Ult | ValueOps::Ugt | ValueOps::Ule | ValueOps::Uge),
             op_type: _,
         } => {
+            let predicate = match op {
+                ValueOps::Ult => IntPredicate::ULT,
+                ValueOps::Ugt => IntPredicate::UGT,
+                ValueOps::Ule => IntPredicate::ULE,
+                _ => IntPredicate::UGE,
+            };
             let ret_name = fresh.fresh_var();
             build_op(
                 context,
@@ -459,28 +970,34 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
+                    Ok(builder
                         .build_int_compare::<IntValue>(
-                            IntPredicate::SLE,
+                            predicate,
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Ge,
+            op: op @ (ValueOps::Ceq | ValueOps::Clt | ValueOps::Cgt | ValueOps::Cle | ValueOps::Cge),
             op_type: _,
         } => {
+            let predicate = match op {
+                ValueOps::Ceq => IntPredicate::EQ,
+                ValueOps::Clt => IntPredicate::ULT,
+                ValueOps::Cgt => IntPredicate::UGT,
+                ValueOps::Cle => IntPredicate::ULE,
+                _ => IntPredicate::UGE,
+            };
             let ret_name = fresh.fresh_var();
             build_op(
                 context,
@@ -488,26 +1005,25 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
+                    Ok(builder
                         .build_int_compare::<IntValue>(
-                            IntPredicate::SGE,
+                            predicate,
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Not,
+            op: ValueOps::Char2int,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -517,21 +1033,25 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_not::<IntValue>(v[0].try_into().unwrap(), &ret_name)
-                        .unwrap()
-                        .into()
+                    Ok(builder
+                        .build_int_z_extend::<IntValue>(
+                            v[0].try_into().unwrap(),
+                            context.i64_type(),
+                            &ret_name,
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
+        // Ints that aren't Unicode scalar values aren't checked for
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::And,
+            op: ValueOps::Int2char,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -541,25 +1061,24 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_and::<IntValue>(
+                    Ok(builder
+                        .build_int_truncate::<IntValue>(
                             v[0].try_into().unwrap(),
-                            v[1].try_into().unwrap(),
+                            context.i32_type(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Or,
+            op: ValueOps::Eq,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -569,33 +1088,27 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_or::<IntValue>(
+                    Ok(builder
+                        .build_int_compare::<IntValue>(
+                            IntPredicate::EQ,
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
             dest,
-            funcs,
+            funcs: _,
             labels: _,
-            op: ValueOps::Call,
+            op: ValueOps::Lt,
             op_type: _,
         } => {
-            let func_name = if funcs[0] == "main" {
-                "_main"
-            } else {
-                &funcs[0]
-            };
-            let function = module.get_function(func_name).unwrap();
             let ret_name = fresh.fresh_var();
             build_op(
                 context,
@@ -603,39 +1116,53 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_call(
-                            function,
-                            v.iter()
-                                .map(|val| (*val).into())
-                                .collect::<Vec<_>>()
-                                .as_slice(),
+                    Ok(builder
+                        .build_int_compare::<IntValue>(
+                            IntPredicate::SLT,
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .try_as_basic_value()
-                        .left()
-                        .unwrap()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Id,
+            op: ValueOps::Gt,
             op_type: _,
-        } => build_op(context, builder, heap, fresh, |v| v[0], args, dest),
-
+        } => {
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_int_compare::<IntValue>(
+                            IntPredicate::SGT,
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
+                            &ret_name,
+                        )?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
+        }
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Select,
+            op: ValueOps::Le,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -645,92 +1172,76 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_select::<BasicValueEnum, IntValue>(
+                    Ok(builder
+                        .build_int_compare::<IntValue>(
+                            IntPredicate::SLE,
                             v[0].try_into().unwrap(),
-                            v[1],
-                            v[2],
+                            v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
-
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Smax,
+            op: ValueOps::Ge,
             op_type: _,
         } => {
-            let cmp_name = fresh.fresh_var();
-            let name = fresh.fresh_var();
+            let ret_name = fresh.fresh_var();
             build_op(
                 context,
                 builder,
                 heap,
                 fresh,
                 |v| {
-                    builder.build_select(
-                        builder.build_int_compare::<IntValue>(
-                            IntPredicate::SGT,
+                    Ok(builder
+                        .build_int_compare::<IntValue>(
+                            IntPredicate::SGE,
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
-                            &cmp_name
-                        ).unwrap(),
-                        v[0],
-                        v[1],
-                        &name
-                    ).unwrap()
+                            &ret_name,
+                        )?
+                        .into())
                 },
                 args,
-                dest
-            );
+                dest,
+            )?;
         }
-
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Smin,
+            op: ValueOps::Not,
             op_type: _,
         } => {
-            let cmp_name = fresh.fresh_var();
-            let name = fresh.fresh_var();
+            let ret_name = fresh.fresh_var();
             build_op(
                 context,
                 builder,
                 heap,
                 fresh,
                 |v| {
-                    builder.build_select(
-                        builder.build_int_compare::<IntValue>(
-                            IntPredicate::SLT,
-                            v[0].try_into().unwrap(),
-                            v[1].try_into().unwrap(),
-                            &cmp_name
-                        ).unwrap(),
-                        v[0],
-                        v[1],
-                        &name
-                    ).unwrap()
+                    Ok(builder
+                        .build_not::<IntValue>(v[0].try_into().unwrap(), &ret_name)?
+                        .into())
                 },
                 args,
-                dest
-            );
+                dest,
+            )?;
         }
-
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Shl,
+            op: ValueOps::Zext,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -740,26 +1251,24 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_left_shift::<IntValue>(
+                    Ok(builder
+                        .build_int_z_extend::<IntValue>(
                             v[0].try_into().unwrap(),
-                            v[1].try_into().unwrap(),
-                            &ret_name
-                        )
-                        .unwrap()
-                        .into()
+                            context.i64_type(),
+                            &ret_name,
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
-
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Shr,
+            op: ValueOps::And,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -769,27 +1278,24 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_right_shift::<IntValue>(
+                    Ok(builder
+                        .build_and::<IntValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
-                            false, // sign extend
-                            &ret_name
-                        )
-                        .unwrap()
-                        .into()
+                            &ret_name,
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
-
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Fadd,
+            op: ValueOps::Or,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -799,27 +1305,32 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_float_add::<FloatValue>(
+                    Ok(builder
+                        .build_or::<IntValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
             dest,
-            funcs: _,
+            funcs,
             labels: _,
-            op: ValueOps::Fsub,
+            op: ValueOps::Call,
             op_type: _,
         } => {
+            let function = module
+                .get_function(llvm_function_name(&funcs[0], library))
+                .ok_or_else(|| BrilError::UndefinedFunction {
+                    name: funcs[0].clone(),
+                    suggestion: None,
+                })?;
             let ret_name = fresh.fresh_var();
             build_op(
                 context,
@@ -827,25 +1338,41 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_float_sub::<FloatValue>(
-                            v[0].try_into().unwrap(),
-                            v[1].try_into().unwrap(),
-                            &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                    let call = builder.build_call(
+                        function,
+                        v.iter()
+                            .map(|val| (*val).into())
+                            .collect::<Vec<_>>()
+                            .as_slice(),
+                        &ret_name,
+                    )?;
+                    call.set_call_convention(function.get_call_conventions());
+                    call.try_as_basic_value().left().ok_or_else(|| {
+                        BrilError::UnsupportedOp(format!(
+                            "using the result of @{}, which doesn't return anything",
+                            funcs[0]
+                        ))
+                    })
                 },
                 args,
                 dest,
-            );
+            )?;
         }
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Fmul,
+            op: ValueOps::Id,
+            op_type: _,
+        } => build_op(context, builder, heap, fresh, |v| Ok(v[0]), args, dest)?,
+
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::Select,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -855,82 +1382,90 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_float_mul::<FloatValue>(
-                            v[0].try_into().unwrap(),
-                            v[1].try_into().unwrap(),
-                            &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                    Ok(builder.build_select::<BasicValueEnum, IntValue>(
+                        v[0].try_into().unwrap(),
+                        v[1],
+                        v[2],
+                        &ret_name,
+                    )?)
                 },
                 args,
                 dest,
-            );
+            )?;
         }
+
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Fdiv,
+            op: ValueOps::Smax,
             op_type: _,
         } => {
-            let ret_name = fresh.fresh_var();
+            let cmp_name = fresh.fresh_var();
+            let name = fresh.fresh_var();
             build_op(
                 context,
                 builder,
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_float_div::<FloatValue>(
+                    Ok(builder.build_select(
+                        builder.build_int_compare::<IntValue>(
+                            IntPredicate::SGT,
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
-                            &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                            &cmp_name,
+                        )?,
+                        v[0],
+                        v[1],
+                        &name,
+                    )?)
                 },
                 args,
                 dest,
-            );
+            )?;
         }
+
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Feq,
+            op: ValueOps::Smin,
             op_type: _,
         } => {
-            let ret_name = fresh.fresh_var();
+            let cmp_name = fresh.fresh_var();
+            let name = fresh.fresh_var();
             build_op(
                 context,
                 builder,
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_float_compare::<FloatValue>(
-                            FloatPredicate::OEQ,
+                    Ok(builder.build_select(
+                        builder.build_int_compare::<IntValue>(
+                            IntPredicate::SLT,
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
-                            &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                            &cmp_name,
+                        )?,
+                        v[0],
+                        v[1],
+                        &name,
+                    )?)
                 },
                 args,
                 dest,
-            );
+            )?;
         }
+
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Flt,
+            op: ValueOps::Shl,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -940,26 +1475,25 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_float_compare::<FloatValue>(
-                            FloatPredicate::OLT,
+                    Ok(builder
+                        .build_left_shift::<IntValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
+
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Fgt,
+            op: ValueOps::Shr,
             op_type: _,
         } => {
             let ret_name = fresh.fresh_var();
@@ -969,28 +1503,35 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_float_compare::<FloatValue>(
-                            FloatPredicate::OGT,
+                    Ok(builder
+                        .build_right_shift::<IntValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
+                            false, // sign extend
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .into())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
+
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Fle,
+            op: op @ (ValueOps::Rotl | ValueOps::Rotr),
             op_type: _,
         } => {
+            // A funnel shift with the same value for both halves is a rotate
+            let name = if op == &ValueOps::Rotl {
+                "llvm.fshl.i64"
+            } else {
+                "llvm.fshr.i64"
+            };
+            let intrinsic = get_intrinsic(module, name, &[module.get_context().i64_type().into()])?;
             let ret_name = fresh.fresh_var();
             build_op(
                 context,
@@ -998,28 +1539,36 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_float_compare::<FloatValue>(
-                            FloatPredicate::OLE,
-                            v[0].try_into().unwrap(),
-                            v[1].try_into().unwrap(),
+                    Ok(builder
+                        .build_call(
+                            intrinsic,
+                            &[v[0].into(), v[0].into(), v[1].into()],
                             &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                        )?
+                        .try_as_basic_value()
+                        .unwrap_left())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
+
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Fge,
+            op: op @ (ValueOps::Popcnt | ValueOps::Clz | ValueOps::Ctz | ValueOps::Bswap),
             op_type: _,
         } => {
+            let name = match op {
+                ValueOps::Popcnt => "llvm.ctpop.i64",
+                ValueOps::Clz => "llvm.ctlz.i64",
+                ValueOps::Ctz => "llvm.cttz.i64",
+                ValueOps::Bswap => "llvm.bswap.i64",
+                _ => unreachable!(),
+            };
+            let intrinsic = get_intrinsic(module, name, &[module.get_context().i64_type().into()])?;
             let ret_name = fresh.fresh_var();
             build_op(
                 context,
@@ -1027,385 +1576,2303 @@ fn build_instruction<'a, 'b>(
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_float_compare::<FloatValue>(
-                            FloatPredicate::OGE,
-                            v[0].try_into().unwrap(),
-                            v[1].try_into().unwrap(),
-                            &ret_name,
-                        )
-                        .unwrap()
-                        .into()
+                    let mut call_args = vec![v[0].into()];
+                    // ctlz and cttz return poison for 0 unless their second argument is false
+                    if matches!(op, ValueOps::Clz | ValueOps::Ctz) {
+                        call_args.push(context.bool_type().const_zero().into());
+                    }
+                    Ok(builder
+                        .build_call(intrinsic, &call_args, &ret_name)?
+                        .try_as_basic_value()
+                        .unwrap_left())
                 },
                 args,
                 dest,
-            );
+            )?;
         }
+
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Fmax,
+            op: ValueOps::Fadd,
             op_type: _,
         } => {
-            let cmp_name = fresh.fresh_var();
-            let name = fresh.fresh_var();
+            let ret_name = fresh.fresh_var();
             build_op(
                 context,
                 builder,
                 heap,
                 fresh,
                 |v| {
-                    builder.build_select(
-                        builder.build_float_compare::<FloatValue>(
-                            FloatPredicate::OGT,
+                    Ok(builder
+                        .build_float_add::<FloatValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
-                            &cmp_name
-                        ).unwrap(),
-                        v[0],
-                        v[1],
-                        &name
-                    ).unwrap()
+                            &ret_name,
+                        )?
+                        .into())
                 },
                 args,
-                dest
-            );
+                dest,
+            )?;
         }
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Fmin,
+            op: ValueOps::Fsub,
             op_type: _,
         } => {
-            let cmp_name = fresh.fresh_var();
-            let name = fresh.fresh_var();
+            let ret_name = fresh.fresh_var();
             build_op(
                 context,
                 builder,
                 heap,
                 fresh,
                 |v| {
-                    builder.build_select(
-                        builder.build_float_compare::<FloatValue>(
-                            FloatPredicate::OLT,
+                    Ok(builder
+                        .build_float_sub::<FloatValue>(
                             v[0].try_into().unwrap(),
                             v[1].try_into().unwrap(),
-                            &cmp_name
-                        ).unwrap(),
-                        v[0],
-                        v[1],
-                        &name
-                    ).unwrap()
+                            &ret_name,
+                        )?
+                        .into())
                 },
                 args,
-                dest
-            );
+                dest,
+            )?;
         }
-
-        Instruction::Effect {
+        Instruction::Value {
             args,
+            dest,
             funcs: _,
             labels: _,
-            op: EffectOps::Return,
+            op: ValueOps::Fmul,
+            op_type: _,
         } => {
-            if args.is_empty() {
-                builder.build_return(None).unwrap();
-            } else {
-                builder
-                    .build_return(Some(&build_load(
-                        context,
-                        builder,
-                        &heap.get(&args[0]),
-                        &fresh.fresh_var(),
-                    )))
-                    .unwrap();
-            }
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_float_mul::<FloatValue>(
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
+                            &ret_name,
+                        )?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
         }
-        Instruction::Effect {
+        Instruction::Value {
             args,
-            funcs,
+            dest,
+            funcs: _,
             labels: _,
-            op: EffectOps::Call,
+            op: ValueOps::Fdiv,
+            op_type: _,
         } => {
-            let func_name = if funcs[0] == "main" {
-                "_main"
-            } else {
-                &funcs[0]
-            };
-            let function = module.get_function(func_name).unwrap();
             let ret_name = fresh.fresh_var();
-            build_effect_op(
+            build_op(
                 context,
                 builder,
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_call(
-                            function,
-                            v.iter()
-                                .map(|val| (*val).into())
-                                .collect::<Vec<_>>()
-                                .as_slice(),
+                    Ok(builder
+                        .build_float_div::<FloatValue>(
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
                             &ret_name,
-                        )
-                        .unwrap();
+                        )?
+                        .into())
                 },
                 args,
-            );
+                dest,
+            )?;
         }
-        Instruction::Effect {
-            args: _,
+        Instruction::Value {
+            args,
+            dest,
             funcs: _,
             labels: _,
-            op: EffectOps::Nop,
-        } => {}
-        Instruction::Effect {
+            op: ValueOps::Feq,
+            op_type: _,
+        } => {
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_float_compare::<FloatValue>(
+                            FloatPredicate::OEQ,
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
+                            &ret_name,
+                        )?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
             args,
+            dest,
             funcs: _,
             labels: _,
-            op: EffectOps::Print,
+            op: ValueOps::Flt,
+            op_type: _,
         } => {
-            let print_int = module.get_function("_bril_print_int").unwrap();
-            let print_bool = module.get_function("_bril_print_bool").unwrap();
-            let print_float = module.get_function("_bril_print_float").unwrap();
-            let print_sep = module.get_function("_bril_print_sep").unwrap();
-            let print_end = module.get_function("_bril_print_end").unwrap();
-            /*            let ret_name = fresh.fresh_var(); */
-            let len = args.len();
-
-            args.iter().enumerate().for_each(|(i, a)| {
-                let wrapped_ptr = heap.get(a);
-                let v = build_load(context, builder, &wrapped_ptr, &fresh.fresh_var());
-                match wrapped_ptr.ty {
-                    Type::Int => {
-                        builder
-                            .build_call(print_int, &[v.into()], "print_int")
-                            .unwrap();
-                    }
-                    Type::Bool => {
-                        builder
-                            .build_call(
-                                print_bool,
-                                &[builder
-                                    .build_int_cast::<IntValue>(
-                                        v.try_into().unwrap(),
-                                        context.bool_type(),
-                                        "bool_cast",
-                                    )
-                                    .unwrap()
-                                    .into()],
-                                "print_bool",
-                            )
-                            .unwrap();
-                    }
-                    Type::Float => {
-                        builder
-                            .build_call(print_float, &[v.into()], "print_float")
-                            .unwrap();
-                    }
-                    Type::Pointer(_) => {
-                        unreachable!()
-                    }
-                };
-                if i < len - 1 {
-                    builder.build_call(print_sep, &[], "print_sep").unwrap();
-                }
-            });
-            builder.build_call(print_end, &[], "print_end").unwrap();
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_float_compare::<FloatValue>(
+                            FloatPredicate::OLT,
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
+                            &ret_name,
+                        )?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
         }
-        Instruction::Effect {
-            args: _,
+        Instruction::Value {
+            args,
+            dest,
             funcs: _,
-            labels,
-            op: EffectOps::Jump,
+            labels: _,
+            op: ValueOps::Fgt,
+            op_type: _,
+        } => {
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_float_compare::<FloatValue>(
+                            FloatPredicate::OGT,
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
+                            &ret_name,
+                        )?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::Fle,
+            op_type: _,
         } => {
-            builder
-                .build_unconditional_branch(block_map_get(
-                    context, llvm_func, block_map, &labels[0],
-                ))
-                .unwrap();
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_float_compare::<FloatValue>(
+                            FloatPredicate::OLE,
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
+                            &ret_name,
+                        )?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
         }
-        Instruction::Effect {
+        Instruction::Value {
             args,
+            dest,
             funcs: _,
-            labels,
-            op: EffectOps::Branch,
+            labels: _,
+            op: ValueOps::Fge,
+            op_type: _,
         } => {
-            let then_block = block_map_get(context, llvm_func, block_map, &labels[0]);
-            let else_block = block_map_get(context, llvm_func, block_map, &labels[1]);
-            build_effect_op(
+            let ret_name = fresh.fresh_var();
+            build_op(
                 context,
                 builder,
                 heap,
                 fresh,
                 |v| {
-                    builder
-                        .build_conditional_branch(v[0].try_into().unwrap(), then_block, else_block)
-                        .unwrap();
+                    Ok(builder
+                        .build_float_compare::<FloatValue>(
+                            FloatPredicate::OGE,
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
+                            &ret_name,
+                        )?
+                        .into())
                 },
                 args,
-            );
+                dest,
+            )?;
         }
         Instruction::Value {
             args,
             dest,
             funcs: _,
-            labels,
-            op: ValueOps::Phi,
-            op_type,
+            labels: _,
+            op: ValueOps::Fne,
+            op_type: _,
         } => {
-            panic!("Phi nodes should be handled by build_phi");
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_float_compare::<FloatValue>(
+                            FloatPredicate::ONE,
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
+                            &ret_name,
+                        )?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
         }
+        // The interpreters return the second argument unless the first is greater, so this can't be `llvm.maxnum`, which ignores a NaN in either position and may return either zero. `maxnum` is the Bril operation for that
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Alloc,
-            op_type,
+            op: ValueOps::Fmax,
+            op_type: _,
         } => {
-            let alloc_name = fresh.fresh_var();
-            let ty = unwrap_bril_ptrtype(op_type);
+            let cmp_name = fresh.fresh_var();
+            let name = fresh.fresh_var();
             build_op(
                 context,
                 builder,
                 heap,
                 fresh,
                 |v| {
-                    llvm_type_map(context, ty, |ty| {
-                        builder
-                            .build_array_malloc(ty, v[0].try_into().unwrap(), &alloc_name)
-                            .unwrap()
-                            .into()
-                    })
+                    Ok(builder.build_select(
+                        builder.build_float_compare::<FloatValue>(
+                            FloatPredicate::OGT,
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
+                            &cmp_name,
+                        )?,
+                        v[0],
+                        v[1],
+                        &name,
+                    )?)
                 },
                 args,
                 dest,
-            );
+            )?;
         }
+        // Like `fmax`, the second argument is returned unless the first is less, which `llvm.minnum` doesn't match
         Instruction::Value {
             args,
             dest,
             funcs: _,
             labels: _,
-            op: ValueOps::Load,
-            op_type,
+            op: ValueOps::Fmin,
+            op_type: _,
         } => {
+            let cmp_name = fresh.fresh_var();
             let name = fresh.fresh_var();
-            llvm_type_map(context, op_type, |pointee_ty| {
-                build_op(
-                    context,
-                    builder,
-                    heap,
-                    fresh,
-                    |v| {
-                        builder
-                            .build_load(pointee_ty, v[0].try_into().unwrap(), &name)
-                            .unwrap()
-                    },
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder.build_select(
+                        builder.build_float_compare::<FloatValue>(
+                            FloatPredicate::OLT,
+                            v[0].try_into().unwrap(),
+                            v[1].try_into().unwrap(),
+                            &cmp_name,
+                        )?,
+                        v[0],
+                        v[1],
+                        &name,
+                    )?)
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op:
+                op @ (ValueOps::Fsqrt
+                | ValueOps::Fabs
+                | ValueOps::Ceil
+                | ValueOps::Floor
+                | ValueOps::Round
+                | ValueOps::Trunc
+                | ValueOps::Fsin
+                | ValueOps::Fcos
+                | ValueOps::Fexp
+                | ValueOps::Flog),
+            op_type: _,
+        } => {
+            let name = match op {
+                ValueOps::Fsqrt => "llvm.sqrt.f64",
+                ValueOps::Fabs => "llvm.fabs.f64",
+                ValueOps::Ceil => "llvm.ceil.f64",
+                ValueOps::Floor => "llvm.floor.f64",
+                ValueOps::Round => "llvm.round.f64",
+                ValueOps::Trunc => "llvm.trunc.f64",
+                ValueOps::Fsin => "llvm.sin.f64",
+                ValueOps::Fcos => "llvm.cos.f64",
+                ValueOps::Fexp => "llvm.exp.f64",
+                ValueOps::Flog => "llvm.log.f64",
+                _ => unreachable!(),
+            };
+            let intrinsic = get_intrinsic(module, name, &[module.get_context().f64_type().into()])?;
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_call(intrinsic, &[v[0].into()], &ret_name)?
+                        .try_as_basic_value()
+                        .unwrap_left())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: op @ (ValueOps::Pow | ValueOps::Fcopysign | ValueOps::Maxnum | ValueOps::Minnum),
+            op_type: _,
+        } => {
+            let name = match op {
+                ValueOps::Pow => "llvm.pow.f64",
+                ValueOps::Fcopysign => "llvm.copysign.f64",
+                ValueOps::Maxnum => "llvm.maxnum.f64",
+                ValueOps::Minnum => "llvm.minnum.f64",
+                _ => unreachable!(),
+            };
+            let intrinsic = get_intrinsic(module, name, &[module.get_context().f64_type().into()])?;
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_call(intrinsic, &[v[0].into(), v[1].into()], &ret_name)?
+                        .try_as_basic_value()
+                        .unwrap_left())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::Fma,
+            op_type: _,
+        } => {
+            let fma = get_intrinsic(
+                module,
+                "llvm.fma.f64",
+                &[module.get_context().f64_type().into()],
+            )?;
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_call(fma, &[v[0].into(), v[1].into(), v[2].into()], &ret_name)?
+                        .try_as_basic_value()
+                        .unwrap_left())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::I2f,
+            op_type: _,
+        } => {
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_signed_int_to_float(
+                            v[0].into_int_value(),
+                            context.f64_type(),
+                            &ret_name,
+                        )?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::F2i,
+            op_type: _,
+        } => {
+            // fptosi is poison for NaN and out of range values, while the saturating version clamps them and gives 0 for NaN
+            let fptosi_sat = get_intrinsic(
+                module,
+                "llvm.fptosi.sat",
+                &[
+                    module.get_context().i64_type().into(),
+                    module.get_context().f64_type().into(),
+                ],
+            )?;
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_call(fptosi_sat, &[v[0].into()], &ret_name)?
+                        .try_as_basic_value()
+                        .unwrap_left())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: op @ (ValueOps::Float2bits | ValueOps::Bits2float),
+            op_type: _,
+        } => {
+            let ret_name = fresh.fresh_var();
+            let to_type: BasicTypeEnum = if op == &ValueOps::Float2bits {
+                context.i64_type().into()
+            } else {
+                context.f64_type().into()
+            };
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| Ok(builder.build_bit_cast(v[0], to_type, &ret_name)?),
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::Fneg,
+            op_type: _,
+        } => {
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_float_neg::<FloatValue>(v[0].try_into().unwrap(), &ret_name)?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
+        }
+        // NaN is the only value that is unordered with itself
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::Isnan,
+            op_type: _,
+        } => {
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    let x: FloatValue = v[0].try_into().unwrap();
+                    Ok(builder
+                        .build_float_compare(FloatPredicate::UNO, x, x, &ret_name)?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::Isinf,
+            op_type: _,
+        } => {
+            let fabs = get_intrinsic(
+                module,
+                "llvm.fabs.f64",
+                &[module.get_context().f64_type().into()],
+            )?;
+            let abs_name = fresh.fresh_var();
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    let abs = builder
+                        .build_call(fabs, &[v[0].into()], &abs_name)?
+                        .try_as_basic_value()
+                        .unwrap_left()
+                        .into_float_value();
+                    Ok(builder
+                        .build_float_compare(
+                            FloatPredicate::OEQ,
+                            abs,
+                            context.f64_type().const_float(f64::INFINITY),
+                            &ret_name,
+                        )?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
+        }
+
+        Instruction::Effect {
+            args,
+            funcs: _,
+            labels: _,
+            op: EffectOps::Return,
+        } => {
+            if args.is_empty() {
+                builder.build_return(None)?;
+            } else {
+                builder.build_return(Some(&build_load(
+                    context,
+                    builder,
+                    &heap.get(&args[0])?,
+                    &fresh.fresh_var(),
+                )?))?;
+            }
+        }
+        Instruction::Effect {
+            args,
+            funcs,
+            labels: _,
+            op: EffectOps::Call,
+        } => {
+            let function = module
+                .get_function(llvm_function_name(&funcs[0], library))
+                .ok_or_else(|| BrilError::UndefinedFunction {
+                    name: funcs[0].clone(),
+                    suggestion: None,
+                })?;
+            let ret_name = fresh.fresh_var();
+            build_effect_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    builder
+                        .build_call(
+                            function,
+                            v.iter()
+                                .map(|val| (*val).into())
+                                .collect::<Vec<_>>()
+                                .as_slice(),
+                            &ret_name,
+                        )?
+                        .set_call_convention(function.get_call_conventions());
+                    Ok(())
+                },
+                args,
+            )?;
+        }
+        Instruction::Effect {
+            args: _,
+            funcs: _,
+            labels: _,
+            op: EffectOps::Nop,
+        } => {}
+        Instruction::Effect {
+            args: _,
+            funcs: _,
+            labels: _,
+            op: EffectOps::Abort,
+        } => {
+            let func_name = llvm_func.get_name().to_str().unwrap();
+            let bril_name = if func_name == "_main" && !library {
+                "main"
+            } else {
+                func_name
+            };
+            let name = builder.build_global_string_ptr(bril_name, &fresh.fresh_var())?;
+            builder.build_call(
+                get_runtime_function(module, "_bril_abort")?,
+                &[name.as_pointer_value().into()],
+                "",
+            )?;
+            builder.build_unreachable()?;
+        }
+        Instruction::Effect {
+            args,
+            funcs: _,
+            labels: _,
+            op: EffectOps::Print,
+        } => {
+            let print_int = get_runtime_function(module, "_bril_print_int")?;
+            let print_bool = get_runtime_function(module, "_bril_print_bool")?;
+            let print_float = get_runtime_function(module, "_bril_print_float")?;
+            let print_char = get_runtime_function(module, "_bril_print_char")?;
+            let print_ptr = get_runtime_function(module, "_bril_print_ptr")?;
+            let print_sep = get_runtime_function(module, "_bril_print_sep")?;
+            let print_end = get_runtime_function(module, "_bril_print_end")?;
+            /*            let ret_name = fresh.fresh_var(); */
+            let len = args.len();
+
+            for (i, a) in args.iter().enumerate() {
+                let wrapped_ptr = heap.get(a)?;
+                let v = build_load(context, builder, &wrapped_ptr, &fresh.fresh_var())?;
+                match wrapped_ptr.ty {
+                    Type::Int => {
+                        builder.build_call(print_int, &[v.into()], "print_int")?;
+                    }
+                    Type::Bool => {
+                        builder.build_call(print_bool, &[v.into()], "print_bool")?;
+                    }
+                    Type::Float => {
+                        builder.build_call(print_float, &[v.into()], "print_float")?;
+                    }
+                    Type::Char => {
+                        builder.build_call(print_char, &[v.into()], "print_char")?;
+                    }
+                    Type::Pointer(ref pointee) => {
+                        // The runtime prints the offset in elements, like `ptradd` counts them
+                        let size =
+                            llvm_memory_type_map(context, pointee, |ty| ty.size_of().unwrap());
+                        builder.build_call(print_ptr, &[v.into(), size.into()], "print_ptr")?;
+                    }
+                    Type::Vector(_) => {
+                        return Err(BrilError::UnsupportedOp("printing a vector".to_string()))
+                    }
+                };
+                if i < len - 1 {
+                    builder.build_call(print_sep, &[], "print_sep")?;
+                }
+            }
+            builder.build_call(print_end, &[], "print_end")?;
+        }
+        Instruction::Effect {
+            args: _,
+            funcs: _,
+            labels,
+            op: EffectOps::Jump,
+        } => {
+            builder.build_unconditional_branch(block_map_get(
+                context, llvm_func, block_map, &labels[0],
+            ))?;
+        }
+        Instruction::Effect {
+            args,
+            funcs: _,
+            labels,
+            op: EffectOps::Branch,
+        } => {
+            let then_block = block_map_get(context, llvm_func, block_map, &labels[0]);
+            let else_block = block_map_get(context, llvm_func, block_map, &labels[1]);
+            build_effect_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    builder.build_conditional_branch(
+                        v[0].try_into().unwrap(),
+                        then_block,
+                        else_block,
+                    )?;
+                    Ok(())
+                },
+                args,
+            )?;
+        }
+        Instruction::Effect {
+            args: _,
+            funcs: _,
+            labels: _,
+            op: EffectOps::Speculate,
+        } => {
+            let nested_block = context.append_basic_block(llvm_func, &fresh.fresh_label());
+            let continue_block = context.append_basic_block(llvm_func, &fresh.fresh_label());
+            let speculating = builder.build_load(
+                context.bool_type(),
+                heap.speculating.unwrap(),
+                &fresh.fresh_var(),
+            )?;
+            builder.build_conditional_branch(
+                speculating.into_int_value(),
+                nested_block,
+                continue_block,
+            )?;
+
+            builder.position_at_end(nested_block);
+            builder.build_call(
+                get_runtime_function(module, "_bril_nested_speculation")?,
+                &[],
+                "",
+            )?;
+            builder.build_unreachable()?;
+
+            builder.position_at_end(continue_block);
+            heap.copy_snapshot(builder, context, fresh, false)?;
+            heap.set_speculating(builder, context, true)?;
+        }
+        Instruction::Effect {
+            args: _,
+            funcs: _,
+            labels: _,
+            op: EffectOps::Commit,
+        } => {
+            heap.set_speculating(builder, context, false)?;
+        }
+        Instruction::Effect {
+            args,
+            funcs: _,
+            labels,
+            op: EffectOps::Guard,
+        } => {
+            let continue_block = context.append_basic_block(llvm_func, &fresh.fresh_label());
+            let abort_block = context.append_basic_block(llvm_func, &fresh.fresh_label());
+            build_effect_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    builder.build_conditional_branch(
+                        v[0].try_into().unwrap(),
+                        continue_block,
+                        abort_block,
+                    )?;
+                    Ok(())
+                },
+                args,
+            )?;
+
+            builder.position_at_end(abort_block);
+            heap.copy_snapshot(builder, context, fresh, true)?;
+            heap.set_speculating(builder, context, false)?;
+            builder.build_unconditional_branch(block_map_get(
+                context, llvm_func, block_map, &labels[0],
+            ))?;
+
+            builder.position_at_end(continue_block);
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels,
+            op: ValueOps::Phi,
+            op_type,
+        } => {
+            panic!("Phi nodes should be handled by build_phi");
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::Alloc,
+            op_type,
+        } => {
+            let ty = unwrap_bril_ptrtype(op_type);
+            let i64_type = context.i64_type();
+            let count = build_load(context, builder, &heap.get(&args[0])?, &fresh.fresh_var())?
+                .into_int_value();
+            // The same error as `brili`, for counts that aren't positive
+            let not_positive = builder.build_int_compare(
+                IntPredicate::SLE,
+                count,
+                i64_type.const_zero(),
+                &fresh.fresh_var(),
+            )?;
+            build_runtime_check(
+                context,
+                builder,
+                llvm_func,
+                fresh,
+                not_positive,
+                get_runtime_function(module, "_bril_alloc_error")?,
+                &[count.into()],
+            )?;
+            // The number of bytes must not wrap around to a small allocation
+            let size = llvm_memory_type_map(context, ty, |ty| ty.size_of().unwrap());
+            let max_count = builder.build_int_unsigned_div(
+                i64_type.const_int(i64::MAX.unsigned_abs(), false),
+                size,
+                &fresh.fresh_var(),
+            )?;
+            let too_big = builder.build_int_compare(
+                IntPredicate::UGT,
+                count,
+                max_count,
+                &fresh.fresh_var(),
+            )?;
+            build_error_check(
+                context,
+                module,
+                builder,
+                llvm_func,
+                fresh,
+                too_big,
+                "malloc failure",
+            )?;
+            let alloc_name = fresh.fresh_var();
+            let ptr = if options.check_frees {
+                let bytes = builder.build_int_mul(count, size, "")?;
+                builder
+                    .build_call(
+                        get_runtime_function(module, "_bril_malloc")?,
+                        &[bytes.into()],
+                        &alloc_name,
+                    )?
+                    .try_as_basic_value()
+                    .unwrap_left()
+                    .into_pointer_value()
+            } else {
+                llvm_memory_type_map(context, ty, |ty| {
+                    builder.build_array_malloc(ty, count, &alloc_name)
+                })?
+            };
+            let is_null = builder.build_is_null(ptr, &fresh.fresh_var())?;
+            build_error_check(
+                context,
+                module,
+                builder,
+                llvm_func,
+                fresh,
+                is_null,
+                "malloc failure",
+            )?;
+            if track_allocations {
+                let bytes = builder.build_int_mul(count, size, "")?;
+                builder.build_call(
+                    get_runtime_function(module, "_bril_record_alloc")?,
+                    &[ptr.into(), bytes.into()],
+                    "",
+                )?;
+            }
+            // `_bril_malloc` has already registered it
+            if options.check_memory && !options.check_frees {
+                let bytes = builder.build_int_mul(count, size, "")?;
+                builder.build_call(
+                    get_runtime_function(module, "_bril_mem_register")?,
+                    &[ptr.into(), bytes.into()],
+                    "",
+                )?;
+            }
+            build_store(context, builder, &heap.get(dest)?, ptr)?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::Load,
+            op_type,
+        } => {
+            let name = fresh.fresh_var();
+            llvm_memory_type_map(context, op_type, |pointee_ty| {
+                build_op(
+                    context,
+                    builder,
+                    heap,
+                    fresh,
+                    |v| {
+                        if options.check_memory {
+                            build_memory_check(
+                                context,
+                                module,
+                                builder,
+                                v[0].into_pointer_value(),
+                                op_type,
+                                context.i64_type().const_int(1, false),
+                            )?;
+                        }
+                        let val =
+                            builder.build_load(pointee_ty, v[0].try_into().unwrap(), &name)?;
+                        Ok(if op_type == &Type::Bool {
+                            builder
+                                .build_int_truncate(
+                                    val.into_int_value(),
+                                    context.bool_type(),
+                                    &name,
+                                )?
+                                .into()
+                        } else {
+                            val
+                        })
+                    },
+                    args,
+                    dest,
+                )
+            })?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::PtrAdd,
+            op_type,
+        } => {
+            let name = fresh.fresh_var();
+            // The stride is the size of the pointee, which is just `ptr` for nested pointers like `ptr<ptr<int>>` no matter how deep they go
+            let op_type = unwrap_bril_ptrtype(op_type);
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    let ptr = llvm_memory_type_map(context, op_type, |pointee_ty| unsafe {
+                        builder.build_gep(
+                            pointee_ty,
+                            v[0].try_into().unwrap(),
+                            &[v[1].try_into().unwrap()],
+                            &name,
+                        )
+                    })?;
+                    // The runtime remembers where the result came from when it leaves the allocation
+                    if options.check_memory {
+                        builder.build_call(
+                            get_runtime_function(module, "_bril_mem_ptradd")?,
+                            &[v[0].into(), ptr.into()],
+                            "",
+                        )?;
+                    }
+                    Ok(ptr.into())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::PtrDiff,
+            op_type: _,
+        } => {
+            let name = fresh.fresh_var();
+            let pointee = unwrap_bril_ptrtype(&heap.get(&args[0])?.ty).clone();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    let i64_type = context.i64_type();
+                    let lhs = builder.build_ptr_to_int::<PointerValue>(
+                        v[0].try_into().unwrap(),
+                        i64_type,
+                        "",
+                    )?;
+                    let rhs = builder.build_ptr_to_int::<PointerValue>(
+                        v[1].try_into().unwrap(),
+                        i64_type,
+                        "",
+                    )?;
+                    let bytes = builder.build_int_sub(lhs, rhs, "")?;
+                    // The distance is in elements of the pointee type, not bytes
+                    let size = llvm_memory_type_map(context, &pointee, |ty| ty.size_of().unwrap());
+                    Ok(builder
+                        .build_int_exact_signed_div(bytes, size, &name)?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: op @ (ValueOps::Peq | ValueOps::Plt | ValueOps::Pgt | ValueOps::Ple | ValueOps::Pge),
+            op_type: _,
+        } => {
+            // Pointers are compared as unsigned addresses
+            let predicate = match op {
+                ValueOps::Peq => IntPredicate::EQ,
+                ValueOps::Plt => IntPredicate::ULT,
+                ValueOps::Pgt => IntPredicate::UGT,
+                ValueOps::Ple => IntPredicate::ULE,
+                _ => IntPredicate::UGE,
+            };
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    let i64_type = context.i64_type();
+                    let lhs = builder.build_ptr_to_int::<PointerValue>(
+                        v[0].try_into().unwrap(),
+                        i64_type,
+                        "",
+                    )?;
+                    let rhs = builder.build_ptr_to_int::<PointerValue>(
+                        v[1].try_into().unwrap(),
+                        i64_type,
+                        "",
+                    )?;
+                    Ok(builder
+                        .build_int_compare(predicate, lhs, rhs, &ret_name)?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::Ptr2int,
+            op_type: _,
+        } => {
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_ptr_to_int::<PointerValue>(
+                            v[0].try_into().unwrap(),
+                            context.i64_type(),
+                            &ret_name,
+                        )?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::Int2ptr,
+            op_type: _,
+        } => {
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    Ok(builder
+                        .build_int_to_ptr::<IntValue>(
+                            v[0].try_into().unwrap(),
+                            context.ptr_type(AddressSpace::default()),
+                            &ret_name,
+                        )?
+                        .into())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs,
+            labels: _,
+            op: ValueOps::Getglobal,
+            op_type,
+        } => {
+            let global = WrappedPointer {
+                ty: op_type.clone(),
+                ptr: module
+                    .get_global(&funcs[0])
+                    .ok_or_else(|| BrilError::UndefinedGlobal(funcs[0].clone()))?
+                    .as_pointer_value(),
+            };
+            let ret_name = fresh.fresh_var();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |_| build_load(context, builder, &global, &ret_name),
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: op @ (ValueOps::Vadd | ValueOps::Vsub | ValueOps::Vmul),
+            op_type,
+        } => {
+            let ret_name = fresh.fresh_var();
+            let float = *unwrap_bril_vectortype(op_type) == Type::Float;
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    let (lhs, rhs) = (v[0].into_vector_value(), v[1].into_vector_value());
+                    let result = match (op, float) {
+                        (ValueOps::Vadd, false) => builder.build_int_add(lhs, rhs, &ret_name)?,
+                        (ValueOps::Vsub, false) => builder.build_int_sub(lhs, rhs, &ret_name)?,
+                        (ValueOps::Vmul, false) => builder.build_int_mul(lhs, rhs, &ret_name)?,
+                        (ValueOps::Vadd, true) => builder.build_float_add(lhs, rhs, &ret_name)?,
+                        (ValueOps::Vsub, true) => builder.build_float_sub(lhs, rhs, &ret_name)?,
+                        _ => builder.build_float_mul(lhs, rhs, &ret_name)?,
+                    };
+                    Ok(result.into())
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Value {
+            args,
+            dest,
+            funcs: _,
+            labels: _,
+            op: ValueOps::Vload,
+            op_type,
+        } => {
+            let ret_name = fresh.fresh_var();
+            // Memory is only aligned to the elements, not to the whole vector
+            let align = u32::try_from(align_of_type(unwrap_bril_vectortype(op_type))).unwrap();
+            build_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    let vector = llvm_type_map(context, op_type, |ty| {
+                        builder.build_load(ty, v[0].into_pointer_value(), &ret_name)
+                    })?;
+                    vector
+                        .as_instruction_value()
+                        .unwrap()
+                        .set_alignment(align)
+                        .map_err(|e| BrilError::Builder(e.to_string()))?;
+                    Ok(vector)
+                },
+                args,
+                dest,
+            )?;
+        }
+        Instruction::Effect {
+            args,
+            funcs: _,
+            labels: _,
+            op: EffectOps::Vstore,
+        } => {
+            let pointee = unwrap_bril_ptrtype(&heap.get(&args[0])?.ty).clone();
+            let align = u32::try_from(align_of_type(&pointee)).unwrap();
+            build_effect_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    builder
+                        .build_store(v[0].into_pointer_value(), v[1])?
+                        .set_alignment(align)
+                        .map_err(|e| BrilError::Builder(e.to_string()))?;
+                    Ok(())
+                },
+                args,
+            )?;
+        }
+        Instruction::Effect {
+            args,
+            funcs: _,
+            labels: _,
+            op: EffectOps::Store,
+        } => {
+            build_effect_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    let val = if heap.get(&args[1])?.ty == Type::Bool {
+                        builder
+                            .build_int_z_extend(v[1].into_int_value(), context.i8_type(), "")?
+                            .into()
+                    } else {
+                        v[1]
+                    };
+                    if options.check_memory {
+                        build_memory_check(
+                            context,
+                            module,
+                            builder,
+                            v[0].into_pointer_value(),
+                            &heap.get(&args[1])?.ty,
+                            context.i64_type().const_int(1, false),
+                        )?;
+                    }
+                    builder.build_store(v[0].try_into().unwrap(), val)?;
+                    Ok(())
+                },
+                args,
+            )?;
+        }
+        Instruction::Effect {
+            args,
+            funcs: _,
+            labels: _,
+            op: EffectOps::MemCopy,
+        } => {
+            let pointee = unwrap_bril_ptrtype(&heap.get(&args[0])?.ty).clone();
+            let align = u32::try_from(align_of_type(&pointee)).unwrap();
+            // The same error as `brilirs`, instead of copying more bytes than there are
+            build_negative_count_check(
+                context,
+                module,
+                builder,
+                llvm_func,
+                heap,
+                fresh,
+                &args[2],
+                "cannot copy a negative number of entries",
+            )?;
+            build_effect_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    if options.check_memory {
+                        for ptr in &v[..2] {
+                            build_memory_check(
+                                context,
+                                module,
+                                builder,
+                                ptr.into_pointer_value(),
+                                &pointee,
+                                v[2].into_int_value(),
+                            )?;
+                        }
+                    }
+                    let size = llvm_memory_type_map(context, &pointee, |ty| ty.size_of().unwrap());
+                    let bytes = builder.build_int_mul(v[2].into_int_value(), size, "")?;
+                    // A count of zero copies no bytes, so it needs no special case
+                    builder.build_memcpy(
+                        v[0].try_into().unwrap(),
+                        align,
+                        v[1].try_into().unwrap(),
+                        align,
+                        bytes,
+                    )?;
+                    Ok(())
+                },
+                args,
+            )?;
+        }
+        Instruction::Effect {
+            args,
+            funcs: _,
+            labels: _,
+            op: EffectOps::Fill,
+        } => {
+            let pointee = unwrap_bril_ptrtype(&heap.get(&args[0])?.ty).clone();
+            let align = u32::try_from(align_of_type(&pointee)).unwrap();
+            // Both the `memset` and the loop below would otherwise go ahead, with a huge size or not at all
+            build_negative_count_check(
+                context,
+                module,
+                builder,
+                llvm_func,
+                heap,
+                fresh,
+                &args[2],
+                "cannot fill a negative number of entries",
+            )?;
+            // Bools, and ints and chars whose bytes are all the same, can be set a byte at a time with `memset`. Everything else is stored an element at a time in a loop
+            let memset_block = matches!(pointee, Type::Int | Type::Bool | Type::Char)
+                .then(|| context.append_basic_block(llvm_func, &fresh.fresh_label()));
+            let loop_blocks = (pointee != Type::Bool).then(|| {
+                (
+                    context.append_basic_block(llvm_func, &fresh.fresh_label()),
+                    context.append_basic_block(llvm_func, &fresh.fresh_label()),
+                )
+            });
+            let done_block = context.append_basic_block(llvm_func, &fresh.fresh_label());
+            build_effect_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    let ptr: PointerValue = v[0].try_into().unwrap();
+                    let count = v[2].into_int_value();
+                    if options.check_memory {
+                        build_memory_check(context, module, builder, ptr, &pointee, count)?;
+                    }
+                    let i64_type = context.i64_type();
+                    let val = if pointee == Type::Bool {
+                        builder
+                            .build_int_z_extend(v[1].into_int_value(), context.i8_type(), "")?
+                            .into()
+                    } else {
+                        v[1]
+                    };
+                    let entry_block = builder.get_insert_block().unwrap();
+
+                    match (memset_block, loop_blocks) {
+                        (Some(memset_block), Some((loop_block, _))) => {
+                            // The value is uniform when repeating its lowest byte gives it back
+                            let int = val.into_int_value();
+                            let byte = builder.build_int_truncate(int, context.i8_type(), "")?;
+                            let ones = if pointee == Type::Int {
+                                u64::MAX / 0xff
+                            } else {
+                                u64::from(u32::MAX / 0xff)
+                            };
+                            let splat = builder.build_int_mul(
+                                builder.build_int_z_extend(byte, int.get_type(), "")?,
+                                int.get_type().const_int(ones, false),
+                                "",
+                            )?;
+                            let uniform = builder.build_int_compare::<IntValue>(
+                                IntPredicate::EQ,
+                                splat,
+                                int,
+                                "",
+                            )?;
+                            builder.build_conditional_branch(uniform, memset_block, loop_block)?;
+                        }
+                        (Some(block), None) | (None, Some((block, _))) => {
+                            builder.build_unconditional_branch(block)?;
+                        }
+                        (None, None) => unreachable!(),
+                    }
+
+                    if let Some(memset_block) = memset_block {
+                        builder.position_at_end(memset_block);
+                        let byte = builder.build_int_truncate(
+                            val.into_int_value(),
+                            context.i8_type(),
+                            "",
+                        )?;
+                        let size =
+                            llvm_memory_type_map(context, &pointee, |ty| ty.size_of().unwrap());
+                        let bytes = builder.build_int_mul(count, size, "")?;
+                        builder.build_memset(ptr, align, byte, bytes)?;
+                        builder.build_unconditional_branch(done_block)?;
+                    }
+
+                    if let Some((loop_block, body_block)) = loop_blocks {
+                        builder.position_at_end(loop_block);
+                        let i = builder.build_phi(i64_type, "")?;
+                        let i_val = i.as_basic_value().into_int_value();
+                        let more =
+                            builder.build_int_compare(IntPredicate::SLT, i_val, count, "")?;
+                        builder.build_conditional_branch(more, body_block, done_block)?;
+
+                        builder.position_at_end(body_block);
+                        let elem = llvm_memory_type_map(context, &pointee, |ty| unsafe {
+                            builder.build_in_bounds_gep(ty, ptr, &[i_val], "")
+                        })?;
+                        builder.build_store(elem, val)?;
+                        let next =
+                            builder.build_int_add(i_val, i64_type.const_int(1, false), "")?;
+                        builder.build_unconditional_branch(loop_block)?;
+                        i.add_incoming(&[
+                            (&i64_type.const_zero(), entry_block),
+                            (&next, body_block),
+                        ]);
+                    }
+
+                    builder.position_at_end(done_block);
+                    Ok(())
+                },
+                args,
+            )?;
+        }
+        Instruction::Effect {
+            args,
+            funcs: _,
+            labels: _,
+            op: EffectOps::Free,
+        } => {
+            let pointee = unwrap_bril_ptrtype(&heap.get(&args[0])?.ty).clone();
+            build_effect_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| {
+                    if options.check_frees {
+                        let size =
+                            llvm_memory_type_map(context, &pointee, |ty| ty.size_of().unwrap());
+                        builder.build_call(
+                            get_runtime_function(module, "_bril_free")?,
+                            &[v[0].into(), size.into()],
+                            "",
+                        )?;
+                        return Ok(());
+                    }
+                    if options.check_memory {
+                        builder.build_call(
+                            get_runtime_function(module, "_bril_mem_unregister")?,
+                            &[v[0].into()],
+                            "",
+                        )?;
+                    }
+                    builder.build_free(v[0].try_into().unwrap())?;
+                    Ok(())
+                },
+                args,
+            )?;
+        }
+        Instruction::Effect {
+            args: _,
+            funcs,
+            labels: _,
+            op: EffectOps::PrintStr,
+        } => {
+            let print_str = get_runtime_function(module, "_bril_print_str")?;
+            // The text is a constant global, which is shared by every `printstr` of the same text
+            let text = module.get_context().const_string(funcs[0].as_bytes(), true);
+            let global = module
+                .get_globals()
+                .find(|g| {
+                    g.get_name().to_bytes().starts_with(b"_bril_str")
+                        && g.get_initializer() == Some(text.into())
+                })
+                .unwrap_or_else(|| {
+                    let global = module.add_global(text.get_type(), None, "_bril_str");
+                    global.set_initializer(&text);
+                    global.set_constant(true);
+                    global.set_linkage(Linkage::Private);
+                    global
+                });
+            builder.build_call(print_str, &[global.as_pointer_value().into()], "")?;
+        }
+        Instruction::Effect {
+            args,
+            funcs,
+            labels: _,
+            op: EffectOps::Setglobal,
+        } => {
+            let global = WrappedPointer {
+                ty: heap.get(&args[0])?.ty,
+                ptr: module
+                    .get_global(&funcs[0])
+                    .ok_or_else(|| BrilError::UndefinedGlobal(funcs[0].clone()))?
+                    .as_pointer_value(),
+            };
+            build_effect_op(
+                context,
+                builder,
+                heap,
+                fresh,
+                |v| build_store(context, builder, &global, v[0]),
+                args,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Check for instructions that end a block
+const fn is_terminating_instr(i: &Option<Instruction>) -> bool {
+    matches!(
+        i,
+        Some(Instruction::Effect {
+            args: _,
+            funcs: _,
+            labels: _,
+            op: EffectOps::Branch | EffectOps::Jump | EffectOps::Return | EffectOps::Abort,
+        })
+    )
+}
+
+// LLVM's `CallingConv::Fast`
+const FASTCC: u32 = 8;
+
+/// Options that change how a Bril program is compiled
+#[derive(Debug, Clone, Copy, Default)]
+#[allow(clippy::struct_excessive_bools)] // Each option is independent of the others
+pub struct CodegenOptions {
+    /// Use LLVM's `fastcc` calling convention for every Bril function except `main`, which keeps the C calling convention so that it can be called from the entry point
+    pub fastcc: bool,
+    /// Make `add`, `sub`, and `mul` exit with an error when they overflow instead of wrapping around
+    pub trap_overflow: bool,
+    /// Leave dividing by zero with `div`, `rem`, `udiv`, and `urem` undefined instead of checking for it and exiting with an error, for measuring performance
+    pub unchecked_division: bool,
+    /// Make `load`, `store`, `copy`, and `fill` exit with an error when they access memory outside of the allocation that the pointer came from
+    pub check_memory: bool,
+    /// Keep track of every allocation so that freeing one twice, or freeing a pointer into the middle of one, exits with an error, and so does returning from `main` before they have all been freed
+    pub check_frees: bool,
+    /// Keep every `alloc` on the heap, instead of building the ones with a constant count whose pointer never leaves the function with an `alloca` and dropping their `free`
+    pub no_stack_promotion: bool,
+    /// Use `musttail` for a function calling itself in tail position, so that its stack frame is always reused however deep the recursion gets, instead of only marking the call `tail` like other calls in tail position
+    pub strict_tail_calls: bool,
+}
+
+/// Given a Bril program, create an LLVM module from it
+/// The `runtime_module` is the module containing the runtime library
+///
+/// When `library` is true, the program is compiled as a library to be linked with C code or other Bril compilation units. There is no C `main` entry point and every Bril function, including one named `main`, keeps its name.
+/// # Errors
+/// If the program calls a function or uses a variable that doesn't exist, uses a value of the wrong type, or uses something that brillvm can't compile
+pub fn create_module_from_program<'a>(
+    context: &'a Context,
+    program: &Program,
+    runtime_module: Module<'a>,
+    options: CodegenOptions,
+    library: bool,
+) -> Result<Module<'a>, BrilError> {
+    check_program(program)?;
+    build_module(context, program, runtime_module, options, None, library)
+}
+
+/// The errors from compiling a Bril program with brillvm
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrilError {
+    /// There is no function with this name in the program
+    UndefinedFunction {
+        /// The name of the function
+        name: String,
+        /// A function of the program with a similar name, which might have been meant instead
+        suggestion: Option<String>,
+    },
+    /// A variable is used in this function without being assigned anywhere in it
+    UndefinedVariable {
+        /// The name of the variable
+        name: String,
+        /// The function that uses it
+        func: String,
+    },
+    /// A variable is used where no assignment to it can reach
+    UseBeforeDef(UseBeforeDefError),
+    /// There is no global variable with this name in the program
+    UndefinedGlobal(String),
+    /// A value doesn't have the type that it is used as
+    TypeMismatch {
+        /// The type that the value should have
+        expected: Type,
+        /// The type that it has
+        got: Type,
+    },
+    /// An instruction has the wrong number of arguments, labels, or function names for its opcode
+    Arity {
+        /// What is wrong with the instruction
+        error: ArityError,
+        /// The function that it is in
+        func: String,
+    },
+    /// An operand of a `select` doesn't have the type it needs, which is `bool` for the condition and the type of the destination for the two values
+    SelectMismatch {
+        /// The variable that the `select` assigns to
+        dest: String,
+        /// The type that the operand should have
+        expected: Type,
+        /// The type that it has
+        got: Type,
+    },
+    /// An instruction's operands or destination don't have the types that its opcode needs
+    Type {
+        /// What is wrong with the instruction
+        error: TypeError,
+        /// The function that it is in
+        func: String,
+    },
+    /// The program uses something that brillvm can't compile
+    UnsupportedOp(String),
+    /// The runtime library doesn't have a function that the program needs
+    MissingRuntimeFunction(String),
+    /// Another error, along with the instruction that caused it
+    InInstruction {
+        /// The function that the instruction is in
+        func: String,
+        /// The index of the instruction in the function, counting labels
+        index: usize,
+        /// What went wrong
+        error: Box<Self>,
+    },
+    /// LLVM could not build an instruction
+    Builder(String),
+    /// LLVM's verifier rejected the module
+    Verify(String),
+    /// LLVM could not set up code generation for the host
+    Target(String),
+    /// LLVM could not write the object file
+    WriteObject(String),
+}
+
+impl std::fmt::Display for BrilError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UndefinedFunction { name, suggestion } => {
+                write!(f, "there is no function named @{name}")?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, "; did you mean @{suggestion}?")?;
+                }
+                Ok(())
+            }
+            Self::UndefinedVariable { name, func } => {
+                write!(
+                    f,
+                    "the variable {name} is used in @{func} but never assigned"
+                )
+            }
+            Self::UseBeforeDef(e) => write!(f, "{e}"),
+            Self::UndefinedGlobal(name) => write!(f, "there is no global variable named @{name}"),
+            Self::TypeMismatch { expected, got } => {
+                write!(f, "expected a value of type {expected} but got {got}")
+            }
+            Self::Arity { error, func } => write!(f, "{error} in @{func}"),
+            Self::SelectMismatch {
+                dest,
+                expected,
+                got,
+            } => write!(
+                f,
+                "the select assigned to {dest} expected a value of type {expected} but got {got}"
+            ),
+            Self::Type { error, func } => write!(
+                f,
+                "instruction {} of @{func}: {}",
+                error.index, error.description
+            ),
+            Self::UnsupportedOp(what) => write!(f, "brillvm can't compile {what}"),
+            Self::MissingRuntimeFunction(name) => write!(
+                f,
+                "the runtime library has no function named {name}, so it may need to be rebuilt with `make rt`"
+            ),
+            Self::InInstruction { func, index, error } => {
+                write!(f, "instruction {index} of @{func}: {error}")
+            }
+            Self::Builder(e) => write!(f, "could not build an LLVM instruction: {e}"),
+            Self::Verify(e) => write!(f, "the generated LLVM is invalid: {e}"),
+            Self::Target(e) => write!(f, "could not target the host: {e}"),
+            Self::WriteObject(e) => write!(f, "could not write the object file: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for BrilError {}
+
+impl BrilError {
+    // Wraps this error with the instruction at `index` of `func` that caused it
+    fn at(self, func: &str, index: usize) -> Self {
+        Self::InInstruction {
+            func: func.to_string(),
+            index,
+            error: Box::new(self),
+        }
+    }
+}
+
+impl From<BuilderError> for BrilError {
+    fn from(e: BuilderError) -> Self {
+        Self::Builder(e.to_string())
+    }
+}
+
+/// Writes `module` to `output_path` as an object file for the host machine
+/// # Errors
+/// If LLVM can't target the host or write the file
+pub fn write_object_file(module: &Module, output_path: &Path) -> Result<(), BrilError> {
+    Target::initialize_native(&InitializationConfig::default()).map_err(BrilError::Target)?;
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple).map_err(|e| BrilError::Target(e.to_string()))?;
+    let machine = target
+        .create_target_machine(
+            &triple,
+            &TargetMachine::get_host_cpu_name().to_string(),
+            &TargetMachine::get_host_cpu_features().to_string(),
+            OptimizationLevel::Default,
+            RelocMode::PIC,
+            CodeModel::Default,
+        )
+        .ok_or_else(|| BrilError::Target(triple.to_string()))?;
+    module.set_triple(&triple);
+    module.set_data_layout(&machine.get_target_data().get_data_layout());
+    machine
+        .write_to_file(module, FileType::Object, output_path)
+        .map_err(|e| BrilError::WriteObject(e.to_string()))
+}
+
+/// Compiles just the Bril function `fn_name` in `program` to an object file at `output_path`.
+///
+/// The other functions of the program are only declared, so that calls to them are left for the linker. Compiling `main` also includes the C `main` that parses the command line arguments. The runtime library is not included in any of these object files. Write it to its own object file with [`write_object_file`] and link it with the rest.
+/// # Errors
+/// If `fn_name` isn't in `program`, the generated module is invalid, or the object file can't be written
+pub fn compile_function_to_object<'a>(
+    context: &'a Context,
+    program: &Program,
+    fn_name: &str,
+    runtime_module: Module<'a>,
+    output_path: &Path,
+) -> Result<(), BrilError> {
+    if !program.functions.iter().any(|f| f.name == fn_name) {
+        return Err(BrilError::UndefinedFunction {
+            name: fn_name.to_string(),
+            suggestion: closest_name(fn_name, program.functions.iter().map(|f| f.name.as_str())),
+        });
+    }
+    check_program(program)?;
+    // The runtime's definitions stay around for optimizations, but are left out of the object file
+    runtime_module
+        .get_functions()
+        .filter(|f| f.count_basic_blocks() > 0 && f.get_linkage() == Linkage::External)
+        .for_each(|f| f.set_linkage(Linkage::AvailableExternally));
+    runtime_module
+        .get_globals()
+        .filter(|g| g.get_initializer().is_some() && g.get_linkage() == Linkage::External)
+        .for_each(|g| g.set_linkage(Linkage::AvailableExternally));
+
+    let module = build_module(
+        context,
+        program,
+        runtime_module,
+        CodegenOptions::default(),
+        Some(fn_name),
+        false,
+    )?;
+    module
+        .verify()
+        .map_err(|e| BrilError::Verify(e.to_string()))?;
+    write_object_file(&module, output_path)
+}
+
+// Catches the mistakes in a program that would otherwise make building it panic: calls to functions that don't exist, variables and globals that are never defined, constants and call arguments of the wrong type, and `main` arguments that can't come from the command line
+fn check_program(
+    Program {
+        functions,
+        globals,
+        externs,
+        ..
+    }: &Program,
+) -> Result<(), BrilError> {
+    let signatures: HashMap<&str, Vec<&Type>> = functions
+        .iter()
+        .map(|f| {
+            (
+                f.name.as_str(),
+                f.args.iter().map(|a| &a.arg_type).collect(),
+            )
+        })
+        .chain(
+            externs
+                .iter()
+                .map(|e| (e.name.as_str(), e.args.iter().collect())),
+        )
+        .collect();
+    let global_types: HashMap<&str, &Type> =
+        globals.iter().map(|g| (g.name.as_str(), &g.ty)).collect();
+    let expect_type = |expected: &Type, got: &Type| {
+        if expected == got {
+            Ok(())
+        } else {
+            Err(BrilError::TypeMismatch {
+                expected: expected.clone(),
+                got: got.clone(),
+            })
+        }
+    };
+
+    for func in functions {
+        let Function {
+            args,
+            instrs,
+            name,
+            return_type,
+            ..
+        } = func;
+        if name == "main" {
+            if let Some(arg) = args
+                .iter()
+                .find(|a| matches!(a.arg_type, Type::Pointer(_) | Type::Vector(_)))
+            {
+                return Err(BrilError::UnsupportedOp(format!(
+                    "the {} argument {} of @main, since its arguments come from the command line",
+                    arg.arg_type, arg.name
+                )));
+            }
+        }
+
+        let mut types: HashMap<&String, &Type> =
+            args.iter().map(|a| (&a.name, &a.arg_type)).collect();
+        for code in instrs {
+            match code {
+                Code::Instruction(Instruction::Constant {
+                    dest, const_type, ..
+                }) => {
+                    types.insert(dest, const_type);
+                }
+                Code::Instruction(Instruction::Value { dest, op_type, .. }) => {
+                    types.insert(dest, op_type);
+                }
+                _ => {}
+            }
+        }
+        for ty in types.values().copied().chain(return_type) {
+            check_vector_type(ty)?;
+        }
+        let type_of = |var: &String| {
+            types
+                .get(var)
+                .copied()
+                .ok_or_else(|| BrilError::UndefinedVariable {
+                    name: var.clone(),
+                    func: name.clone(),
+                })
+        };
+
+        for (index, code) in instrs.iter().enumerate() {
+            if let Code::Instruction(instr) = code {
+                validate_arity(instr).map_err(|error| BrilError::Arity {
+                    error,
+                    func: name.clone(),
+                })?;
+            }
+            // Besides calls, only `getglobal` and `setglobal` name something in `funcs` that has to exist
+            let (op_args, funcs, call, global) = match code {
+                Code::Label { .. } => continue,
+                Code::Instruction(Instruction::Constant {
+                    const_type, value, ..
+                }) => {
+                    // `null` has no type of its own to compare against
+                    if !matches!(value, Literal::Null) && !value.fits_type(const_type) {
+                        expect_type(const_type, &value.get_type())
+                            .map_err(|e| e.at(name, index))?;
+                    }
+                    continue;
+                }
+                Code::Instruction(Instruction::Value {
+                    op: ValueOps::Phi,
+                    args,
+                    ..
+                }) => {
+                    for a in args.iter().filter(|a| *a != UNDEFINED) {
+                        type_of(a).map_err(|e| e.at(name, index))?;
+                    }
+                    continue;
+                }
+                // LLVM's select needs both values to have the same type, and `validate_arity` has already made sure there are three operands
+                Code::Instruction(Instruction::Value {
+                    op: ValueOps::Select,
+                    args,
+                    dest,
+                    op_type,
+                    ..
+                }) => {
+                    for (arg, expected) in args.iter().zip([&Type::Bool, op_type, op_type]) {
+                        let got = type_of(arg).map_err(|e| e.at(name, index))?;
+                        if got != expected {
+                            return Err(BrilError::SelectMismatch {
+                                dest: dest.clone(),
+                                expected: expected.clone(),
+                                got: got.clone(),
+                            }
+                            .at(name, index));
+                        }
+                    }
+                    continue;
+                }
+                Code::Instruction(Instruction::Value {
+                    op, args, funcs, ..
+                }) => (
                     args,
-                    dest,
-                );
-            });
+                    funcs,
+                    *op == ValueOps::Call,
+                    *op == ValueOps::Getglobal,
+                ),
+                Code::Instruction(Instruction::Effect {
+                    op, args, funcs, ..
+                }) => (
+                    args,
+                    funcs,
+                    *op == EffectOps::Call,
+                    *op == EffectOps::Setglobal,
+                ),
+            };
+            let arg_types = op_args
+                .iter()
+                .map(type_of)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.at(name, index))?;
+            if call {
+                let params = signatures.get(funcs[0].as_str()).ok_or_else(|| {
+                    BrilError::UndefinedFunction {
+                        name: funcs[0].clone(),
+                        suggestion: closest_name(&funcs[0], signatures.keys().copied()),
+                    }
+                    .at(name, index)
+                })?;
+                for (param, arg) in params.iter().zip(&arg_types) {
+                    expect_type(param, arg).map_err(|e| e.at(name, index))?;
+                }
+            } else if global && !global_types.contains_key(funcs[0].as_str()) {
+                return Err(BrilError::UndefinedGlobal(funcs[0].clone()).at(name, index));
+            }
         }
-        Instruction::Value {
-            args,
-            dest,
-            funcs: _,
-            labels: _,
-            op: ValueOps::PtrAdd,
-            op_type,
-        } => {
-            let name = fresh.fresh_var();
-            let op_type = unwrap_bril_ptrtype(op_type);
-            build_op(
-                context,
-                builder,
-                heap,
-                fresh,
-                |v| unsafe {
-                    llvm_type_map(context, op_type, |pointee_ty| {
-                        builder
-                            .build_gep(
-                                pointee_ty,
-                                v[0].try_into().unwrap(),
-                                &[v[1].try_into().unwrap()],
-                                &name,
-                            )
-                            .unwrap()
-                            .into()
-                    })
-                },
-                args,
-                dest,
-            );
+        if let Some(error) = check_use_before_def(func).into_iter().next() {
+            return Err(BrilError::UseBeforeDef(error));
         }
-        Instruction::Effect {
-            args,
-            funcs: _,
-            labels: _,
-            op: EffectOps::Store,
-        } => {
-            build_effect_op(
-                context,
-                builder,
-                heap,
-                fresh,
-                |v| {
-                    builder.build_store(v[0].try_into().unwrap(), v[1]).unwrap();
-                },
-                args,
-            );
+        if let Some(error) = type_check_function(func).into_iter().next() {
+            return Err(BrilError::Type {
+                error,
+                func: name.clone(),
+            });
         }
-        Instruction::Effect {
-            args,
-            funcs: _,
-            labels: _,
-            op: EffectOps::Free,
-        } => {
-            build_effect_op(
-                context,
-                builder,
-                heap,
-                fresh,
-                |v| {
-                    builder.build_free(v[0].try_into().unwrap()).unwrap();
-                },
-                args,
-            );
+    }
+    Ok(())
+}
+
+// The number of characters that have to be inserted, removed, or replaced to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let replaced = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(diagonal + 1);
         }
     }
+    row[b.len()]
 }
 
-// Check for instructions that end a block
-const fn is_terminating_instr(i: &Option<Instruction>) -> bool {
-    matches!(
-        i,
-        Some(Instruction::Effect {
-            args: _,
-            funcs: _,
-            labels: _,
-            op: EffectOps::Branch | EffectOps::Jump | EffectOps::Return,
+// The name in `names` closest to `name`, if it is close enough that `name` is probably a typo of it
+fn closest_name<'n>(name: &str, names: impl Iterator<Item = &'n str>) -> Option<String> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    names
+        .map(|n| (edit_distance(name, n), n))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min()
+        .map(|(_, n)| n.to_string())
+}
+
+// Vectors can only hold `int` or `float`, including when they are behind a pointer
+fn check_vector_type(ty: &Type) -> Result<(), BrilError> {
+    match ty {
+        Type::Pointer(ty) => check_vector_type(ty),
+        Type::Vector(elem) if !matches!(**elem, Type::Int | Type::Float) => {
+            Err(BrilError::UnsupportedOp(format!(
+                "the type {ty}, since vectors can only hold int or float"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Whether any function prints a pointer. If one does, every `alloc` is recorded by the runtime so that pointers print the same way on every run, as which allocation they point into and how far
+fn prints_pointers(functions: &[Function]) -> bool {
+    functions.iter().any(|Function { args, instrs, .. }| {
+        let pointers: HashSet<&String> = args
+            .iter()
+            .filter(|a| matches!(a.arg_type, Type::Pointer(_)))
+            .map(|a| &a.name)
+            .chain(instrs.iter().filter_map(|i| match i {
+                Code::Instruction(
+                    Instruction::Constant {
+                        dest,
+                        const_type: Type::Pointer(_),
+                        ..
+                    }
+                    | Instruction::Value {
+                        dest,
+                        op_type: Type::Pointer(_),
+                        ..
+                    },
+                ) => Some(dest),
+                _ => None,
+            }))
+            .collect();
+        instrs.iter().any(|i| {
+            matches!(i, Code::Instruction(Instruction::Effect {
+                op: EffectOps::Print,
+                args,
+                ..
+            }) if args.iter().any(|a| pointers.contains(a)))
         })
-    )
+    })
 }
 
-/// Given a Bril program, create an LLVM module from it
-/// The `runtime_module` is the module containing the runtime library
-/// # Panics
-/// Panics if the program is invalid
-#[must_use]
-pub fn create_module_from_program<'a>(
+// Allocations with more entries than this stay on the heap, so that promoting them can't overflow the stack
+const MAX_STACK_ALLOC_COUNT: u64 = 1024;
+
+// The `alloc`s of `func` that can be built with an `alloca` in the entry block instead of `malloc`, by their index in `instrs` along with their count, and the indices of the `free`s that go with them.
+// An `alloc` qualifies when its count comes from a single `const`, it isn't on a cycle of the control flow graph, and its pointer never leaves the function by being passed to a call, stored in memory, returned, printed, turned into an int, or put in a global. It also has to be freed only through the variable it was assigned to, since a `free` of anything else might be freeing a pointer from `malloc` instead
+fn promotable_allocs(func: &Function) -> (BTreeMap<usize, u64>, HashSet<usize>) {
+    let mut assignments: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, code) in func.instrs.iter().enumerate() {
+        if let Code::Instruction(
+            Instruction::Constant { dest, .. } | Instruction::Value { dest, .. },
+        ) = code
+        {
+            assignments.entry(dest).or_default().push(index);
+        }
+    }
+    let assigned_once = |var: &str| match assignments.get(var).map(Vec::as_slice) {
+        Some(&[index]) => Some(index),
+        _ => None,
+    };
+
+    let cfg = ControlFlowGraph::build(func);
+    let on_cycle = |label: &str| {
+        let mut seen = HashSet::new();
+        let mut stack: Vec<&str> = cfg.successors[label].iter().map(String::as_str).collect();
+        while let Some(b) = stack.pop() {
+            if b == label {
+                return true;
+            }
+            if seen.insert(b) {
+                stack.extend(cfg.successors[b].iter().map(String::as_str));
+            }
+        }
+        false
+    };
+
+    let mut allocs = BTreeMap::new();
+    let mut frees = HashSet::new();
+    for info in &cfg.blocks {
+        if on_cycle(&info.label) {
+            continue;
+        }
+        for index in info.start..info.end {
+            let Code::Instruction(Instruction::Value {
+                args,
+                dest,
+                op: ValueOps::Alloc,
+                ..
+            }) = &func.instrs[index]
+            else {
+                continue;
+            };
+            let count = match assigned_once(&args[0]).map(|i| &func.instrs[i]) {
+                Some(Code::Instruction(Instruction::Constant {
+                    value: Literal::Int(count),
+                    ..
+                })) => *count,
+                _ => continue,
+            };
+            let Ok(count) = u64::try_from(count) else {
+                continue;
+            };
+            if count == 0 || count > MAX_STACK_ALLOC_COUNT || assigned_once(dest).is_none() {
+                continue;
+            }
+
+            // The variables that might hold a pointer into this allocation
+            let mut derived = HashSet::from([dest.as_str()]);
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for code in &func.instrs {
+                    if let Code::Instruction(Instruction::Value {
+                        args,
+                        dest,
+                        op: ValueOps::PtrAdd | ValueOps::Id | ValueOps::Phi | ValueOps::Select,
+                        ..
+                    }) = code
+                    {
+                        if args.iter().any(|a| derived.contains(a.as_str()))
+                            && derived.insert(dest.as_str())
+                        {
+                            changed = true;
+                        }
+                    }
+                }
+            }
+
+            let mut alloc_frees = Vec::new();
+            let escapes = func.instrs.iter().enumerate().any(|(i, code)| match code {
+                Code::Label { .. } | Code::Instruction(Instruction::Constant { .. }) => false,
+                Code::Instruction(Instruction::Value { op, args, .. }) => {
+                    !matches!(
+                        op,
+                        ValueOps::PtrAdd
+                            | ValueOps::Id
+                            | ValueOps::Phi
+                            | ValueOps::Select
+                            | ValueOps::Load
+                            | ValueOps::PtrDiff
+                            | ValueOps::Peq
+                            | ValueOps::Plt
+                            | ValueOps::Pgt
+                            | ValueOps::Ple
+                            | ValueOps::Pge
+                            | ValueOps::Vload
+                    ) && args.iter().any(|a| derived.contains(a.as_str()))
+                }
+                Code::Instruction(Instruction::Effect {
+                    op: EffectOps::Free,
+                    args,
+                    ..
+                }) => {
+                    if args[0] == *dest {
+                        alloc_frees.push(i);
+                    }
+                    args[0] != *dest && derived.contains(args[0].as_str())
+                }
+                // Both write their second argument into memory, which might outlive the frame
+                Code::Instruction(Instruction::Effect {
+                    op: EffectOps::Store | EffectOps::Fill,
+                    args,
+                    ..
+                }) => derived.contains(args[1].as_str()),
+                Code::Instruction(Instruction::Effect { op, args, .. }) => {
+                    !matches!(op, EffectOps::MemCopy | EffectOps::Vstore)
+                        && args.iter().any(|a| derived.contains(a.as_str()))
+                }
+            });
+            if !escapes {
+                allocs.insert(index, count);
+                frees.extend(alloc_frees);
+            }
+        }
+    }
+    (allocs, frees)
+}
+
+// Builds every function of the program into `runtime_module`, or just the one named `only` with the rest declared
+fn build_module<'a>(
     context: &'a Context,
-    Program { functions, .. }: &Program,
+    Program {
+        functions,
+        globals,
+        externs,
+        ..
+    }: &Program,
     runtime_module: Module<'a>,
-) -> Module<'a> {
+    options: CodegenOptions,
+    only: Option<&str>,
+    library: bool,
+) -> Result<Module<'a>, BrilError> {
     let builder = context.create_builder();
+    let track_allocations = prints_pointers(functions);
+
+    // Globals are defined along with `main` when building one function at a time, so that linking the objects together doesn't define them twice
+    for GlobalVar { name, ty, init } in globals {
+        // Stored like a variable, so a `bool` is an `i8` that `build_load` and `build_store` can use
+        let global =
+            llvm_memory_type_map(context, ty, |ty| runtime_module.add_global(ty, None, name));
+        if only.is_none_or(|only| only == "main") {
+            let init = match init {
+                Literal::Bool(b) => context.i8_type().const_int(u64::from(*b), false).into(),
+                _ => build_literal(context, ty, init),
+            };
+            global.set_initializer(&init);
+        }
+    }
+
+    // Functions from outside of Bril are only declared, for the linker to find, and calls find them by name like any other function
+    for ExternFunc {
+        name,
+        args,
+        return_type,
+    } in externs
+    {
+        let ty = build_functiontype(context, &args.iter().collect::<Vec<_>>(), return_type);
+        runtime_module.add_function(name, ty, Some(Linkage::External));
+    }
 
     // "Global" counter for creating labels/temp variable names
     let mut fresh = Fresh::new();
@@ -1415,7 +3882,7 @@ pub fn create_module_from_program<'a>(
     let funcs: Vec<_> = functions
         .iter()
         .map(
-            |Function {
+            |func @ Function {
                  args,
                  instrs,
                  name,
@@ -1431,9 +3898,11 @@ pub fn create_module_from_program<'a>(
                     return_type,
                 );
 
-                let func_name = if name == "main" { "_main" } else { name };
-
-                let llvm_func = runtime_module.add_function(func_name, ty, None);
+                let llvm_func =
+                    runtime_module.add_function(llvm_function_name(name, library), ty, None);
+                if options.fastcc && name != "main" {
+                    llvm_func.set_call_conventions(FASTCC);
+                }
                 args.iter().zip(llvm_func.get_param_iter()).for_each(
                     |(Argument { name, .. }, bve)| match bve {
                         inkwell::values::BasicValueEnum::IntValue(i) => i.set_name(name),
@@ -1445,141 +3914,254 @@ pub fn create_module_from_program<'a>(
                     },
                 );
 
+                // Functions that aren't being built are left as declarations
+                if only.is_some_and(|only| only != name) {
+                    return Ok(None);
+                }
+
                 // For each function, we also need to push all variables onto the stack
-                let mut heap = Heap::new();
+                let mut heap = Heap::new(name);
                 let block = context.append_basic_block(llvm_func, &fresh.fresh_label());
                 builder.position_at_end(block);
 
-                llvm_func.get_param_iter().enumerate().for_each(|(i, arg)| {
-                    let Argument { name, arg_type } = &args[i];
-                    let ptr = heap.add(&builder, context, name, arg_type).ptr;
-                    builder.build_store(ptr, arg).unwrap();
-                });
+                for (Argument { name, arg_type }, arg) in
+                    args.iter().zip(llvm_func.get_param_iter())
+                {
+                    let ptr = heap.add(&builder, context, name, arg_type)?;
+                    build_store(context, &builder, &ptr, arg)?;
+                }
 
-                instrs.iter().for_each(|i| match i {
-                    Code::Label { .. } | Code::Instruction(Instruction::Effect { .. }) => {}
-                    Code::Instruction(Instruction::Constant {
-                        dest, const_type, ..
-                    }) => {
-                        heap.add(&builder, context, dest, const_type);
-                    }
-                    Code::Instruction(Instruction::Value { dest, op_type, .. }) => {
-                        heap.add(&builder, context, dest, op_type);
+                for i in instrs {
+                    match i {
+                        Code::Label { .. } | Code::Instruction(Instruction::Effect { .. }) => {}
+                        Code::Instruction(Instruction::Constant {
+                            dest, const_type, ..
+                        }) => {
+                            heap.add(&builder, context, dest, const_type)?;
+                        }
+                        Code::Instruction(Instruction::Value { dest, op_type, .. }) => {
+                            heap.add(&builder, context, dest, op_type)?;
+                        }
                     }
+                }
+
+                let speculates = instrs.iter().any(|i| {
+                    matches!(
+                        i,
+                        Code::Instruction(Instruction::Effect {
+                            op: EffectOps::Speculate,
+                            ..
+                        })
+                    )
                 });
+                if speculates {
+                    heap.add_snapshot(&builder, context)?;
+                }
+
+                // The runtime has to see every allocation to print pointers or check them
+                let (promoted, frees) = if options.no_stack_promotion
+                    || options.check_memory
+                    || options.check_frees
+                    || track_allocations
+                {
+                    (BTreeMap::new(), HashSet::new())
+                } else {
+                    promotable_allocs(func)
+                };
+                let mut allocas = HashMap::new();
+                for (index, count) in promoted {
+                    let Code::Instruction(Instruction::Value { op_type, .. }) = &instrs[index]
+                    else {
+                        unreachable!()
+                    };
+                    let count = context.i64_type().const_int(count, false);
+                    let alloca =
+                        llvm_memory_type_map(context, unwrap_bril_ptrtype(op_type), |ty| {
+                            builder.build_array_alloca(ty, count, &fresh.fresh_var())
+                        })?;
+                    allocas.insert(index, alloca);
+                }
 
-                (llvm_func, instrs, block, heap)
+                Ok(Some((name, llvm_func, instrs, block, heap, allocas, frees)))
             },
         )
-        .collect(); // Important to collect, can't be done lazily because we need all functions to be loaded in before a call instruction of a function is processed.
+        .collect::<Result<_, BrilError>>()?; // Important to collect, can't be done lazily because we need all functions to be loaded in before a call instruction of a function is processed.
 
     // Now actually build each function
-    funcs
-        .into_iter()
-        .for_each(|(llvm_func, instrs, mut block, heap)| {
-            let mut last_instr = None;
+    for (name, llvm_func, instrs, mut block, heap, allocas, frees) in funcs.into_iter().flatten() {
+        let mut last_instr = None;
 
-            // If their are actually instructions, proceed
-            if !instrs.is_empty() {
-                builder.position_at_end(block);
+        // If their are actually instructions, proceed
+        if !instrs.is_empty() {
+            builder.position_at_end(block);
 
-                // Maps labels to llvm blocks for jumps
-                let mut block_map = HashMap::new();
-                let mut index = 0;
-                while index < instrs.len() {
-                    if is_terminating_instr(&last_instr)
-                        && matches!(instrs[index], Code::Instruction { .. })
-                    {
-                        index += 1;
-                        continue;
-                    }
+            // Maps labels to llvm blocks for jumps
+            let mut block_map = HashMap::new();
+            // The LLVM block that each label's code ends in, which is where its phi arguments come from
+            let mut block_ends = HashMap::new();
+            let mut current_label: Option<&String> = None;
+            let mut pending_phis = Vec::new();
+            let mut index = 0;
+            while index < instrs.len() {
+                if is_terminating_instr(&last_instr)
+                    && matches!(instrs[index], Code::Instruction { .. })
+                {
+                    index += 1;
+                    continue;
+                }
 
-                    let mut phi_index = index;
-                    let mut phi_ptrs = vec![];
-                    while phi_index < instrs.len() && is_phi(&instrs[phi_index]) {
-                        match &instrs[phi_index] {
-                            Code::Instruction(instr) => {
-                                phi_ptrs.push((
-                                    instr.clone(),
-                                    build_phi(
-                                        instr,
-                                        context,
-                                        &runtime_module,
-                                        &builder,
-                                        &heap,
-                                        &mut block_map,
-                                        llvm_func,
-                                        &mut fresh,
-                                    ),
-                                ));
-                                last_instr = Some(instr.clone());
-                            }
-                            Code::Label { .. } => unreachable!(),
+                let mut phi_index = index;
+                let mut phi_ptrs = vec![];
+                while phi_index < instrs.len() && is_phi(&instrs[phi_index]) {
+                    match &instrs[phi_index] {
+                        Code::Instruction(instr) => {
+                            let (phi, incoming) = build_phi(
+                                instr,
+                                context,
+                                &runtime_module,
+                                &builder,
+                                &heap,
+                                &mut fresh,
+                            )
+                            .map_err(|e| e.at(name, phi_index))?;
+                            phi_ptrs.push((
+                                phi_index,
+                                instr.clone(),
+                                phi.as_basic_value().into_pointer_value(),
+                            ));
+                            pending_phis.push((phi, incoming));
+                            last_instr = Some(instr.clone());
                         }
-                        phi_index += 1;
+                        Code::Label { .. } => unreachable!(),
                     }
+                    phi_index += 1;
+                }
+
+                for (phi_index, instr, phi) in phi_ptrs {
+                    finish_phi(
+                        &instr,
+                        context,
+                        &runtime_module,
+                        &builder,
+                        &heap,
+                        &mut fresh,
+                        phi,
+                    )
+                    .map_err(|e| e.at(name, phi_index))?;
+                }
+                if phi_index > index {
+                    index = phi_index;
+                    continue;
+                }
+
+                match &instrs[index] {
+                    bril_rs::Code::Label { label, .. } => {
+                        let new_block = block_map_get(context, llvm_func, &mut block_map, label);
+
+                        // Check if wee need to insert a jump since all llvm blocks must be terminated
+                        if !is_terminating_instr(&last_instr) {
+                            builder.build_unconditional_branch(block_map_get(
+                                context,
+                                llvm_func,
+                                &mut block_map,
+                                label,
+                            ))?;
+                        }
+
+                        if let Some(l) = current_label.replace(label) {
+                            block_ends.insert(l, builder.get_insert_block().unwrap());
+                        }
 
-                    for (instr, phi) in phi_ptrs {
-                        finish_phi(
-                            &instr,
+                        // Start a new block
+                        block = new_block;
+                        builder.position_at_end(block);
+                        last_instr = None;
+                    }
+                    bril_rs::Code::Instruction(i) if frees.contains(&index) => {
+                        last_instr = Some(i.clone());
+                    }
+                    bril_rs::Code::Instruction(i @ Instruction::Value { dest, .. })
+                        if allocas.contains_key(&index) =>
+                    {
+                        build_store(context, &builder, &heap.get(dest)?, allocas[&index])
+                            .map_err(|e| e.at(name, index))?;
+                        last_instr = Some(i.clone());
+                    }
+                    bril_rs::Code::Instruction(i)
+                        if is_tail_call(
+                            instrs,
+                            index,
+                            llvm_func.get_type().get_return_type().is_some(),
+                        ) =>
+                    {
+                        build_tail_call(
+                            i,
                             context,
                             &runtime_module,
                             &builder,
                             &heap,
+                            llvm_func,
                             &mut fresh,
-                            phi,
-                        );
+                            library,
+                            options,
+                        )
+                        .map_err(|e| e.at(name, index))?;
+                        last_instr = Some(i.clone());
+                        // The call has already returned its result, so the `ret` after it is done too
+                        if let (Instruction::Value { .. }, Some(Code::Instruction(ret))) =
+                            (i, instrs.get(index + 1))
+                        {
+                            last_instr = Some(ret.clone());
+                            index += 1;
+                        }
                     }
-                    if phi_index > index {
-                        index = phi_index;
-                        continue;
+                    bril_rs::Code::Instruction(i) => {
+                        build_instruction(
+                            i,
+                            context,
+                            &runtime_module,
+                            &builder,
+                            &heap,
+                            &mut block_map,
+                            llvm_func,
+                            &mut fresh,
+                            library,
+                            track_allocations,
+                            options,
+                        )
+                        .map_err(|e| e.at(name, index))?;
+                        last_instr = Some(i.clone());
                     }
+                }
+                index += 1;
+            }
 
-                    match &instrs[index] {
-                        bril_rs::Code::Label { label, .. } => {
-                            let new_block =
-                                block_map_get(context, llvm_func, &mut block_map, label);
-
-                            // Check if wee need to insert a jump since all llvm blocks must be terminated
-                            if !is_terminating_instr(&last_instr) {
-                                builder
-                                    .build_unconditional_branch(block_map_get(
-                                        context,
-                                        llvm_func,
-                                        &mut block_map,
-                                        label,
-                                    ))
-                                    .unwrap();
-                            }
-
-                            // Start a new block
-                            block = new_block;
-                            builder.position_at_end(block);
-                            last_instr = None;
-                        }
-                        bril_rs::Code::Instruction(i) => {
-                            build_instruction(
-                                i,
-                                context,
-                                &runtime_module,
-                                &builder,
-                                &heap,
-                                &mut block_map,
-                                llvm_func,
-                                &mut fresh,
-                            );
-                            last_instr = Some(i.clone());
-                        }
-                    }
-                    index += 1;
+            if let Some(l) = current_label {
+                block_ends.insert(l, builder.get_insert_block().unwrap());
+            }
+            for (phi, incoming) in pending_phis {
+                for (ptr, label) in incoming {
+                    let block = block_ends.get(label).copied().unwrap_or_else(|| {
+                        block_map_get(context, llvm_func, &mut block_map, label)
+                    });
+                    phi.add_incoming(&[(&ptr, block)]);
                 }
             }
+        }
 
-            // Make sure every function is terminated with a return if not already
-            if !is_terminating_instr(&last_instr) {
-                builder.build_return(None).unwrap();
+        // Make sure every function is terminated with a return if not already. A function that returns a value can't fall off the end, so that path is unreachable
+        if !is_terminating_instr(&last_instr) {
+            if llvm_func.get_type().get_return_type().is_some() {
+                builder.build_unreachable()?;
+            } else {
+                builder.build_return(None)?;
             }
-        });
+        }
+    }
+
+    if library || only.is_some_and(|only| only != "main") {
+        return Ok(runtime_module);
+    }
 
     // Add new main function to act as a entry point to the function.
     // Sets up arguments for a _main call
@@ -1598,58 +4180,63 @@ pub fn create_module_from_program<'a>(
     let entry_block = context.append_basic_block(entry_func, &fresh.fresh_label());
     builder.position_at_end(entry_block);
 
-    let mut heap = Heap::new();
+    let mut heap = Heap::new("main");
 
     if let Some(function) = runtime_module.get_function("_main") {
         let Function { args, .. } = functions
             .iter()
             .find(|Function { name, .. }| name == "main")
-            .unwrap();
+            .ok_or_else(|| BrilError::UndefinedFunction {
+                name: "main".to_string(),
+                suggestion: None,
+            })?;
 
         let argv = entry_func.get_nth_param(1).unwrap().into_pointer_value();
 
-        let parse_int = runtime_module.get_function("_bril_parse_int").unwrap();
-        let parse_bool = runtime_module.get_function("_bril_parse_bool").unwrap();
-        let parse_float = runtime_module.get_function("_bril_parse_float").unwrap();
-
-        function.get_param_iter().enumerate().for_each(|(i, _)| {
-            let Argument { name, arg_type } = &args[i];
-            let ptr = heap.add(&builder, context, name, arg_type).ptr;
-            let arg_str = builder
-                .build_load(
-                    context.ptr_type(AddressSpace::default()),
-                    unsafe {
-                        builder.build_in_bounds_gep(
-                            context.ptr_type(AddressSpace::default()),
-                            argv,
-                            &[context.i64_type().const_int((i + 1) as u64, true)],
-                            "calculate offset",
-                        )
-                    }
-                    .unwrap(),
-                    "load arg",
-                )
-                .unwrap();
+        let parse_int = get_runtime_function(&runtime_module, "_bril_parse_int")?;
+        let parse_bool = get_runtime_function(&runtime_module, "_bril_parse_bool")?;
+        let parse_float = get_runtime_function(&runtime_module, "_bril_parse_float")?;
+        let parse_char = get_runtime_function(&runtime_module, "_bril_parse_char")?;
+
+        for (i, Argument { name, arg_type }) in args.iter().enumerate() {
+            let ptr = heap.add(&builder, context, name, arg_type)?;
+            let arg_str = builder.build_load(
+                context.ptr_type(AddressSpace::default()),
+                unsafe {
+                    builder.build_in_bounds_gep(
+                        context.ptr_type(AddressSpace::default()),
+                        argv,
+                        &[context.i64_type().const_int((i + 1) as u64, true)],
+                        "calculate offset",
+                    )
+                }?,
+                "load arg",
+            )?;
             let arg = match arg_type {
                 Type::Int => builder
-                    .build_call(parse_int, &[arg_str.into()], "parse_int")
-                    .unwrap()
+                    .build_call(parse_int, &[arg_str.into()], "parse_int")?
                     .try_as_basic_value()
                     .unwrap_left(),
                 Type::Bool => builder
-                    .build_call(parse_bool, &[arg_str.into()], "parse_bool")
-                    .unwrap()
+                    .build_call(parse_bool, &[arg_str.into()], "parse_bool")?
                     .try_as_basic_value()
                     .unwrap_left(),
                 Type::Float => builder
-                    .build_call(parse_float, &[arg_str.into()], "parse_float")
-                    .unwrap()
+                    .build_call(parse_float, &[arg_str.into()], "parse_float")?
+                    .try_as_basic_value()
+                    .unwrap_left(),
+                Type::Char => builder
+                    .build_call(parse_char, &[arg_str.into()], "parse_char")?
                     .try_as_basic_value()
                     .unwrap_left(),
-                Type::Pointer(_) => unreachable!(),
+                Type::Pointer(_) | Type::Vector(_) => {
+                    return Err(BrilError::UnsupportedOp(format!(
+                        "the {arg_type} argument {name} of @main, since its arguments come from the command line"
+                    )))
+                }
             };
-            builder.build_store(ptr, arg).unwrap();
-        });
+            build_store(context, &builder, &ptr, arg)?;
+        }
 
         build_effect_op(
             context,
@@ -1665,21 +4252,62 @@ pub fn create_module_from_program<'a>(
                             .collect::<Vec<_>>()
                             .as_slice(),
                         "call main",
-                    )
-                    .unwrap();
+                    )?
+                    .set_call_convention(function.get_call_conventions());
+                Ok(())
             },
             &args
                 .iter()
                 .map(|Argument { name, .. }| name.clone())
                 .collect::<Vec<String>>(),
-        );
+        )?;
     }
-    builder
-        .build_return(Some(&context.i32_type().const_int(0, true)))
-        .unwrap();
+    // Checked here rather than in an `atexit` hook, which would also run after an error and after `-i` has freed the code of the program
+    if options.check_frees {
+        builder.build_call(
+            get_runtime_function(&runtime_module, "_bril_check_leaks")?,
+            &[],
+            "",
+        )?;
+    }
+    builder.build_return(Some(&context.i32_type().const_int(0, true)))?;
 
     // Return the module
-    runtime_module
+    Ok(runtime_module)
+}
+
+/// Checks that every call uses the same calling convention as the function it calls, which LLVM's verifier does not catch
+/// # Errors
+/// Describes the first call whose calling convention doesn't match its callee
+pub fn verify_calling_conventions(module: &Module) -> Result<(), String> {
+    for caller in module.get_functions() {
+        for block in caller.get_basic_blocks() {
+            for instr in block.get_instructions() {
+                let Ok(call) = CallSiteValue::try_from(instr) else {
+                    continue;
+                };
+                let callee = call.get_called_fn_value();
+                if call.get_call_convention() != callee.get_call_conventions() {
+                    return Err(format!(
+                        "call to {} in {} uses calling convention {} but the callee uses {}",
+                        callee.get_name().to_string_lossy(),
+                        caller.get_name().to_string_lossy(),
+                        call.get_call_convention(),
+                        callee.get_call_conventions()
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the LLVM IR of just the function named `func_name` in `module`, or [`None`] if there isn't one. The Bril function `main` is named `_main` in the module, while `main` is the wrapper that parses the command line arguments.
+#[must_use]
+pub fn dump_function_ir(module: &Module, func_name: &str) -> Option<String> {
+    module
+        .get_function(func_name)
+        .map(|f| f.print_to_string().to_string())
 }
 
 pub(crate) fn is_phi(i: &Code) -> bool {
@@ -1692,50 +4320,39 @@ pub(crate) fn is_phi(i: &Code) -> bool {
     )
 }
 
+// The stack locations that a phi chooses between, with the label of the block that each comes from
+type PhiIncoming<'a, 'b> = Vec<(PointerValue<'a>, &'b String)>;
+
 // The workhorse of converting a Bril Instruction to an LLVM Instruction
-#[allow(clippy::too_many_arguments)]
 fn build_phi<'a, 'b>(
     i: &'b Instruction,
     context: &'a Context,
     _module: &'a Module,
     builder: &'a Builder,
     heap: &Heap<'a, 'b>,
-    block_map: &mut HashMap<String, BasicBlock<'a>>,
-    llvm_func: FunctionValue<'a>,
     fresh: &mut Fresh,
-) -> PointerValue<'a> {
+) -> Result<(PhiValue<'a>, PhiIncoming<'a, 'b>), BrilError> {
     match i {
         Instruction::Value {
             args,
-            dest: _,
+            dest,
             funcs: _,
             labels,
             op: ValueOps::Phi,
             op_type: _,
         } => {
             let name = fresh.fresh_var();
-            let blocks = labels
-                .iter()
-                .map(|l| block_map_get(context, llvm_func, block_map, l))
-                .collect::<Vec<_>>();
+            let phi = builder.build_phi(context.ptr_type(AddressSpace::default()), &name)?;
 
-            let phi = builder
-                .build_phi(context.ptr_type(AddressSpace::default()), &name)
-                .unwrap();
-
-            let pointers = args.iter().map(|a| heap.get(a).ptr).collect::<Vec<_>>();
+            // A variable that isn't assigned along some path doesn't have a stack location. Loading from `dest` instead leaves it unchanged, which is as good as any other value
+            let pointers = args
+                .iter()
+                .map(|a| Ok(heap.get(if a == UNDEFINED { dest } else { a })?.ptr))
+                .collect::<Result<Vec<_>, BrilError>>()?;
 
             // The phi node is a little non-standard since we can't load in values from the stack before the phi instruction. Instead, the phi instruction will be over stack locations which will then be loaded into the corresponding output location.
-            phi.add_incoming(
-                pointers
-                    .iter()
-                    .zip(blocks.iter())
-                    .map(|(val, block)| (val as &dyn BasicValue, *block))
-                    .collect::<Vec<_>>()
-                    .as_slice(),
-            );
-
-            phi.as_basic_value().into_pointer_value()
+            // The incoming blocks are added once the whole function is built, since runtime checks can split a Bril block into several LLVM blocks and only the last one branches here
+            Ok((phi, pointers.into_iter().zip(labels).collect()))
         }
         _ => unreachable!(),
     }
@@ -1751,7 +4368,7 @@ fn finish_phi<'a, 'b>(
     heap: &Heap<'a, 'b>,
     fresh: &mut Fresh,
     ptr: PointerValue<'a>,
-) {
+) -> Result<(), BrilError> {
     match i {
         Instruction::Value {
             args: _,
@@ -1761,20 +4378,21 @@ fn finish_phi<'a, 'b>(
             op: ValueOps::Phi,
             op_type,
         } => {
-            builder
-                .build_store(
-                    heap.get(dest).ptr,
-                    build_load(
-                        context,
-                        builder,
-                        &WrappedPointer {
-                            ty: op_type.clone(),
-                            ptr,
-                        },
-                        &fresh.fresh_var(),
-                    ),
-                )
-                .unwrap();
+            build_store(
+                context,
+                builder,
+                &heap.get(dest)?,
+                build_load(
+                    context,
+                    builder,
+                    &WrappedPointer {
+                        ty: op_type.clone(),
+                        ptr,
+                    },
+                    &fresh.fresh_var(),
+                )?,
+            )?;
+            Ok(())
         }
         _ => unreachable!(),
     }
@@ -1,8 +1,22 @@
-use brillvm::{cli::run, cli::Cli};
+use brillvm::cli::{record, replay, run, Cli};
 use clap::Parser;
 
 fn main() {
     let args = Cli::parse();
 
-    println!("{}", run(&args));
+    if let Some(path) = &args.replay {
+        std::process::exit(replay(&args, path));
+    }
+    if let Some(path) = &args.record_repro {
+        record(&args, path);
+        return;
+    }
+
+    match run(&args) {
+        Ok(output) => print!("{output}"),
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::exit(2);
+        }
+    }
 }
@@ -0,0 +1,5 @@
+use bril_rs::{load_program, mlir::to_mlir};
+
+fn main() {
+    print!("{}", to_mlir(&load_program()));
+}
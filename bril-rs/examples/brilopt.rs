@@ -0,0 +1,145 @@
+use bril_rs::analysis::{find_natural_loops, ControlFlowGraph, DominanceTree};
+use bril_rs::passes::ssa::convert_to_ssa;
+use bril_rs::passes::{
+    eliminate_common_subexpressions, eliminate_dead_code, fold_constants, global_value_number,
+    hoist_loop_invariants, inline_function, propagate_copies,
+};
+use bril_rs::{load_program, Code, EffectOps, Function, Instruction, Program, ValueOps};
+
+// Inlines the first call in `func` to a function defined in `program`
+fn inline_first_call(func: &Function, program: &Program) -> Function {
+    func.instrs
+        .iter()
+        .enumerate()
+        .find_map(|(i, code)| match code {
+            Code::Instruction(
+                Instruction::Value {
+                    op: ValueOps::Call,
+                    funcs,
+                    ..
+                }
+                | Instruction::Effect {
+                    op: EffectOps::Call,
+                    funcs,
+                    ..
+                },
+            ) => program
+                .functions
+                .iter()
+                .find(|f| f.name == funcs[0])
+                .map(|callee| func.inline_into(callee, i)),
+            _ => None,
+        })
+        .unwrap_or_else(|| func.clone())
+}
+
+// A copy of `func` with the operations that its dominators already computed replaced by copies
+fn gvn(func: &Function) -> Function {
+    let mut func = func.clone();
+    let cfg = ControlFlowGraph::build(&func);
+    global_value_number(&mut func, &cfg, &DominanceTree::build(&cfg));
+    func
+}
+
+// A copy of `func` with its loop-invariant code moved out of its loops
+fn licm(func: &Function) -> Function {
+    let mut func = func.clone();
+    let cfg = ControlFlowGraph::build(&func);
+    let dom = DominanceTree::build(&cfg);
+    hoist_loop_invariants(&mut func, &find_natural_loops(&cfg, &dom), &dom);
+    func
+}
+
+// A copy of `func` in SSA form
+fn to_ssa(func: &Function) -> Function {
+    let mut func = func.clone();
+    let cfg = ControlFlowGraph::build(&func);
+    convert_to_ssa(&mut func, &cfg, &DominanceTree::build(&cfg));
+    func
+}
+
+// A copy of `func` with its copies propagated
+fn copy_prop(func: &Function) -> Function {
+    let mut func = func.clone();
+    propagate_copies(&mut func);
+    func
+}
+
+// A copy of `func` with its common subexpressions replaced by copies
+fn cse(func: &Function) -> Function {
+    let mut func = func.clone();
+    eliminate_common_subexpressions(&mut func);
+    func
+}
+
+// A copy of `func` without its dead code
+fn dce(func: &Function) -> Function {
+    let mut func = func.clone();
+    eliminate_dead_code(&mut func);
+    func
+}
+
+// A copy of `func` with its constant expressions folded
+fn fold(func: &Function) -> Function {
+    let mut func = func.clone();
+    fold_constants(&mut func);
+    func
+}
+
+// Applies the passes named on the command line, in order, to the program on stdin and prints the result as Bril text. `inline:<function>` inlines every call to that function
+fn main() {
+    let mut program = load_program();
+    for pass in std::env::args().skip(1) {
+        program = match pass.as_str() {
+            "copy-prop" => Program {
+                functions: program.functions.iter().map(copy_prop).collect(),
+                ..program
+            },
+            "cps" => Program {
+                functions: program.functions.iter().map(Function::to_cps).collect(),
+                ..program
+            },
+            "cse" => Program {
+                functions: program.functions.iter().map(cse).collect(),
+                ..program
+            },
+            "dce" => Program {
+                functions: program.functions.iter().map(dce).collect(),
+                ..program
+            },
+            "fold" => Program {
+                functions: program.functions.iter().map(fold).collect(),
+                ..program
+            },
+            "gvn" => Program {
+                functions: program.functions.iter().map(gvn).collect(),
+                ..program
+            },
+            "inline-once" => Program {
+                functions: program
+                    .functions
+                    .iter()
+                    .map(|f| inline_first_call(f, &program))
+                    .collect(),
+                ..program
+            },
+            "licm" => Program {
+                functions: program.functions.iter().map(licm).collect(),
+                ..program
+            },
+            "ssa" => Program {
+                functions: program.functions.iter().map(to_ssa).collect(),
+                ..program
+            },
+            p if p.starts_with("inline:") => {
+                inline_function(&mut program, &p["inline:".len()..]);
+                program
+            }
+            p => {
+                eprintln!("unknown pass `{p}`");
+                std::process::exit(1);
+            }
+        };
+    }
+    print!("{program}");
+}
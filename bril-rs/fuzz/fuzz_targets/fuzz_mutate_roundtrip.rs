@@ -0,0 +1,307 @@
+//! Generates a valid [`Program`], serializes it, corrupts the JSON and then parses it again.
+//! Parsing may fail, but it should never panic. Whatever does parse has to survive being serialized and parsed again unchanged.
+//! When a check fails, the program that was being checked is also saved as a `.brilrepro` file next to libfuzzer's crash artifact.
+#![no_main]
+
+use std::cell::RefCell;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Once;
+
+use arbitrary::{Result, Unstructured};
+use bril_rs::repro::{Outcome, Repro};
+use bril_rs::{
+    AbstractProgram, Argument, Code, ColRow, ConstOps, EffectOps, Function, Instruction, Literal,
+    Position, Program, Type, ValueOps,
+};
+use libfuzzer_sys::fuzz_target;
+
+// A few names are shared between everything so that arguments, destinations, labels and functions collide
+const NAMES: [&str; 5] = ["main", "x", "y", "a.0", "\u{3bb}"];
+
+const VALUE_OPS: [ValueOps; 12] = [
+    ValueOps::Add,
+    ValueOps::Div,
+    ValueOps::Eq,
+    ValueOps::Not,
+    ValueOps::Call,
+    ValueOps::Id,
+    ValueOps::Phi,
+    ValueOps::Fadd,
+    ValueOps::Ceq,
+    ValueOps::Char2int,
+    ValueOps::Alloc,
+    ValueOps::Load,
+];
+
+const EFFECT_OPS: [EffectOps; 8] = [
+    EffectOps::Jump,
+    EffectOps::Branch,
+    EffectOps::Call,
+    EffectOps::Return,
+    EffectOps::Print,
+    EffectOps::Nop,
+    EffectOps::Store,
+    EffectOps::Guard,
+];
+
+// Pieces of Bril JSON that are spliced into the serialized program
+const TOKENS: [&str; 16] = [
+    "{",
+    "}",
+    "[",
+    "]",
+    ",",
+    ":",
+    "\"",
+    "null",
+    "-1",
+    "1.5",
+    "1e999",
+    "\"ptr\"",
+    "{\"ptr\":",
+    "\"op\":",
+    "\"type\":",
+    "\"label\":",
+];
+
+fn name(u: &mut Unstructured) -> Result<String> {
+    Ok((*u.choose(&NAMES)?).to_string())
+}
+
+fn names(u: &mut Unstructured) -> Result<Vec<String>> {
+    (0..u.int_in_range(0..=3)?).map(|_| name(u)).collect()
+}
+
+fn bril_type(u: &mut Unstructured, depth: u8) -> Result<Type> {
+    Ok(match u.int_in_range(0..=4)? {
+        0 => Type::Int,
+        1 => Type::Bool,
+        2 => Type::Float,
+        3 => Type::Char,
+        _ if depth < 3 => Type::Pointer(Box::new(bril_type(u, depth + 1)?)),
+        _ => Type::Int,
+    })
+}
+
+fn literal(u: &mut Unstructured) -> Result<Literal> {
+    Ok(match u.int_in_range(0..=3)? {
+        0 => Literal::Int(u.arbitrary()?),
+        1 => Literal::Bool(u.arbitrary()?),
+        2 => Literal::Float(u.arbitrary()?),
+        _ => Literal::Char(u.arbitrary()?),
+    })
+}
+
+fn col_row(u: &mut Unstructured) -> Result<ColRow> {
+    Ok(ColRow {
+        col: u.arbitrary()?,
+        row: u.arbitrary()?,
+    })
+}
+
+fn position(u: &mut Unstructured) -> Result<Option<Position>> {
+    if !u.arbitrary()? {
+        return Ok(None);
+    }
+    Ok(Some(Position {
+        pos: col_row(u)?,
+        pos_end: if u.arbitrary()? {
+            Some(col_row(u)?)
+        } else {
+            None
+        },
+        src: if u.arbitrary()? {
+            Some(u.arbitrary()?)
+        } else {
+            None
+        },
+    }))
+}
+
+fn code(u: &mut Unstructured) -> Result<Code> {
+    Ok(match u.int_in_range(0..=3)? {
+        0 => Code::Label {
+            label: name(u)?,
+            pos: position(u)?,
+        },
+        1 => Code::Instruction(Instruction::Constant {
+            dest: name(u)?,
+            op: ConstOps::Const,
+            pos: position(u)?,
+            const_type: bril_type(u, 0)?,
+            value: literal(u)?,
+        }),
+        2 => Code::Instruction(Instruction::Value {
+            args: names(u)?,
+            dest: name(u)?,
+            funcs: names(u)?,
+            labels: names(u)?,
+            op: *u.choose(&VALUE_OPS)?,
+            pos: position(u)?,
+            op_type: bril_type(u, 0)?,
+        }),
+        _ => Code::Instruction(Instruction::Effect {
+            args: names(u)?,
+            funcs: names(u)?,
+            labels: names(u)?,
+            op: *u.choose(&EFFECT_OPS)?,
+            pos: position(u)?,
+        }),
+    })
+}
+
+fn function(u: &mut Unstructured) -> Result<Function> {
+    Ok(Function {
+        args: (0..u.int_in_range(0..=3)?)
+            .map(|_| {
+                Ok(Argument {
+                    name: name(u)?,
+                    arg_type: bril_type(u, 0)?,
+                })
+            })
+            .collect::<Result<_>>()?,
+        instrs: (0..u.int_in_range(0..=8)?)
+            .map(|_| code(u))
+            .collect::<Result<_>>()?,
+        name: name(u)?,
+        pos: position(u)?,
+        return_type: if u.arbitrary()? {
+            Some(bril_type(u, 0)?)
+        } else {
+            None
+        },
+    })
+}
+
+fn program(u: &mut Unstructured) -> Result<Program> {
+    Ok(Program {
+        functions: (0..u.int_in_range(0..=3)?)
+            .map(|_| function(u))
+            .collect::<Result<_>>()?,
+        imports: Vec::new(),
+    })
+}
+
+fn mutate(u: &mut Unstructured, json: &mut Vec<u8>) -> Result<()> {
+    for _ in 0..u.int_in_range(0..=8)? {
+        let at = u.int_in_range(0..=json.len())?;
+        let len = u.int_in_range(0..=(json.len() - at).min(16))?;
+        match u.int_in_range(0..=3)? {
+            0 => {
+                json.drain(at..at + len);
+            }
+            1 => {
+                let copy = json[at..at + len].to_vec();
+                json.splice(at..at, copy);
+            }
+            2 => {
+                let token = u.choose(&TOKENS)?.as_bytes();
+                json.splice(at..at + len, token.iter().copied());
+            }
+            _ => {
+                if at < json.len() {
+                    json[at] = u.arbitrary()?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Floats that aren't finite are written as `null`, which is not a valid literal
+fn is_finite(prog: &Program) -> bool {
+    prog.functions
+        .iter()
+        .flat_map(|f| &f.instrs)
+        .all(|c| match c {
+            Code::Instruction(Instruction::Constant {
+                value: Literal::Float(f),
+                ..
+            }) => f.is_finite(),
+            _ => true,
+        })
+}
+
+// Serializing a parsed program and parsing it again gives back the same program.
+// Debug is compared instead of using PartialEq so that NaN is equal to itself.
+fn check_roundtrip(prog: &Program) {
+    let _ = prog.to_string();
+    if !is_finite(prog) {
+        return;
+    }
+    let json = serde_json::to_string(prog).expect("a parsed program could not be serialized");
+    let reparsed: Program =
+        serde_json::from_str(&json).expect("a serialized program could not be parsed");
+    assert_eq!(format!("{prog:?}"), format!("{reparsed:?}"));
+}
+
+thread_local! {
+    // The corrupted JSON that is being checked, for the panic hook to save
+    static CURRENT: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+// libfuzzer's artifact is the raw fuzzer input, which only turns into a program after going through `program` and `mutate`
+fn write_repro(json: &[u8]) {
+    let Ok(program) = serde_json::from_slice::<AbstractProgram>(json) else {
+        return;
+    };
+    let repro = Repro {
+        program,
+        tool: "bril-rs-fuzz".to_string(),
+        options: Vec::new(),
+        args: Vec::new(),
+        stdin: None,
+        seed: None,
+        // libfuzzer aborts the process on a panic
+        expected: Outcome {
+            stdout: String::new(),
+            exit_code: -1,
+        },
+    };
+    let prefix = std::env::args()
+        .find_map(|a| a.strip_prefix("-artifact_prefix=").map(ToString::to_string))
+        .unwrap_or_default();
+    let mut hasher = DefaultHasher::new();
+    json.hash(&mut hasher);
+    let path = format!("{prefix}crash-{:016x}.brilrepro", hasher.finish());
+    if let Ok(file) = std::fs::File::create(&path) {
+        if repro.write(file).is_ok() {
+            eprintln!("saved the program that crashed to {path}");
+        }
+    }
+}
+
+// Runs before libfuzzer's own panic hook, which aborts
+fn save_repro_on_panic() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            CURRENT.with_borrow(|json| write_repro(json));
+            previous(info);
+        }));
+    });
+}
+
+fuzz_target!(|data: &[u8]| {
+    save_repro_on_panic();
+    let mut u = Unstructured::new(data);
+    let Ok(prog) = program(&mut u) else {
+        return;
+    };
+    let mut json = serde_json::to_vec(&prog).unwrap();
+    if mutate(&mut u, &mut json).is_err() {
+        return;
+    }
+    CURRENT.with_borrow_mut(|current| current.clone_from(&json));
+
+    if let Ok(parsed) = serde_json::from_slice::<Program>(&json) {
+        check_roundtrip(&parsed);
+    }
+    if let Ok(parsed) = serde_json::from_slice::<AbstractProgram>(&json) {
+        let _ = parsed.to_string();
+        if let Ok(converted) = Program::try_from(parsed) {
+            check_roundtrip(&converted);
+        }
+    }
+});
@@ -9,4 +9,10 @@ pub struct Cli {
     /// Flag for whether position information should be included
     #[arg(short, action)]
     pub position: bool,
+    /// Abort if the generated program would have more than this many instructions, reporting the construct that contributed the most
+    #[arg(long)]
+    pub max_instrs: Option<usize>,
+    /// Print a per-function and per-construct breakdown of the number of generated instructions to stderr
+    #[arg(long, action)]
+    pub report_size: bool,
 }
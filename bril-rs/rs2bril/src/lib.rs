@@ -23,16 +23,111 @@ use syn::{
 use proc_macro2::Span;
 
 use std::collections::HashMap;
+use std::fmt::Display;
 
 // References, Dereference, Mutability, and Visibility are all silently ignored
 // Most other things are rejected as they can't be handled
 
+/// The number of instructions emitted for a single Rust construct, not counting those emitted for the constructs nested inside of it
+#[derive(Debug, Clone)]
+pub struct ConstructSize {
+    /// The function containing the construct
+    pub function: String,
+    /// A short description of the kind of construct, like "array literal"
+    pub construct: &'static str,
+    /// The line the construct starts on
+    pub line: usize,
+    /// The column the construct starts on
+    pub column: usize,
+    /// The number of instructions attributed to the construct
+    pub instrs: usize,
+}
+
+impl Display for ConstructSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at {}:{} in @{}",
+            self.construct, self.line, self.column, self.function
+        )
+    }
+}
+
+/// A breakdown of how many instructions were emitted for each function and for each Rust construct
+#[derive(Debug, Clone, Default)]
+pub struct SizeReport {
+    /// The number of instructions in each function, in the order they were translated
+    pub functions: Vec<(String, usize)>,
+    /// Every construct that had at least one instruction attributed to it
+    pub constructs: Vec<ConstructSize>,
+}
+
+impl SizeReport {
+    /// The construct with the most instructions attributed to it
+    #[must_use]
+    pub fn largest_construct(&self) -> Option<&ConstructSize> {
+        self.constructs
+            .iter()
+            .reduce(|max, c| if c.instrs > max.instrs { c } else { max })
+    }
+}
+
+impl Display for SizeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, instrs) in &self.functions {
+            writeln!(f, "@{name}: {instrs} instructions")?;
+            let mut constructs: Vec<_> = self
+                .constructs
+                .iter()
+                .filter(|c| &c.function == name)
+                .collect();
+            constructs.sort_by_key(|c| std::cmp::Reverse(c.instrs));
+            for c in constructs {
+                writeln!(
+                    f,
+                    "  {} at {}:{}: {}",
+                    c.construct, c.line, c.column, c.instrs
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Translation was aborted because the program needed more instructions than `--max-instrs` allows
+#[derive(Debug, Clone)]
+pub struct InstrBudgetExceeded {
+    /// The budget that was exceeded
+    pub max_instrs: usize,
+    /// The sizes of everything translated before aborting
+    pub report: SizeReport,
+}
+
+impl Display for InstrBudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exceeded the budget of {} instructions", self.max_instrs)?;
+        if let Some(largest) = self.report.largest_construct() {
+            write!(
+                f,
+                ": the largest contribution is {} instructions from the {largest}",
+                largest.instrs
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InstrBudgetExceeded {}
+
 struct State {
     is_pos: bool,
     src: Option<String>,
     temp_var_count: u64,
     ident_type_map: HashMap<String, Type>,
     func_context_map: HashMap<String, (HashMap<String, Type>, Option<Type>)>,
+    current_function: String,
+    claimed_instrs: usize,
+    size_report: SizeReport,
 }
 
 impl State {
@@ -43,6 +138,9 @@ impl State {
             temp_var_count: 0,
             ident_type_map: HashMap::new(),
             func_context_map: HashMap::new(),
+            current_function: String::new(),
+            claimed_instrs: 0,
+            size_report: SizeReport::default(),
         }
     }
 
@@ -62,7 +160,31 @@ impl State {
     }
 
     fn starting_new_function(&mut self, name: &String) {
-        self.ident_type_map.clone_from(&self.func_context_map.get(name).unwrap().0);
+        self.ident_type_map
+            .clone_from(&self.func_context_map.get(name).unwrap().0);
+        self.current_function.clone_from(name);
+    }
+
+    // Attributes the instructions in `code` to the construct at `span`, except for the ones already claimed by nested constructs since `claimed_before`
+    fn attribute_instrs(
+        &mut self,
+        construct: &'static str,
+        span: Span,
+        claimed_before: usize,
+        code: &[Code],
+    ) {
+        let instrs = count_instrs(code) - (self.claimed_instrs - claimed_before);
+        self.claimed_instrs += instrs;
+        if instrs > 0 {
+            let start = span.start();
+            self.size_report.constructs.push(ConstructSize {
+                function: self.current_function.clone(),
+                construct,
+                line: start.line,
+                column: start.column + 1,
+                instrs,
+            });
+        }
     }
 
     fn add_type_for_ident(&mut self, ident: String, ty: Type) {
@@ -236,6 +358,37 @@ fn from_expr_to_span(expr: &Expr) -> Span {
     }
 }
 
+// A helper to describe the kind of a Rust expression for size reports
+const fn from_expr_to_construct(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Array(_) => "array literal",
+        Expr::Assign(_) => "assignment",
+        Expr::Binary(_) => "binary operation",
+        Expr::Block(_) => "block",
+        Expr::Call(_) => "call",
+        Expr::Cast(_) => "cast",
+        Expr::If(_) => "if",
+        Expr::Index(_) => "index",
+        Expr::Lit(_) => "literal",
+        Expr::Loop(_) => "loop",
+        Expr::Macro(_) => "macro",
+        Expr::Paren(_) => "parenthesized expression",
+        Expr::Path(_) => "path",
+        Expr::Reference(_) => "reference",
+        Expr::Repeat(_) => "array repeat",
+        Expr::Return(_) => "return",
+        Expr::Unary(_) => "unary operation",
+        Expr::While(_) => "while loop",
+        _ => "expression",
+    }
+}
+
+fn count_instrs(code: &[Code]) -> usize {
+    code.iter()
+        .filter(|c| matches!(c, Code::Instruction(_)))
+        .count()
+}
+
 // A helper for converting Syn Span to Bril Position
 fn from_span_to_position(
     starting_span: Span,
@@ -447,12 +600,21 @@ fn array_init_helper(
 }
 
 fn from_expr_to_bril(expr: Expr, state: &mut State) -> (Option<String>, Vec<Code>) {
+    let construct = from_expr_to_construct(&expr);
+    let span = from_expr_to_span(&expr);
+    let claimed_before = state.claimed_instrs;
+    let (var, code) = from_expr_to_bril_inner(expr, span, state);
+    state.attribute_instrs(construct, span, claimed_before, &code);
+    (var, code)
+}
+
+fn from_expr_to_bril_inner(
+    expr: Expr,
+    span: Span,
+    state: &mut State,
+) -> (Option<String>, Vec<Code>) {
     let pos = if state.is_pos {
-        Some(from_span_to_position(
-            from_expr_to_span(&expr),
-            None,
-            state.src.clone(),
-        ))
+        Some(from_span_to_position(span, None, state.src.clone()))
     } else {
         None
     };
@@ -1056,6 +1218,7 @@ fn from_stmt_to_vec_code(s: Stmt, state: &mut State) -> Vec<Code> {
                     colon_token: _,
                     ty,
                 }) if attrs.is_empty() => {
+                    let claimed_before = state.claimed_instrs;
                     let op_type = from_type_to_type(*ty);
                     let dest = from_pat_to_string(*pat);
                     state.add_type_for_ident(dest.clone(), op_type.clone());
@@ -1078,6 +1241,12 @@ fn from_stmt_to_vec_code(s: Stmt, state: &mut State) -> Vec<Code> {
                         },
                         op_type,
                     }));
+                    state.attribute_instrs(
+                        "let statement",
+                        let_token.span.join(semi_token.span).unwrap(),
+                        claimed_before,
+                        &code,
+                    );
                     code
                 }
                 // todo would be nice to infer the types of variables
@@ -1131,11 +1300,18 @@ fn from_empty_function_to_function(
     state: &mut State,
 ) -> Function {
     func.instrs = from_block_to_vec_code(block, state);
+    state
+        .size_report
+        .functions
+        .push((func.name.clone(), count_instrs(&func.instrs)));
     func
 }
 
+/// Translates a Rust file into a Bril program, along with a breakdown of where its instructions came from.
+///
+/// # Errors
+/// If `max_instrs` is provided, translation stops after the first function that takes the program over that many instructions.
 #[doc(hidden)]
-#[must_use]
 pub fn from_file_to_program(
     File {
         shebang,
@@ -1144,7 +1320,8 @@ pub fn from_file_to_program(
     }: File,
     is_pos: bool,
     src: Option<String>,
-) -> Program {
+    max_instrs: Option<usize>,
+) -> Result<(Program, SizeReport), InstrBudgetExceeded> {
     assert!(shebang.is_none(), "can't handle shebang items in Rust file");
 
     assert!(attrs.is_empty(), "can't handle attributes in Rust file");
@@ -1164,15 +1341,27 @@ pub fn from_file_to_program(
     // Use starting_new_function or something???
     // Pass function name to start context?
 
-    Program {
-        functions: sigs_processed
-            .into_iter()
-            .map(|f| {
-                state.starting_new_function(&f.0.name);
-                from_empty_function_to_function(f, &mut state)
-            })
-            .collect(),
-        #[cfg(feature = "import")]
-        imports: vec![],
-    }
+    let functions = sigs_processed
+        .into_iter()
+        .map(|f| {
+            state.starting_new_function(&f.0.name);
+            let func = from_empty_function_to_function(f, &mut state);
+            match max_instrs {
+                Some(max_instrs) if state.claimed_instrs > max_instrs => Err(InstrBudgetExceeded {
+                    max_instrs,
+                    report: state.size_report.clone(),
+                }),
+                _ => Ok(func),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((
+        Program {
+            functions,
+            #[cfg(feature = "import")]
+            imports: vec![],
+        },
+        state.size_report,
+    ))
 }
@@ -22,5 +22,19 @@ fn main() {
 
     let syntax = syn::parse_file(&src).unwrap();
 
-    output_program(&from_file_to_program(syntax, args.position, source_name));
+    match from_file_to_program(syntax, args.position, source_name, args.max_instrs) {
+        Ok((program, report)) => {
+            if args.report_size {
+                eprint!("{report}");
+            }
+            output_program(&program);
+        }
+        Err(e) => {
+            if args.report_size {
+                eprint!("{}", e.report);
+            }
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+    }
 }
@@ -9,6 +9,12 @@ use crate::program::Position;
 #[cfg(feature = "import")]
 use crate::program::Import;
 
+#[cfg(feature = "global")]
+use crate::program::GlobalVar;
+
+#[cfg(feature = "extern")]
+use crate::program::ExternFunc;
+
 use serde::{Deserialize, Serialize};
 
 use serde::de::{self, Error, MapAccess, Visitor};
@@ -24,10 +30,26 @@ pub struct AbstractProgram {
     #[cfg(feature = "import")]
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub imports: Vec<Import>,
+    /// A list of global variables for this program
+    #[cfg(feature = "global")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub globals: Vec<GlobalVar>,
+    /// A list of functions defined outside of Bril for this program
+    #[cfg(feature = "extern")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub externs: Vec<ExternFunc>,
 }
 
 impl Display for AbstractProgram {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        #[cfg(feature = "global")]
+        for g in &self.globals {
+            writeln!(f, "{g}")?;
+        }
+        #[cfg(feature = "extern")]
+        for e in &self.externs {
+            writeln!(f, "{e}")?;
+        }
         for func in &self.functions {
             writeln!(f, "{func}")?;
         }
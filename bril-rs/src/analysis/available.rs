@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+
+use super::blocks::blocks;
+use crate::{Code, Function, Instruction, ValueOps};
+
+/// An expression is an operation along with the variables it is applied to, in order
+pub type Expr = (ValueOps, Vec<String>);
+
+/// The result of [`Function::compute_available_expressions`]
+#[derive(Debug, Clone)]
+pub struct AvailExprs {
+    // The expressions available just before each instruction, followed by those available just after the last one
+    points: Vec<HashSet<Expr>>,
+}
+
+impl AvailExprs {
+    /// Whether `op` applied to `args` is available just before the instruction at index `point` of `instrs`. The index one past the last instruction is just after it.
+    ///
+    /// An expression is available if it was computed on every path that reaches `point` and none of its arguments have been assigned to since.
+    ///
+    /// # Panics
+    /// If `point` is greater than the number of instructions
+    #[must_use]
+    pub fn is_available(&self, point: usize, op: ValueOps, args: &[&str]) -> bool {
+        self.points[point]
+            .iter()
+            .any(|(o, a)| *o == op && a.iter().map(String::as_str).eq(args.iter().copied()))
+    }
+
+    /// All of the expressions available just before the instruction at index `point` of `instrs`
+    ///
+    /// # Panics
+    /// If `point` is greater than the number of instructions
+    #[must_use]
+    pub fn available_at(&self, point: usize) -> &HashSet<Expr> {
+        &self.points[point]
+    }
+}
+
+// Operations whose result only depends on their arguments. Calls, loads (including `vload`), and reads of globals can give a different result each time and phi depends on where control came from.
+const fn is_pure(op: ValueOps) -> bool {
+    match op {
+        ValueOps::Call => false,
+        #[cfg(feature = "ssa")]
+        ValueOps::Phi => false,
+        #[cfg(feature = "memory")]
+        ValueOps::Alloc | ValueOps::Load => false,
+        #[cfg(feature = "global")]
+        ValueOps::Getglobal => false,
+        #[cfg(feature = "vector")]
+        ValueOps::Vload => false,
+        _ => true,
+    }
+}
+
+// The expression computed by `code` and the variable it assigns to
+fn expr_and_dest(code: &Code) -> (Option<Expr>, Option<&String>) {
+    match code {
+        Code::Instruction(Instruction::Value { op, args, dest, .. }) => {
+            (is_pure(*op).then(|| (*op, args.clone())), Some(dest))
+        }
+        Code::Instruction(Instruction::Constant { dest, .. }) => (None, Some(dest)),
+        Code::Label { .. } | Code::Instruction(Instruction::Effect { .. }) => (None, None),
+    }
+}
+
+// Updates the available expressions for the effect of one instruction
+fn transfer(avail: &mut HashSet<Expr>, code: &Code) {
+    let (expr, dest) = expr_and_dest(code);
+    if let Some(expr) = expr {
+        avail.insert(expr);
+    }
+    if let Some(dest) = dest {
+        avail.retain(|(_, args)| !args.contains(dest));
+    }
+}
+
+impl Function {
+    /// Computes which expressions are available at each point in the function with a forward dataflow analysis.
+    ///
+    /// Only operations whose result depends on nothing but their arguments are tracked, so calls, loads, allocations, and phis are never available. Arguments are compared by name and in order, so `add a b` and `add b a` are different expressions.
+    ///
+    /// ```
+    /// use bril_rs::{Program, ValueOps};
+    /// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "args": [{"name": "a", "type": "int"}], "instrs": [
+    ///     {"op": "add", "dest": "x", "type": "int", "args": ["a", "a"]},
+    ///     {"op": "print", "args": ["x"]},
+    ///     {"op": "const", "dest": "a", "type": "int", "value": 1},
+    ///     {"op": "print", "args": ["a"]}
+    /// ]}]}"#).unwrap();
+    /// let avail = prog.functions[0].compute_available_expressions();
+    /// assert!(!avail.is_available(0, ValueOps::Add, &["a", "a"]));
+    /// assert!(avail.is_available(1, ValueOps::Add, &["a", "a"]));
+    /// assert!(!avail.is_available(3, ValueOps::Add, &["a", "a"]));
+    /// ```
+    #[must_use]
+    pub fn compute_available_expressions(&self) -> AvailExprs {
+        let blocks = blocks(self);
+        let mut preds = vec![Vec::new(); blocks.len()];
+        for (b, block) in blocks.iter().enumerate() {
+            for &s in &block.successors {
+                preds[s].push(b);
+            }
+        }
+
+        // Everything starts out available except at the entry, then the analysis removes what isn't
+        let all: HashSet<Expr> = self
+            .instrs
+            .iter()
+            .filter_map(|code| expr_and_dest(code).0)
+            .collect();
+        let mut outs = vec![all; blocks.len()];
+        let block_in = |b: usize, outs: &[HashSet<Expr>]| -> HashSet<Expr> {
+            if b == 0 {
+                return HashSet::new();
+            }
+            let mut preds = preds[b].iter();
+            preds.next().map_or_else(HashSet::new, |&p| {
+                preds.fold(outs[p].clone(), |acc, &p| {
+                    acc.intersection(&outs[p]).cloned().collect()
+                })
+            })
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (b, block) in blocks.iter().enumerate() {
+                let mut avail = block_in(b, &outs);
+                for code in &self.instrs[block.start..block.end] {
+                    transfer(&mut avail, code);
+                }
+                if avail != outs[b] {
+                    outs[b] = avail;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut points = Vec::with_capacity(self.instrs.len() + 1);
+        for (b, block) in blocks.iter().enumerate() {
+            let mut avail = block_in(b, &outs);
+            for code in &self.instrs[block.start..block.end] {
+                points.push(avail.clone());
+                transfer(&mut avail, code);
+            }
+        }
+        points.push(outs.last().cloned().unwrap_or_default());
+        AvailExprs { points }
+    }
+}
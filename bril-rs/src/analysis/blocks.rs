@@ -0,0 +1,97 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{Code, EffectOps, Function, Instruction};
+
+// A basic block as a range of `instrs` along with the blocks that control can go to from its end
+pub struct Block {
+    pub start: usize,
+    pub end: usize,
+    pub successors: Vec<usize>,
+}
+
+pub fn blocks(func: &Function) -> Vec<Block> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for (i, code) in func.instrs.iter().enumerate() {
+        match code {
+            Code::Label { .. } if i > start => {
+                ranges.push((start, i));
+                start = i;
+            }
+            Code::Instruction(Instruction::Effect { op, labels, .. })
+                if matches!(
+                    op,
+                    EffectOps::Jump | EffectOps::Branch | EffectOps::Return | EffectOps::Abort
+                ) || !labels.is_empty() =>
+            {
+                ranges.push((start, i + 1));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < func.instrs.len() {
+        ranges.push((start, func.instrs.len()));
+    }
+
+    let by_label: HashMap<&String, usize> = ranges
+        .iter()
+        .enumerate()
+        .filter_map(|(b, (start, _))| match &func.instrs[*start] {
+            Code::Label { label, .. } => Some((label, b)),
+            Code::Instruction(_) => None,
+        })
+        .collect();
+    ranges
+        .iter()
+        .enumerate()
+        .map(|(b, &(start, end))| {
+            let successors = match &func.instrs[end - 1] {
+                Code::Instruction(Instruction::Effect { op, labels, .. }) => {
+                    let mut successors: Vec<usize> = labels
+                        .iter()
+                        .filter_map(|l| by_label.get(l).copied())
+                        .collect();
+                    // A guard that succeeds keeps going to the next block
+                    if !matches!(
+                        op,
+                        EffectOps::Jump | EffectOps::Branch | EffectOps::Return | EffectOps::Abort
+                    ) && b + 1 < ranges.len()
+                    {
+                        successors.push(b + 1);
+                    }
+                    successors
+                }
+                _ if b + 1 < ranges.len() => vec![b + 1],
+                _ => Vec::new(),
+            };
+            Block {
+                start,
+                end,
+                successors,
+            }
+        })
+        .collect()
+}
+
+// The label that each block starts with, or a fresh name like `b1` for blocks without one, following `examples/cfg.py`
+pub fn block_names(func: &Function, blocks: &[Block]) -> Vec<String> {
+    let labels: HashSet<&String> = func
+        .instrs
+        .iter()
+        .filter_map(|code| match code {
+            Code::Label { label, .. } => Some(label),
+            Code::Instruction(_) => None,
+        })
+        .collect();
+    let mut fresh = (1..)
+        .map(|i| format!("b{i}"))
+        .filter(|n| !labels.contains(n));
+    blocks
+        .iter()
+        .map(|block| match &func.instrs[block.start] {
+            Code::Label { label, .. } => label.clone(),
+            Code::Instruction(_) => fresh.next().unwrap(),
+        })
+        .collect()
+}
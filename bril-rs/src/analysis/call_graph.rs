@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{Code, EffectOps, Function, Instruction, Program, ValueOps};
+
+/// The error from [`topological_sort`] when functions call each other recursively
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("the calls between these functions form a cycle: @{}", .cycle.join(" -> @"))]
+pub struct CycleError {
+    /// The names of the functions in the cycle, each of which calls the next. The first function is repeated at the end.
+    pub cycle: Vec<String>,
+}
+
+// The functions called by `func`, in the order that the calls appear
+fn callees(func: &Function) -> impl Iterator<Item = &String> {
+    func.instrs.iter().filter_map(|code| match code {
+        Code::Instruction(
+            Instruction::Value {
+                op: ValueOps::Call,
+                funcs,
+                ..
+            }
+            | Instruction::Effect {
+                op: EffectOps::Call,
+                funcs,
+                ..
+            },
+        ) => funcs.first(),
+        _ => None,
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    NotStarted,
+    InProgress,
+    Done,
+}
+
+struct Sorter<'a> {
+    by_name: HashMap<&'a str, usize>,
+    functions: &'a [Function],
+    visits: Vec<Visit>,
+    // The functions currently being visited, from the first one down to the most recent
+    stack: Vec<usize>,
+    order: Vec<&'a Function>,
+}
+
+impl Sorter<'_> {
+    fn visit(&mut self, f: usize) -> Result<(), CycleError> {
+        match self.visits[f] {
+            Visit::Done => return Ok(()),
+            Visit::InProgress => {
+                let start = self.stack.iter().position(|&g| g == f).unwrap();
+                let cycle = self.stack[start..]
+                    .iter()
+                    .chain(std::iter::once(&f))
+                    .map(|&g| self.functions[g].name.clone())
+                    .collect();
+                return Err(CycleError { cycle });
+            }
+            Visit::NotStarted => {}
+        }
+        self.visits[f] = Visit::InProgress;
+        self.stack.push(f);
+        let functions = self.functions;
+        for callee in callees(&functions[f]) {
+            // Calls to functions outside of the program, like imported ones, don't affect the order
+            if let Some(&g) = self.by_name.get(callee.as_str()) {
+                self.visit(g)?;
+            }
+        }
+        self.stack.pop();
+        self.visits[f] = Visit::Done;
+        self.order.push(&functions[f]);
+        Ok(())
+    }
+}
+
+/// Orders the functions of `program` so that every function comes after all of the functions that it calls.
+///
+/// Functions that don't depend on each other keep the order they have in `program`, as far as that's possible.
+///
+/// ```
+/// use bril_rs::analysis::topological_sort;
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [
+///     {"name": "main", "instrs": [{"op": "call", "funcs": ["f"], "args": []}]},
+///     {"name": "f", "instrs": [{"op": "call", "funcs": ["g"], "args": []}]},
+///     {"name": "g", "instrs": []}
+/// ]}"#).unwrap();
+/// let names: Vec<_> = topological_sort(&prog).unwrap().iter().map(|f| &f.name).collect();
+/// assert_eq!(names, ["g", "f", "main"]);
+///
+/// let rec: Program = serde_json::from_str(r#"{"functions": [
+///     {"name": "even", "instrs": [{"op": "call", "funcs": ["odd"], "args": []}]},
+///     {"name": "odd", "instrs": [{"op": "call", "funcs": ["even"], "args": []}]}
+/// ]}"#).unwrap();
+/// assert_eq!(topological_sort(&rec).unwrap_err().cycle, ["even", "odd", "even"]);
+/// ```
+///
+/// # Errors
+/// A [`CycleError`] with one of the cycles if any function can end up calling itself
+pub fn topological_sort(program: &Program) -> Result<Vec<&Function>, CycleError> {
+    let mut sorter = Sorter {
+        by_name: program
+            .functions
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (f.name.as_str(), i))
+            .collect(),
+        functions: &program.functions,
+        visits: vec![Visit::NotStarted; program.functions.len()],
+        stack: Vec::new(),
+        order: Vec::with_capacity(program.functions.len()),
+    };
+    for f in 0..program.functions.len() {
+        sorter.visit(f)?;
+    }
+    Ok(sorter.order)
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use super::blocks::{block_names, blocks};
+use crate::{Code, EffectOps, Function, Instruction};
+
+/// A basic block of a [`ControlFlowGraph`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(not(feature = "float"), derive(Eq))]
+pub struct BasicBlockInfo {
+    /// The label that the block starts with, or a fresh name like `b1` for a block without one, following `examples/cfg.py`
+    pub label: String,
+    /// The index in the `instrs` of the function where the block starts, which is its label if it has one
+    pub start: usize,
+    /// The index in the `instrs` of the function just past the end of the block
+    pub end: usize,
+    /// The instruction that ends the block by going somewhere else, like `jmp`, `br`, or `ret`. It is `None` when control falls through to the next block or off the end of the function
+    pub terminator: Option<Instruction>,
+}
+
+/// The basic blocks of a [Function] and the edges between them, named by their labels
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(not(feature = "float"), derive(Eq))]
+pub struct ControlFlowGraph {
+    /// The blocks in the order they appear in the function
+    pub blocks: Vec<BasicBlockInfo>,
+    /// The labels of the blocks that control can go to from the end of each block
+    pub successors: HashMap<String, Vec<String>>,
+    /// The labels of the blocks that control can come from to the start of each block, in the order of [`ControlFlowGraph::blocks`]
+    pub predecessors: HashMap<String, Vec<String>>,
+}
+
+impl ControlFlowGraph {
+    /// Splits `func` into basic blocks and finds the edges between them.
+    ///
+    /// A block ends at a label or after an instruction with labels, so a `guard` ends one too and its successors are its label and the next block.
+    ///
+    /// ```
+    /// use bril_rs::analysis::ControlFlowGraph;
+    /// use bril_rs::Program;
+    /// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "instrs": [
+    ///     {"op": "const", "dest": "c", "type": "bool", "value": true},
+    ///     {"op": "br", "args": ["c"], "labels": ["then", "else"]},
+    ///     {"label": "then"},
+    ///     {"op": "jmp", "labels": ["end"]},
+    ///     {"label": "else"},
+    ///     {"label": "end"},
+    ///     {"op": "ret"}
+    /// ]}]}"#).unwrap();
+    /// let cfg = ControlFlowGraph::build(&prog.functions[0]);
+    /// assert_eq!(cfg.entry_label(), Some("b1"));
+    /// assert_eq!(cfg.successors["b1"], ["then", "else"]);
+    /// assert_eq!(cfg.successors["else"], ["end"]);
+    /// assert_eq!(cfg.predecessors["end"], ["then", "else"]);
+    /// assert_eq!(cfg.exit_labels(), ["end"]);
+    /// assert!(cfg.blocks[2].terminator.is_none());
+    /// ```
+    #[must_use]
+    pub fn build(func: &Function) -> Self {
+        let ranges = blocks(func);
+        let labels = block_names(func, &ranges);
+        let mut successors = HashMap::with_capacity(ranges.len());
+        let mut predecessors: HashMap<String, Vec<String>> = labels
+            .iter()
+            .map(|label| (label.clone(), Vec::new()))
+            .collect();
+        let blocks = ranges
+            .iter()
+            .zip(&labels)
+            .map(|(block, label)| {
+                let succs: Vec<String> = block
+                    .successors
+                    .iter()
+                    .map(|&s| labels[s].clone())
+                    .collect();
+                for s in &succs {
+                    predecessors
+                        .entry(s.clone())
+                        .or_default()
+                        .push(label.clone());
+                }
+                successors.insert(label.clone(), succs);
+                let terminator = match &func.instrs[block.end - 1] {
+                    Code::Instruction(i @ Instruction::Effect { op, labels, .. })
+                        if matches!(
+                            op,
+                            EffectOps::Jump
+                                | EffectOps::Branch
+                                | EffectOps::Return
+                                | EffectOps::Abort
+                        ) || !labels.is_empty() =>
+                    {
+                        Some(i.clone())
+                    }
+                    _ => None,
+                };
+                BasicBlockInfo {
+                    label: label.clone(),
+                    start: block.start,
+                    end: block.end,
+                    terminator,
+                }
+            })
+            .collect();
+        Self {
+            blocks,
+            successors,
+            predecessors,
+        }
+    }
+
+    /// The label of the block where the function starts, or `None` if it has no instructions
+    #[must_use]
+    pub fn entry_label(&self) -> Option<&str> {
+        self.blocks.first().map(|b| b.label.as_str())
+    }
+
+    /// The labels of the blocks that control leaves the function from, because they return, abort, or fall off the end, in the order of [`ControlFlowGraph::blocks`]
+    #[must_use]
+    pub fn exit_labels(&self) -> Vec<&str> {
+        self.blocks
+            .iter()
+            .filter(|b| self.successors[&b.label].is_empty())
+            .map(|b| b.label.as_str())
+            .collect()
+    }
+}
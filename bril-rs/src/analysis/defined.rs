@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use super::blocks::blocks;
+#[cfg(feature = "ssa")]
+use crate::passes::ssa::UNDEFINED;
+use crate::{Code, Function, Instruction};
+
+/// A use of a variable that isn't assigned on any path from the start of the function to it, from [`check_use_before_def`]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{var} is used in @{func} before it is assigned, at instruction {index}")]
+pub struct UseBeforeDefError {
+    /// The function that uses the variable
+    pub func: String,
+    /// The name of the variable
+    pub var: String,
+    /// The index of the instruction that uses it in the `instrs` of the function, counting labels
+    pub index: usize,
+}
+
+// Adds the variable that `code` assigns to, if any
+fn define<'a>(defined: &mut HashSet<&'a str>, code: &'a Code) {
+    if let Code::Instruction(Instruction::Constant { dest, .. } | Instruction::Value { dest, .. }) =
+        code
+    {
+        defined.insert(dest);
+    }
+}
+
+/// Finds every use of a variable that no assignment reaches, with a forward dataflow analysis of which variables may have been assigned at each point of `func`.
+///
+/// A variable counts as assigned if it is on at least one path, so this only finds uses that can never have a value. Blocks that can't be reached from the start are skipped. Arguments of a `phi` are skipped when they come from a block that can't be reached, and arguments named `__undefined` from `examples/to_ssa.py` are allowed.
+///
+/// ```
+/// use bril_rs::analysis::{check_use_before_def, UseBeforeDefError};
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "instrs": [
+///     {"op": "const", "dest": "c", "type": "bool", "value": true},
+///     {"op": "br", "args": ["c"], "labels": ["a", "b"]},
+///     {"label": "a"},
+///     {"op": "const", "dest": "x", "type": "int", "value": 1},
+///     {"op": "print", "args": ["x"]},
+///     {"label": "b"},
+///     {"op": "print", "args": ["x", "y"]},
+///     {"op": "const", "dest": "y", "type": "int", "value": 2}
+/// ]}]}"#).unwrap();
+/// assert_eq!(
+///     check_use_before_def(&prog.functions[0]),
+///     [UseBeforeDefError { func: "main".to_string(), var: "y".to_string(), index: 6 }]
+/// );
+/// ```
+#[must_use]
+pub fn check_use_before_def<'a>(func: &'a Function) -> Vec<UseBeforeDefError> {
+    let blocks = blocks(func);
+    let mut preds = vec![Vec::new(); blocks.len()];
+    for (b, block) in blocks.iter().enumerate() {
+        for &s in &block.successors {
+            preds[s].push(b);
+        }
+    }
+
+    let mut reachable = vec![false; blocks.len()];
+    let mut work = vec![0];
+    while let Some(b) = work.pop() {
+        if b < blocks.len() && !reachable[b] {
+            reachable[b] = true;
+            work.extend(&blocks[b].successors);
+        }
+    }
+
+    let args: HashSet<&str> = func.args.iter().map(|a| a.name.as_str()).collect();
+    let block_in = |b: usize, outs: &[HashSet<&'a str>]| {
+        let mut defined = if b == 0 { args.clone() } else { HashSet::new() };
+        for &p in &preds[b] {
+            defined.extend(&outs[p]);
+        }
+        defined
+    };
+
+    // The sets only grow, so starting from nothing reaches the least fixed point
+    let mut outs: Vec<HashSet<&str>> = vec![HashSet::new(); blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (b, block) in blocks.iter().enumerate() {
+            let mut defined = block_in(b, &outs);
+            for code in &func.instrs[block.start..block.end] {
+                define(&mut defined, code);
+            }
+            if defined != outs[b] {
+                outs[b] = defined;
+                changed = true;
+            }
+        }
+    }
+
+    #[cfg(feature = "ssa")]
+    let block_of_label = |label: &String| {
+        blocks.iter().position(
+            |block| matches!(&func.instrs[block.start], Code::Label { label: l, .. } if l == label),
+        )
+    };
+    let mut errors = Vec::new();
+    for (b, block) in blocks.iter().enumerate() {
+        if !reachable[b] {
+            continue;
+        }
+        let mut defined = block_in(b, &outs);
+        for (index, code) in func
+            .instrs
+            .iter()
+            .enumerate()
+            .take(block.end)
+            .skip(block.start)
+        {
+            let mut report = |var: &String| {
+                errors.push(UseBeforeDefError {
+                    func: func.name.clone(),
+                    var: var.clone(),
+                    index,
+                });
+            };
+            match code {
+                #[cfg(feature = "ssa")]
+                Code::Instruction(Instruction::Value {
+                    op: crate::ValueOps::Phi,
+                    args,
+                    labels,
+                    ..
+                }) => {
+                    for (arg, label) in args.iter().zip(labels) {
+                        let pred = block_of_label(label);
+                        // Control never comes from a block that can't be reached
+                        if arg == UNDEFINED || pred.is_some_and(|p| !reachable[p]) {
+                            continue;
+                        }
+                        if !defined.contains(arg.as_str()) {
+                            report(arg);
+                        }
+                    }
+                }
+                Code::Instruction(
+                    Instruction::Value { args, .. } | Instruction::Effect { args, .. },
+                ) => {
+                    for arg in args {
+                        if !defined.contains(arg.as_str()) {
+                            report(arg);
+                        }
+                    }
+                }
+                Code::Instruction(Instruction::Constant { .. }) | Code::Label { .. } => {}
+            }
+            define(&mut defined, code);
+        }
+    }
+    errors
+}
@@ -0,0 +1,227 @@
+use std::collections::{HashMap, HashSet};
+
+use super::ControlFlowGraph;
+
+/// The dominator tree of a [`ControlFlowGraph`] along with the dominance frontier of each block, with blocks named by their labels.
+///
+/// Blocks that can't be reached from the entry have no immediate dominator, dominate nothing, and aren't dominated by anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DominanceTree {
+    // The immediate dominator of each reachable block except the entry
+    idoms: HashMap<String, String>,
+    // The reachable blocks in reverse postorder, starting with the entry
+    order: Vec<String>,
+    frontiers: HashMap<String, HashSet<String>>,
+}
+
+// The immediate dominator of a block that hasn't been processed yet, and of the entry once they all have
+const UNSET: usize = usize::MAX;
+
+// The blocks reachable from the entry of `cfg` in reverse postorder
+fn reverse_postorder(cfg: &ControlFlowGraph) -> Vec<&str> {
+    let Some(entry) = cfg.entry_label() else {
+        return Vec::new();
+    };
+    let mut seen = HashSet::from([entry]);
+    let mut postorder = Vec::with_capacity(cfg.blocks.len());
+    // Each block on the stack is paired with how many of its successors have been visited
+    let mut stack = vec![(entry, 0)];
+    while let Some((b, next)) = stack.pop() {
+        match cfg.successors[b].get(next) {
+            Some(s) => {
+                stack.push((b, next + 1));
+                if seen.insert(s.as_str()) {
+                    stack.push((s.as_str(), 0));
+                }
+            }
+            None => postorder.push(b),
+        }
+    }
+    postorder.reverse();
+    postorder
+}
+
+impl DominanceTree {
+    /// Computes the immediate dominator of each block with the iterative algorithm from Cooper, Harvey, and Kennedy's "A Simple, Fast Dominance Algorithm", and then the dominance frontiers from those.
+    #[must_use]
+    pub fn build(cfg: &ControlFlowGraph) -> Self {
+        let order = reverse_postorder(cfg);
+        let index: HashMap<&str, usize> = order.iter().enumerate().map(|(i, &b)| (b, i)).collect();
+        // Only the predecessors that can be reached, as their indices in `order`
+        let preds: Vec<Vec<usize>> = order
+            .iter()
+            .map(|&b| {
+                cfg.predecessors[b]
+                    .iter()
+                    .filter_map(|p| index.get(p.as_str()).copied())
+                    .collect()
+            })
+            .collect();
+
+        // Walks up from two blocks until they meet, which is their closest common dominator. Blocks later in `order` can't dominate earlier ones
+        let intersect = |idoms: &[usize], mut a: usize, mut b: usize| {
+            while a != b {
+                while a > b {
+                    a = idoms[a];
+                }
+                while b > a {
+                    b = idoms[b];
+                }
+            }
+            a
+        };
+        // The entry is its own immediate dominator while the algorithm runs
+        let mut idoms = vec![UNSET; order.len()];
+        if !order.is_empty() {
+            idoms[0] = 0;
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for b in 1..order.len() {
+                let mut processed = preds[b].iter().copied().filter(|&p| idoms[p] != UNSET);
+                let Some(first) = processed.next() else {
+                    continue;
+                };
+                let new = processed.fold(first, |new, p| intersect(&idoms, p, new));
+                if idoms[b] != new {
+                    idoms[b] = new;
+                    changed = true;
+                }
+            }
+        }
+
+        // Control also comes into the entry from outside the function, so nothing dominates it
+        if !order.is_empty() {
+            idoms[0] = UNSET;
+        }
+
+        let mut frontiers: HashMap<String, HashSet<String>> = cfg
+            .blocks
+            .iter()
+            .map(|b| (b.label.clone(), HashSet::new()))
+            .collect();
+        for (b, block_preds) in preds.iter().enumerate() {
+            if block_preds.len() + usize::from(b == 0) < 2 {
+                continue;
+            }
+            for &p in block_preds {
+                let mut runner = p;
+                while runner != idoms[b] {
+                    frontiers
+                        .entry(order[runner].to_string())
+                        .or_default()
+                        .insert(order[b].to_string());
+                    runner = idoms[runner];
+                }
+            }
+        }
+
+        Self {
+            idoms: idoms
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(b, idom)| (order[b].to_string(), order[*idom].to_string()))
+                .collect(),
+            order: order.into_iter().map(String::from).collect(),
+            frontiers,
+        }
+    }
+
+    /// The immediate dominator of `node`, which is `None` for the entry and for blocks that can't be reached.
+    ///
+    /// ```
+    /// use bril_rs::analysis::{ControlFlowGraph, DominanceTree};
+    /// use bril_rs::Program;
+    /// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "args": [{"name": "c", "type": "bool"}], "instrs": [
+    ///     {"label": "entry"},
+    ///     {"op": "br", "args": ["c"], "labels": ["left", "right"]},
+    ///     {"label": "left"},
+    ///     {"op": "jmp", "labels": ["join"]},
+    ///     {"label": "right"},
+    ///     {"op": "jmp", "labels": ["join"]},
+    ///     {"label": "join"},
+    ///     {"op": "ret"}
+    /// ]}]}"#).unwrap();
+    /// let dom = DominanceTree::build(&ControlFlowGraph::build(&prog.functions[0]));
+    /// assert_eq!(dom.idom("entry"), None);
+    /// assert_eq!(dom.idom("left"), Some("entry"));
+    /// assert_eq!(dom.idom("right"), Some("entry"));
+    /// assert_eq!(dom.idom("join"), Some("entry"));
+    /// ```
+    #[must_use]
+    pub fn idom(&self, node: &str) -> Option<&str> {
+        self.idoms.get(node).map(String::as_str)
+    }
+
+    /// Whether every path from the entry to `b` goes through `a`. Every reachable block dominates itself.
+    ///
+    /// ```
+    /// use bril_rs::analysis::{ControlFlowGraph, DominanceTree};
+    /// use bril_rs::Program;
+    /// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "args": [{"name": "c", "type": "bool"}], "instrs": [
+    ///     {"label": "entry"},
+    ///     {"op": "br", "args": ["c"], "labels": ["left", "right"]},
+    ///     {"label": "left"},
+    ///     {"op": "jmp", "labels": ["join"]},
+    ///     {"label": "right"},
+    ///     {"op": "jmp", "labels": ["join"]},
+    ///     {"label": "join"},
+    ///     {"op": "ret"}
+    /// ]}]}"#).unwrap();
+    /// let dom = DominanceTree::build(&ControlFlowGraph::build(&prog.functions[0]));
+    /// assert!(dom.dominates("entry", "join"));
+    /// assert!(dom.dominates("join", "join"));
+    /// assert!(!dom.dominates("left", "join"));
+    /// assert!(!dom.dominates("join", "left"));
+    /// assert!(!dom.dominates("left", "right"));
+    /// ```
+    #[must_use]
+    pub fn dominates(&self, a: &str, b: &str) -> bool {
+        if !self.order.iter().any(|n| n == b) {
+            return false;
+        }
+        let mut node = b;
+        loop {
+            if node == a {
+                return true;
+            }
+            match self.idom(node) {
+                Some(idom) => node = idom,
+                None => return false,
+            }
+        }
+    }
+
+    /// The blocks where the dominance of `node` ends: those that `node` doesn't strictly dominate but that have a predecessor it dominates. This is where SSA construction puts the phis for variables assigned in `node`.
+    ///
+    /// ```
+    /// use bril_rs::analysis::{ControlFlowGraph, DominanceTree};
+    /// use bril_rs::Program;
+    /// use std::collections::HashSet;
+    /// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "args": [{"name": "c", "type": "bool"}], "instrs": [
+    ///     {"label": "entry"},
+    ///     {"op": "br", "args": ["c"], "labels": ["left", "right"]},
+    ///     {"label": "left"},
+    ///     {"op": "jmp", "labels": ["join"]},
+    ///     {"label": "right"},
+    ///     {"op": "jmp", "labels": ["join"]},
+    ///     {"label": "join"},
+    ///     {"op": "ret"}
+    /// ]}]}"#).unwrap();
+    /// let dom = DominanceTree::build(&ControlFlowGraph::build(&prog.functions[0]));
+    /// let join = HashSet::from(["join".to_string()]);
+    /// assert_eq!(dom.dominance_frontier("left"), &join);
+    /// assert_eq!(dom.dominance_frontier("right"), &join);
+    /// assert!(dom.dominance_frontier("entry").is_empty());
+    /// assert!(dom.dominance_frontier("join").is_empty());
+    /// ```
+    ///
+    /// # Panics
+    /// If `node` isn't the label of a block in the graph
+    #[must_use]
+    pub fn dominance_frontier(&self, node: &str) -> &HashSet<String> {
+        &self.frontiers[node]
+    }
+}
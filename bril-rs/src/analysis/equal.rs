@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use crate::{Code, Function, Instruction, Literal};
+
+// A one to one mapping between the names used in two functions, which grows as they are compared
+#[derive(Default)]
+struct Bijection<'a> {
+    forward: HashMap<&'a str, &'a str>,
+    backward: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Bijection<'a> {
+    // Whether `a` and `b` can be paired up, pairing them if neither has been seen yet
+    fn pair(&mut self, a: &'a str, b: &'a str) -> bool {
+        match (self.forward.get(a), self.backward.get(b)) {
+            (None, None) => {
+                self.forward.insert(a, b);
+                self.backward.insert(b, a);
+                true
+            }
+            (Some(&a_to), Some(&b_to)) => a_to == b && b_to == a,
+            _ => false,
+        }
+    }
+
+    fn pair_all(&mut self, a: &'a [String], b: &'a [String]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.pair(a, b))
+    }
+}
+
+// Literals are compared by their bits so that a NaN constant is equal to itself
+fn same_literal(a: &Literal, b: &Literal) -> bool {
+    match (a, b) {
+        #[cfg(feature = "float")]
+        (Literal::Float(a), Literal::Float(b)) => a.to_bits() == b.to_bits(),
+        _ => a == b,
+    }
+}
+
+/// Whether `a` and `b` are the same function up to renaming their variables and labels.
+///
+/// This holds when there is a one to one mapping from the variable names of `a` to those of `b`, and another for the labels, that turns `a` into `b`. The names of the functions themselves and the positions of instructions are ignored, but the functions that they call have to be the same.
+///
+/// ```
+/// use bril_rs::analysis::structurally_equal;
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [
+///     {"name": "f", "args": [{"name": "x", "type": "int"}], "type": "int", "instrs": [
+///         {"op": "add", "dest": "y", "type": "int", "args": ["x", "x"]},
+///         {"op": "ret", "args": ["y"]}
+///     ]},
+///     {"name": "g", "args": [{"name": "a", "type": "int"}], "type": "int", "instrs": [
+///         {"op": "add", "dest": "b", "type": "int", "args": ["a", "a"]},
+///         {"op": "ret", "args": ["b"]}
+///     ]},
+///     {"name": "h", "args": [{"name": "a", "type": "int"}], "type": "int", "instrs": [
+///         {"op": "add", "dest": "a", "type": "int", "args": ["a", "a"]},
+///         {"op": "ret", "args": ["a"]}
+///     ]}
+/// ]}"#).unwrap();
+/// let [f, g, h] = &prog.functions[..] else { unreachable!() };
+/// assert!(structurally_equal(f, g));
+/// // `h` reuses `a` where `f` has two different variables
+/// assert!(!structurally_equal(f, h));
+/// ```
+#[must_use]
+pub fn structurally_equal(a: &Function, b: &Function) -> bool {
+    let mut vars = Bijection::default();
+    let mut labels = Bijection::default();
+
+    if a.return_type != b.return_type
+        || a.args.len() != b.args.len()
+        || a.instrs.len() != b.instrs.len()
+    {
+        return false;
+    }
+    let same_args = a
+        .args
+        .iter()
+        .zip(&b.args)
+        .all(|(x, y)| x.arg_type == y.arg_type && vars.pair(&x.name, &y.name));
+    if !same_args {
+        return false;
+    }
+
+    a.instrs.iter().zip(&b.instrs).all(|pair| match pair {
+        (Code::Label { label: l1, .. }, Code::Label { label: l2, .. }) => labels.pair(l1, l2),
+        (
+            Code::Instruction(Instruction::Constant {
+                dest: d1,
+                op: o1,
+                const_type: t1,
+                value: v1,
+                ..
+            }),
+            Code::Instruction(Instruction::Constant {
+                dest: d2,
+                op: o2,
+                const_type: t2,
+                value: v2,
+                ..
+            }),
+        ) => o1 == o2 && t1 == t2 && same_literal(v1, v2) && vars.pair(d1, d2),
+        (
+            Code::Instruction(Instruction::Value {
+                args: a1,
+                dest: d1,
+                funcs: f1,
+                labels: l1,
+                op: o1,
+                op_type: t1,
+                ..
+            }),
+            Code::Instruction(Instruction::Value {
+                args: a2,
+                dest: d2,
+                funcs: f2,
+                labels: l2,
+                op: o2,
+                op_type: t2,
+                ..
+            }),
+        ) => {
+            o1 == o2
+                && t1 == t2
+                && f1 == f2
+                && vars.pair_all(a1, a2)
+                && labels.pair_all(l1, l2)
+                && vars.pair(d1, d2)
+        }
+        (
+            Code::Instruction(Instruction::Effect {
+                args: a1,
+                funcs: f1,
+                labels: l1,
+                op: o1,
+                ..
+            }),
+            Code::Instruction(Instruction::Effect {
+                args: a2,
+                funcs: f2,
+                labels: l2,
+                op: o2,
+                ..
+            }),
+        ) => o1 == o2 && f1 == f2 && vars.pair_all(a1, a2) && labels.pair_all(l1, l2),
+        _ => false,
+    })
+}
@@ -0,0 +1,120 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{ControlFlowGraph, DominanceTree};
+use crate::Function;
+
+/// A loop of a [`ControlFlowGraph`] found by [`find_natural_loops`], with blocks named by their labels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NaturalLoop {
+    /// The block that every path into the loop goes through, which dominates the whole loop
+    pub header: String,
+    /// The block at the end of the back edge, which jumps back to the header
+    pub back_edge_source: String,
+    /// The blocks that can reach the back edge without going through the header, along with the header itself
+    pub body: HashSet<String>,
+}
+
+/// Finds the natural loop of each back edge of `cfg`, which is an edge from a block to one that dominates it, using `dom` built from the same graph.
+///
+/// Unlike [`Function::compute_loop_depth`], back edges to the same header are separate loops. They are in the order of their source blocks in [`ControlFlowGraph::blocks`], and then of the successors of each source.
+///
+/// ```
+/// use bril_rs::analysis::{find_natural_loops, ControlFlowGraph, DominanceTree};
+/// use bril_rs::Program;
+/// use std::collections::HashSet;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "args": [{"name": "c", "type": "bool"}], "instrs": [
+///     {"label": "entry"},
+///     {"op": "jmp", "labels": ["loop_header"]},
+///     {"label": "loop_header"},
+///     {"op": "br", "args": ["c"], "labels": ["loop_body", "exit"]},
+///     {"label": "loop_body"},
+///     {"op": "jmp", "labels": ["loop_header"]},
+///     {"label": "exit"},
+///     {"op": "ret"}
+/// ]}]}"#).unwrap();
+/// let cfg = ControlFlowGraph::build(&prog.functions[0]);
+/// let loops = find_natural_loops(&cfg, &DominanceTree::build(&cfg));
+/// assert_eq!(loops.len(), 1);
+/// assert_eq!(loops[0].header, "loop_header");
+/// assert_eq!(loops[0].back_edge_source, "loop_body");
+/// assert_eq!(loops[0].body, HashSet::from(["loop_header".to_string(), "loop_body".to_string()]));
+/// ```
+#[must_use]
+pub fn find_natural_loops(cfg: &ControlFlowGraph, dom: &DominanceTree) -> Vec<NaturalLoop> {
+    let mut loops = Vec::new();
+    for source in &cfg.blocks {
+        let source = &source.label;
+        for header in cfg.successors[source]
+            .iter()
+            .filter(|h| dom.dominates(h, source))
+        {
+            let mut body = HashSet::from([header.clone()]);
+            // The header dominates every block that can reach the back edge without going through it, except for those that can't be reached at all
+            let mut stack = vec![source];
+            while let Some(b) = stack.pop() {
+                if body.insert(b.clone()) {
+                    stack.extend(
+                        cfg.predecessors[b]
+                            .iter()
+                            .filter(|p| dom.dominates(header, p)),
+                    );
+                }
+            }
+            loops.push(NaturalLoop {
+                header: header.clone(),
+                back_edge_source: source.clone(),
+                body,
+            });
+        }
+    }
+    loops
+}
+
+impl Function {
+    /// The loop nesting depth of each basic block, by the label that the block starts with. Blocks that don't start with a label are named `b1`, `b2`, and so on like in `examples/cfg.py`.
+    ///
+    /// Blocks outside of every loop have depth 0, blocks in a loop have depth 1, blocks in a loop inside of another loop have depth 2, and so on. Loops are the natural loops of the function's control flow graph, and all of the back edges to the same header count as one loop.
+    ///
+    /// ```
+    /// use bril_rs::Program;
+    /// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "instrs": [
+    ///     {"op": "const", "dest": "b", "type": "bool", "value": true},
+    ///     {"label": "outer"},
+    ///     {"op": "br", "args": ["b"], "labels": ["inner", "exit"]},
+    ///     {"label": "inner"},
+    ///     {"op": "br", "args": ["b"], "labels": ["body", "latch"]},
+    ///     {"label": "body"},
+    ///     {"op": "jmp", "labels": ["inner"]},
+    ///     {"label": "latch"},
+    ///     {"op": "jmp", "labels": ["outer"]},
+    ///     {"label": "exit"},
+    ///     {"op": "ret", "args": []}
+    /// ]}]}"#).unwrap();
+    /// let depth = prog.functions[0].compute_loop_depth();
+    /// assert_eq!(depth["b1"], 0);
+    /// assert_eq!(depth["outer"], 1);
+    /// assert_eq!(depth["inner"], 2);
+    /// assert_eq!(depth["body"], 2);
+    /// assert_eq!(depth["latch"], 1);
+    /// assert_eq!(depth["exit"], 0);
+    /// ```
+    #[must_use]
+    pub fn compute_loop_depth(&self) -> HashMap<String, usize> {
+        let cfg = ControlFlowGraph::build(self);
+        let dom = DominanceTree::build(&cfg);
+        let mut loops: HashMap<String, HashSet<String>> = HashMap::new();
+        for l in find_natural_loops(&cfg, &dom) {
+            loops.entry(l.header).or_default().extend(l.body);
+        }
+        cfg.blocks
+            .into_iter()
+            .map(|b| {
+                let depth = loops
+                    .values()
+                    .filter(|body| body.contains(&b.label))
+                    .count();
+                (b.label, depth)
+            })
+            .collect()
+    }
+}
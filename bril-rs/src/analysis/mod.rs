@@ -0,0 +1,17 @@
+mod available;
+mod blocks;
+mod call_graph;
+mod cfg;
+mod defined;
+mod dominance;
+mod equal;
+mod loops;
+pub use available::{AvailExprs, Expr};
+pub use call_graph::{topological_sort, CycleError};
+pub use cfg::{BasicBlockInfo, ControlFlowGraph};
+pub use defined::{check_use_before_def, UseBeforeDefError};
+pub use dominance::DominanceTree;
+pub use equal::structurally_equal;
+pub use loops::{find_natural_loops, NaturalLoop};
+
+pub(crate) use blocks::{blocks, Block};
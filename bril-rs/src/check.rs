@@ -0,0 +1,745 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use crate::{Code, EffectOps, Function, Instruction, Type, ValueOps};
+
+/// How many of something an opcode takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    /// Any number, like the arguments of `print` or `call`
+    Any,
+    /// Exactly this many
+    Exactly(usize),
+    /// Up to this many, like the optional argument of `ret`
+    AtMost(usize),
+    /// One for each label, like the arguments of `phi`
+    OnePerLabel(usize),
+}
+
+impl Expected {
+    const fn allows(self, found: usize) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Exactly(n) | Self::OnePerLabel(n) => found == n,
+            Self::AtMost(n) => found <= n,
+        }
+    }
+}
+
+/// Which list of an [Instruction] has the wrong length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// The `args` of the instruction
+    Args,
+    /// The `labels` of the instruction
+    Labels,
+    /// The `funcs` of the instruction
+    Funcs,
+}
+
+impl Operand {
+    const fn name(self, count: usize) -> &'static str {
+        match (self, count) {
+            (Self::Args, 1) => "argument",
+            (Self::Args, _) => "arguments",
+            (Self::Labels, 1) => "label",
+            (Self::Labels, _) => "labels",
+            (Self::Funcs, 1) => "function name",
+            (Self::Funcs, _) => "function names",
+        }
+    }
+}
+
+/// An instruction with the wrong number of arguments, labels, or function names for its opcode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArityError {
+    /// The opcode of the instruction as it is written in Bril
+    pub op: String,
+    /// The list that has the wrong length
+    pub operand: Operand,
+    /// How many the opcode takes
+    pub expected: Expected,
+    /// How many the instruction has
+    pub found: usize,
+}
+
+impl Display for ArityError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Self {
+            op,
+            operand,
+            expected,
+            found,
+        } = self;
+        write!(f, "`{op}` takes ")?;
+        match *expected {
+            Expected::Any => write!(f, "any number of {}", operand.name(0))?,
+            Expected::Exactly(n) => write!(f, "{n} {}", operand.name(n))?,
+            Expected::AtMost(n) => write!(f, "at most {n} {}", operand.name(n))?,
+            Expected::OnePerLabel(n) => write!(f, "one {} per label ({n} here)", operand.name(1))?,
+        }
+        write!(f, " but was given {found}")
+    }
+}
+
+impl std::error::Error for ArityError {}
+
+// The number of args, labels, and funcs that `op` takes. `labels` is how many labels the instruction has, which `phi` needs one argument for each of
+#[cfg_attr(not(feature = "ssa"), allow(unused_variables))]
+const fn value_arity(op: ValueOps, labels: usize) -> (Expected, usize, usize) {
+    match op {
+        ValueOps::Call => (Expected::Any, 0, 1),
+        #[cfg(feature = "ssa")]
+        ValueOps::Phi => (Expected::OnePerLabel(labels), labels, 0),
+        #[cfg(feature = "global")]
+        ValueOps::Getglobal => (Expected::Exactly(0), 0, 1),
+        ValueOps::Not
+        | ValueOps::Id
+        | ValueOps::Popcnt
+        | ValueOps::Clz
+        | ValueOps::Ctz
+        | ValueOps::Bswap
+        | ValueOps::Zext => (Expected::Exactly(1), 0, 0),
+        #[cfg(feature = "float")]
+        ValueOps::Fsqrt
+        | ValueOps::Fabs
+        | ValueOps::Fneg
+        | ValueOps::Ceil
+        | ValueOps::Floor
+        | ValueOps::Round
+        | ValueOps::Trunc
+        | ValueOps::Fsin
+        | ValueOps::Fcos
+        | ValueOps::Fexp
+        | ValueOps::Flog
+        | ValueOps::I2f
+        | ValueOps::F2i
+        | ValueOps::Float2bits
+        | ValueOps::Bits2float
+        | ValueOps::Isnan
+        | ValueOps::Isinf => (Expected::Exactly(1), 0, 0),
+        #[cfg(feature = "char")]
+        ValueOps::Char2int | ValueOps::Int2char => (Expected::Exactly(1), 0, 0),
+        #[cfg(feature = "memory")]
+        ValueOps::Alloc | ValueOps::Load | ValueOps::Ptr2int | ValueOps::Int2ptr => {
+            (Expected::Exactly(1), 0, 0)
+        }
+        #[cfg(feature = "vector")]
+        ValueOps::Vload => (Expected::Exactly(1), 0, 0),
+        ValueOps::Select => (Expected::Exactly(3), 0, 0),
+        #[cfg(feature = "float")]
+        ValueOps::Fma => (Expected::Exactly(3), 0, 0),
+        ValueOps::Add
+        | ValueOps::Sub
+        | ValueOps::Mul
+        | ValueOps::Div
+        | ValueOps::Rem
+        | ValueOps::Udiv
+        | ValueOps::Urem
+        | ValueOps::Smax
+        | ValueOps::Smin
+        | ValueOps::Shl
+        | ValueOps::Shr
+        | ValueOps::Rotl
+        | ValueOps::Rotr
+        | ValueOps::Eq
+        | ValueOps::Lt
+        | ValueOps::Gt
+        | ValueOps::Le
+        | ValueOps::Ge
+        | ValueOps::Ult
+        | ValueOps::Ugt
+        | ValueOps::Ule
+        | ValueOps::Uge
+        | ValueOps::And
+        | ValueOps::Or => (Expected::Exactly(2), 0, 0),
+        #[cfg(feature = "float")]
+        ValueOps::Fadd
+        | ValueOps::Fsub
+        | ValueOps::Fmul
+        | ValueOps::Fdiv
+        | ValueOps::Fmax
+        | ValueOps::Fmin
+        | ValueOps::Maxnum
+        | ValueOps::Minnum
+        | ValueOps::Pow
+        | ValueOps::Fcopysign
+        | ValueOps::Feq
+        | ValueOps::Flt
+        | ValueOps::Fgt
+        | ValueOps::Fle
+        | ValueOps::Fge
+        | ValueOps::Fne => (Expected::Exactly(2), 0, 0),
+        #[cfg(feature = "char")]
+        ValueOps::Ceq | ValueOps::Clt | ValueOps::Cgt | ValueOps::Cle | ValueOps::Cge => {
+            (Expected::Exactly(2), 0, 0)
+        }
+        #[cfg(feature = "memory")]
+        ValueOps::PtrAdd
+        | ValueOps::PtrDiff
+        | ValueOps::Peq
+        | ValueOps::Plt
+        | ValueOps::Pgt
+        | ValueOps::Ple
+        | ValueOps::Pge => (Expected::Exactly(2), 0, 0),
+        #[cfg(feature = "vector")]
+        ValueOps::Vadd | ValueOps::Vsub | ValueOps::Vmul => (Expected::Exactly(2), 0, 0),
+    }
+}
+
+// The number of args, labels, and funcs that `op` takes
+const fn effect_arity(op: EffectOps) -> (Expected, usize, usize) {
+    match op {
+        EffectOps::Jump => (Expected::Exactly(0), 1, 0),
+        EffectOps::Branch => (Expected::Exactly(1), 2, 0),
+        EffectOps::Call => (Expected::Any, 0, 1),
+        EffectOps::Return => (Expected::AtMost(1), 0, 0),
+        EffectOps::Print => (Expected::Any, 0, 0),
+        EffectOps::PrintStr => (Expected::Exactly(0), 0, 1),
+        EffectOps::Nop | EffectOps::Abort => (Expected::Exactly(0), 0, 0),
+        #[cfg(feature = "memory")]
+        EffectOps::Store => (Expected::Exactly(2), 0, 0),
+        #[cfg(feature = "memory")]
+        EffectOps::Free => (Expected::Exactly(1), 0, 0),
+        #[cfg(feature = "memory")]
+        EffectOps::MemCopy | EffectOps::Fill => (Expected::Exactly(3), 0, 0),
+        #[cfg(feature = "global")]
+        EffectOps::Setglobal => (Expected::Exactly(1), 0, 1),
+        #[cfg(feature = "vector")]
+        EffectOps::Vstore => (Expected::Exactly(2), 0, 0),
+        #[cfg(feature = "speculate")]
+        EffectOps::Speculate | EffectOps::Commit => (Expected::Exactly(0), 0, 0),
+        #[cfg(feature = "speculate")]
+        EffectOps::Guard => (Expected::Exactly(1), 1, 0),
+    }
+}
+
+/// Checks that `instr` has as many `args`, `labels`, and `funcs` as its opcode takes, so that a compiler can index into them without checking.
+///
+/// ```
+/// use bril_rs::check::{validate_arity, ArityError, Expected, Operand};
+/// use bril_rs::Instruction;
+/// let add: Instruction = serde_json::from_str(
+///     r#"{"op": "add", "dest": "x", "type": "int", "args": ["a", "b", "c"]}"#,
+/// ).unwrap();
+/// assert_eq!(
+///     validate_arity(&add),
+///     Err(ArityError {
+///         op: "add".to_string(),
+///         operand: Operand::Args,
+///         expected: Expected::Exactly(2),
+///         found: 3,
+///     })
+/// );
+/// assert_eq!(
+///     validate_arity(&add).unwrap_err().to_string(),
+///     "`add` takes 2 arguments but was given 3"
+/// );
+///
+/// let br: Instruction = serde_json::from_str(
+///     r#"{"op": "br", "args": ["cond"], "labels": ["then"]}"#,
+/// ).unwrap();
+/// assert_eq!(
+///     validate_arity(&br).unwrap_err().to_string(),
+///     "`br` takes 2 labels but was given 1"
+/// );
+///
+/// let ret: Instruction = serde_json::from_str(r#"{"op": "ret", "args": []}"#).unwrap();
+/// assert!(validate_arity(&ret).is_ok());
+/// ```
+///
+/// # Errors
+/// Returns the first of `args`, `labels`, and `funcs` that has the wrong length
+pub fn validate_arity(instr: &Instruction) -> Result<(), ArityError> {
+    let (op, (expected_args, expected_labels, expected_funcs), args, labels, funcs) = match instr {
+        Instruction::Constant { .. } => return Ok(()),
+        Instruction::Value {
+            op,
+            args,
+            labels,
+            funcs,
+            ..
+        } => (
+            op.to_string(),
+            value_arity(*op, labels.len()),
+            args,
+            labels,
+            funcs,
+        ),
+        Instruction::Effect {
+            op,
+            args,
+            labels,
+            funcs,
+            ..
+        } => (op.to_string(), effect_arity(*op), args, labels, funcs),
+    };
+    for (operand, expected, found) in [
+        (Operand::Args, expected_args, args.len()),
+        (
+            Operand::Labels,
+            Expected::Exactly(expected_labels),
+            labels.len(),
+        ),
+        (
+            Operand::Funcs,
+            Expected::Exactly(expected_funcs),
+            funcs.len(),
+        ),
+    ] {
+        if !expected.allows(found) {
+            return Err(ArityError {
+                op,
+                operand,
+                expected,
+                found,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// An instruction whose operands or destination don't have the types that its opcode needs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    /// The index of the instruction in the `instrs` of the function, counting labels
+    pub index: usize,
+    /// What is wrong with it
+    pub description: String,
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "instruction {}: {}", self.index, self.description)
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+// Collects what is wrong with the types of a single instruction
+struct InstrChecker<'a, 'b> {
+    types: &'b HashMap<&'a str, &'a Type>,
+    op: String,
+    args: &'a [String],
+    problems: Vec<String>,
+}
+
+impl<'a> InstrChecker<'a, '_> {
+    // The type of the `i`th argument, or `None` if there is no such argument or the variable is never assigned, which is left to other checks
+    fn arg(&self, i: usize) -> Option<&'a Type> {
+        self.args
+            .get(i)
+            .and_then(|a| self.types.get(a.as_str()).copied())
+    }
+
+    fn expect_arg(&mut self, i: usize, expected: &Type) {
+        if let Some(got) = self.arg(i) {
+            if got != expected {
+                self.problems.push(format!(
+                    "`{}` needs {} to have type {expected} but it has type {got}",
+                    self.op, self.args[i]
+                ));
+            }
+        }
+    }
+
+    fn expect_dest(&mut self, dest: &str, op_type: &Type, expected: &Type) {
+        if op_type != expected {
+            self.problems.push(format!(
+                "`{}` produces a value of type {expected} but its destination {dest} has type {op_type}",
+                self.op
+            ));
+        }
+    }
+
+    // The type that the `i`th argument points to
+    #[cfg(feature = "memory")]
+    fn pointee(&mut self, i: usize) -> Option<&'a Type> {
+        match self.arg(i)? {
+            Type::Pointer(ty) => Some(ty),
+            got => {
+                self.problems.push(format!(
+                    "`{}` needs {} to be a pointer but it has type {got}",
+                    self.op, self.args[i]
+                ));
+                None
+            }
+        }
+    }
+}
+
+// Checks the types of one instruction of `func` and returns a description of each problem
+fn type_check_instruction<'a>(
+    instr: &'a Instruction,
+    func: &Function,
+    types: &HashMap<&'a str, &'a Type>,
+) -> Vec<String> {
+    let (op, args) = match instr {
+        Instruction::Constant {
+            dest,
+            const_type,
+            value,
+            ..
+        } => {
+            return if value.fits_type(const_type) {
+                Vec::new()
+            } else {
+                vec![format!(
+                    "the constant {value} assigned to {dest} doesn't have type {const_type}"
+                )]
+            };
+        }
+        Instruction::Value { op, args, .. } => (op.to_string(), args),
+        Instruction::Effect { op, args, .. } => (op.to_string(), args),
+    };
+    let mut c = InstrChecker {
+        types,
+        op,
+        args,
+        problems: Vec::new(),
+    };
+
+    match instr {
+        Instruction::Constant { .. } => {}
+        Instruction::Value {
+            op, dest, op_type, ..
+        } => match op {
+            ValueOps::Add
+            | ValueOps::Sub
+            | ValueOps::Mul
+            | ValueOps::Div
+            | ValueOps::Rem
+            | ValueOps::Udiv
+            | ValueOps::Urem
+            | ValueOps::Smax
+            | ValueOps::Smin
+            | ValueOps::Shl
+            | ValueOps::Shr
+            | ValueOps::Rotl
+            | ValueOps::Rotr => {
+                c.expect_arg(0, &Type::Int);
+                c.expect_arg(1, &Type::Int);
+                c.expect_dest(dest, op_type, &Type::Int);
+            }
+            ValueOps::Popcnt | ValueOps::Clz | ValueOps::Ctz | ValueOps::Bswap => {
+                c.expect_arg(0, &Type::Int);
+                c.expect_dest(dest, op_type, &Type::Int);
+            }
+            ValueOps::Eq
+            | ValueOps::Lt
+            | ValueOps::Gt
+            | ValueOps::Le
+            | ValueOps::Ge
+            | ValueOps::Ult
+            | ValueOps::Ugt
+            | ValueOps::Ule
+            | ValueOps::Uge => {
+                c.expect_arg(0, &Type::Int);
+                c.expect_arg(1, &Type::Int);
+                c.expect_dest(dest, op_type, &Type::Bool);
+            }
+            ValueOps::Zext => {
+                c.expect_arg(0, &Type::Bool);
+                c.expect_dest(dest, op_type, &Type::Int);
+            }
+            ValueOps::Not => {
+                c.expect_arg(0, &Type::Bool);
+                c.expect_dest(dest, op_type, &Type::Bool);
+            }
+            ValueOps::And | ValueOps::Or => {
+                c.expect_arg(0, &Type::Bool);
+                c.expect_arg(1, &Type::Bool);
+                c.expect_dest(dest, op_type, &Type::Bool);
+            }
+            ValueOps::Id => c.expect_arg(0, op_type),
+            ValueOps::Select => {
+                c.expect_arg(0, &Type::Bool);
+                c.expect_arg(1, op_type);
+                c.expect_arg(2, op_type);
+            }
+            // These depend on the signatures of other functions and on the globals of the program
+            ValueOps::Call => {}
+            #[cfg(feature = "global")]
+            ValueOps::Getglobal => {}
+            #[cfg(feature = "ssa")]
+            ValueOps::Phi => {
+                for i in 0..args.len() {
+                    c.expect_arg(i, op_type);
+                }
+            }
+            #[cfg(feature = "float")]
+            ValueOps::Fadd
+            | ValueOps::Fsub
+            | ValueOps::Fmul
+            | ValueOps::Fdiv
+            | ValueOps::Fmax
+            | ValueOps::Fmin
+            | ValueOps::Maxnum
+            | ValueOps::Minnum
+            | ValueOps::Pow
+            | ValueOps::Fcopysign => {
+                c.expect_arg(0, &Type::Float);
+                c.expect_arg(1, &Type::Float);
+                c.expect_dest(dest, op_type, &Type::Float);
+            }
+            #[cfg(feature = "float")]
+            ValueOps::Fma => {
+                c.expect_arg(0, &Type::Float);
+                c.expect_arg(1, &Type::Float);
+                c.expect_arg(2, &Type::Float);
+                c.expect_dest(dest, op_type, &Type::Float);
+            }
+            #[cfg(feature = "float")]
+            ValueOps::Feq
+            | ValueOps::Flt
+            | ValueOps::Fgt
+            | ValueOps::Fle
+            | ValueOps::Fge
+            | ValueOps::Fne => {
+                c.expect_arg(0, &Type::Float);
+                c.expect_arg(1, &Type::Float);
+                c.expect_dest(dest, op_type, &Type::Bool);
+            }
+            #[cfg(feature = "float")]
+            ValueOps::Fsqrt
+            | ValueOps::Fabs
+            | ValueOps::Fneg
+            | ValueOps::Ceil
+            | ValueOps::Floor
+            | ValueOps::Round
+            | ValueOps::Trunc
+            | ValueOps::Fsin
+            | ValueOps::Fcos
+            | ValueOps::Fexp
+            | ValueOps::Flog => {
+                c.expect_arg(0, &Type::Float);
+                c.expect_dest(dest, op_type, &Type::Float);
+            }
+            #[cfg(feature = "float")]
+            ValueOps::I2f | ValueOps::Bits2float => {
+                c.expect_arg(0, &Type::Int);
+                c.expect_dest(dest, op_type, &Type::Float);
+            }
+            #[cfg(feature = "float")]
+            ValueOps::F2i | ValueOps::Float2bits => {
+                c.expect_arg(0, &Type::Float);
+                c.expect_dest(dest, op_type, &Type::Int);
+            }
+            #[cfg(feature = "float")]
+            ValueOps::Isnan | ValueOps::Isinf => {
+                c.expect_arg(0, &Type::Float);
+                c.expect_dest(dest, op_type, &Type::Bool);
+            }
+            #[cfg(feature = "char")]
+            ValueOps::Ceq | ValueOps::Clt | ValueOps::Cgt | ValueOps::Cle | ValueOps::Cge => {
+                c.expect_arg(0, &Type::Char);
+                c.expect_arg(1, &Type::Char);
+                c.expect_dest(dest, op_type, &Type::Bool);
+            }
+            #[cfg(feature = "char")]
+            ValueOps::Char2int => {
+                c.expect_arg(0, &Type::Char);
+                c.expect_dest(dest, op_type, &Type::Int);
+            }
+            #[cfg(feature = "char")]
+            ValueOps::Int2char => {
+                c.expect_arg(0, &Type::Int);
+                c.expect_dest(dest, op_type, &Type::Char);
+            }
+            #[cfg(feature = "memory")]
+            ValueOps::Alloc | ValueOps::Int2ptr => {
+                c.expect_arg(0, &Type::Int);
+                if !matches!(op_type, Type::Pointer(_)) {
+                    c.problems.push(format!(
+                        "`{}` produces a pointer but its destination {dest} has type {op_type}",
+                        c.op
+                    ));
+                }
+            }
+            #[cfg(feature = "memory")]
+            ValueOps::Load => {
+                if let Some(pointee) = c.pointee(0) {
+                    c.expect_dest(dest, op_type, pointee);
+                }
+            }
+            #[cfg(feature = "memory")]
+            ValueOps::PtrAdd => {
+                if c.pointee(0).is_some() {
+                    c.expect_arg(1, &Type::Int);
+                    if let Some(ptr) = c.arg(0) {
+                        c.expect_dest(dest, op_type, ptr);
+                    }
+                }
+            }
+            #[cfg(feature = "memory")]
+            ValueOps::PtrDiff
+            | ValueOps::Peq
+            | ValueOps::Plt
+            | ValueOps::Pgt
+            | ValueOps::Ple
+            | ValueOps::Pge => {
+                if let Some(ptr) = c.pointee(0).and_then(|_| c.arg(0)) {
+                    c.expect_arg(1, ptr);
+                }
+                let result = if *op == ValueOps::PtrDiff {
+                    Type::Int
+                } else {
+                    Type::Bool
+                };
+                c.expect_dest(dest, op_type, &result);
+            }
+            #[cfg(feature = "memory")]
+            ValueOps::Ptr2int => {
+                c.pointee(0);
+                c.expect_dest(dest, op_type, &Type::Int);
+            }
+            #[cfg(feature = "vector")]
+            ValueOps::Vadd | ValueOps::Vsub | ValueOps::Vmul => {
+                if !matches!(op_type, Type::Vector(_)) {
+                    c.problems.push(format!(
+                        "`{}` produces a vector but its destination {dest} has type {op_type}",
+                        c.op
+                    ));
+                }
+                c.expect_arg(0, op_type);
+                c.expect_arg(1, op_type);
+            }
+            #[cfg(feature = "vector")]
+            ValueOps::Vload => {
+                if let Some(pointee) = c.pointee(0) {
+                    c.expect_dest(dest, op_type, &Type::Vector(Box::new(pointee.clone())));
+                }
+            }
+        },
+        Instruction::Effect { op, .. } => match op {
+            EffectOps::Branch => c.expect_arg(0, &Type::Bool),
+            EffectOps::Return => match (&func.return_type, args.is_empty()) {
+                (Some(ty), false) => c.expect_arg(0, ty),
+                (Some(ty), true) => c
+                    .problems
+                    .push(format!("@{} has to return a value of type {ty}", func.name)),
+                (None, false) => c
+                    .problems
+                    .push(format!("@{} doesn't return a value", func.name)),
+                (None, true) => {}
+            },
+            EffectOps::Jump
+            | EffectOps::Call
+            | EffectOps::Print
+            | EffectOps::PrintStr
+            | EffectOps::Nop
+            | EffectOps::Abort => {}
+            #[cfg(feature = "memory")]
+            EffectOps::Store => {
+                if let Some(pointee) = c.pointee(0) {
+                    c.expect_arg(1, pointee);
+                }
+            }
+            #[cfg(feature = "memory")]
+            EffectOps::Free => {
+                c.pointee(0);
+            }
+            #[cfg(feature = "memory")]
+            EffectOps::MemCopy => {
+                if let Some(ptr) = c.pointee(0).and_then(|_| c.arg(0)) {
+                    c.expect_arg(1, ptr);
+                }
+                c.expect_arg(2, &Type::Int);
+            }
+            #[cfg(feature = "memory")]
+            EffectOps::Fill => {
+                if let Some(pointee) = c.pointee(0) {
+                    c.expect_arg(1, pointee);
+                }
+                c.expect_arg(2, &Type::Int);
+            }
+            #[cfg(feature = "global")]
+            EffectOps::Setglobal => {}
+            #[cfg(feature = "vector")]
+            EffectOps::Vstore => {
+                if let Some(pointee) = c.pointee(0) {
+                    c.expect_arg(1, &Type::Vector(Box::new(pointee.clone())));
+                }
+            }
+            #[cfg(feature = "speculate")]
+            EffectOps::Guard => c.expect_arg(0, &Type::Bool),
+            #[cfg(feature = "speculate")]
+            EffectOps::Speculate | EffectOps::Commit => {}
+        },
+    }
+    c.problems
+}
+
+/// Checks that the arguments and destination of every instruction in `func` have the types that its opcode needs, like `int` for both arguments of `add` and `float` for `fadd`.
+///
+/// Each variable must have the same type everywhere in the function, so the types of the arguments of `func` and of every destination are gathered first. Variables that are never assigned are left to other checks, as are the arguments of calls and the types of globals, which depend on the rest of the program. An instruction with the wrong number of operands, as found by [`validate_arity`], is reported as a type error and not checked further.
+///
+/// ```
+/// use bril_rs::check::{type_check_function, TypeError};
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "instrs": [
+///     {"op": "const", "dest": "a", "type": "int", "value": 1},
+///     {"op": "const", "dest": "b", "type": "float", "value": 2.5},
+///     {"op": "add", "dest": "c", "type": "int", "args": ["a", "b"]},
+///     {"op": "fadd", "dest": "d", "type": "float", "args": ["b", "b"]},
+///     {"op": "print", "args": ["c", "d"]}
+/// ]}]}"#).unwrap();
+/// assert_eq!(
+///     type_check_function(&prog.functions[0]),
+///     vec![TypeError {
+///         index: 2,
+///         description: "`add` needs b to have type int but it has type float".to_string(),
+///     }]
+/// );
+/// ```
+#[must_use]
+pub fn type_check_function(func: &Function) -> Vec<TypeError> {
+    let mut errors = Vec::new();
+    let mut types: HashMap<&str, &Type> = func
+        .args
+        .iter()
+        .map(|a| (a.name.as_str(), &a.arg_type))
+        .collect();
+    for (index, code) in func.instrs.iter().enumerate() {
+        if let Code::Instruction(
+            Instruction::Constant {
+                dest,
+                const_type: ty,
+                ..
+            }
+            | Instruction::Value {
+                dest, op_type: ty, ..
+            },
+        ) = code
+        {
+            match types.get(dest.as_str()) {
+                Some(&old) if old != ty => errors.push(TypeError {
+                    index,
+                    description: format!(
+                        "{dest} is assigned a value of type {ty} but already has type {old}"
+                    ),
+                }),
+                Some(_) => {}
+                None => {
+                    types.insert(dest, ty);
+                }
+            }
+        }
+    }
+
+    for (index, code) in func.instrs.iter().enumerate() {
+        let Code::Instruction(instr) = code else {
+            continue;
+        };
+        let problems = match validate_arity(instr) {
+            Ok(()) => type_check_instruction(instr, func, &types),
+            Err(e) => vec![e.to_string()],
+        };
+        errors.extend(
+            problems
+                .into_iter()
+                .map(|description| TypeError { index, description }),
+        );
+    }
+    errors
+}
@@ -90,12 +90,20 @@ impl TryFrom<AbstractProgram> for Program {
         AbstractProgram {
             #[cfg(feature = "import")]
             imports,
+            #[cfg(feature = "global")]
+            globals,
+            #[cfg(feature = "extern")]
+            externs,
             functions,
         }: AbstractProgram,
     ) -> Result<Self, Self::Error> {
         Ok(Self {
             #[cfg(feature = "import")]
             imports,
+            #[cfg(feature = "global")]
+            globals,
+            #[cfg(feature = "extern")]
+            externs,
             functions: functions
                 .into_iter()
                 .map(std::convert::TryInto::try_into)
@@ -230,6 +238,20 @@ impl TryFrom<AbstractInstruction> for Instruction {
                     "sub" => ValueOps::Sub,
                     "shl" => ValueOps::Shl,
                     "shr" => ValueOps::Shr,
+                    "popcnt" => ValueOps::Popcnt,
+                    "clz" => ValueOps::Clz,
+                    "ctz" => ValueOps::Ctz,
+                    "bswap" => ValueOps::Bswap,
+                    "rotl" => ValueOps::Rotl,
+                    "rotr" => ValueOps::Rotr,
+                    "rem" => ValueOps::Rem,
+                    "udiv" => ValueOps::Udiv,
+                    "urem" => ValueOps::Urem,
+                    "ult" => ValueOps::Ult,
+                    "ugt" => ValueOps::Ugt,
+                    "ule" => ValueOps::Ule,
+                    "uge" => ValueOps::Uge,
+                    "zext" => ValueOps::Zext,
                     #[cfg(feature = "ssa")]
                     "phi" => ValueOps::Phi,
                     #[cfg(feature = "float")]
@@ -251,9 +273,55 @@ impl TryFrom<AbstractInstruction> for Instruction {
                     #[cfg(feature = "float")]
                     "fge" => ValueOps::Fge,
                     #[cfg(feature = "float")]
+                    "fne" => ValueOps::Fne,
+                    #[cfg(feature = "float")]
                     "fmax" => ValueOps::Fmax,
                     #[cfg(feature = "float")]
                     "fmin" => ValueOps::Fmin,
+                    #[cfg(feature = "float")]
+                    "maxnum" => ValueOps::Maxnum,
+                    #[cfg(feature = "float")]
+                    "minnum" => ValueOps::Minnum,
+                    #[cfg(feature = "float")]
+                    "fsqrt" => ValueOps::Fsqrt,
+                    #[cfg(feature = "float")]
+                    "fabs" => ValueOps::Fabs,
+                    #[cfg(feature = "float")]
+                    "fneg" => ValueOps::Fneg,
+                    #[cfg(feature = "float")]
+                    "ceil" => ValueOps::Ceil,
+                    #[cfg(feature = "float")]
+                    "floor" => ValueOps::Floor,
+                    #[cfg(feature = "float")]
+                    "round" => ValueOps::Round,
+                    #[cfg(feature = "float")]
+                    "trunc" => ValueOps::Trunc,
+                    #[cfg(feature = "float")]
+                    "pow" => ValueOps::Pow,
+                    #[cfg(feature = "float")]
+                    "fsin" => ValueOps::Fsin,
+                    #[cfg(feature = "float")]
+                    "fcos" => ValueOps::Fcos,
+                    #[cfg(feature = "float")]
+                    "fexp" => ValueOps::Fexp,
+                    #[cfg(feature = "float")]
+                    "flog" => ValueOps::Flog,
+                    #[cfg(feature = "float")]
+                    "fma" => ValueOps::Fma,
+                    #[cfg(feature = "float")]
+                    "i2f" => ValueOps::I2f,
+                    #[cfg(feature = "float")]
+                    "f2i" => ValueOps::F2i,
+                    #[cfg(feature = "float")]
+                    "float2bits" => ValueOps::Float2bits,
+                    #[cfg(feature = "float")]
+                    "bits2float" => ValueOps::Bits2float,
+                    #[cfg(feature = "float")]
+                    "fcopysign" => ValueOps::Fcopysign,
+                    #[cfg(feature = "float")]
+                    "isnan" => ValueOps::Isnan,
+                    #[cfg(feature = "float")]
+                    "isinf" => ValueOps::Isinf,
                     #[cfg(feature = "char")]
                     "ceq" => ValueOps::Ceq,
                     #[cfg(feature = "char")]
@@ -274,6 +342,32 @@ impl TryFrom<AbstractInstruction> for Instruction {
                     "load" => ValueOps::Load,
                     #[cfg(feature = "memory")]
                     "ptradd" => ValueOps::PtrAdd,
+                    #[cfg(feature = "memory")]
+                    "ptrdiff" => ValueOps::PtrDiff,
+                    #[cfg(feature = "memory")]
+                    "peq" | "ptreq" => ValueOps::Peq,
+                    #[cfg(feature = "memory")]
+                    "plt" => ValueOps::Plt,
+                    #[cfg(feature = "memory")]
+                    "pgt" => ValueOps::Pgt,
+                    #[cfg(feature = "memory")]
+                    "ple" => ValueOps::Ple,
+                    #[cfg(feature = "memory")]
+                    "pge" => ValueOps::Pge,
+                    #[cfg(feature = "memory")]
+                    "ptr2int" => ValueOps::Ptr2int,
+                    #[cfg(feature = "memory")]
+                    "int2ptr" => ValueOps::Int2ptr,
+                    #[cfg(feature = "global")]
+                    "getglobal" => ValueOps::Getglobal,
+                    #[cfg(feature = "vector")]
+                    "vadd" => ValueOps::Vadd,
+                    #[cfg(feature = "vector")]
+                    "vsub" => ValueOps::Vsub,
+                    #[cfg(feature = "vector")]
+                    "vmul" => ValueOps::Vmul,
+                    #[cfg(feature = "vector")]
+                    "vload" => ValueOps::Vload,
                     v => {
                         return Err(ConversionError::InvalidValueOps(v.to_string()))
                             .map_err(|e| e.add_pos(pos))
@@ -299,11 +393,21 @@ impl TryFrom<AbstractInstruction> for Instruction {
                     "call" => EffectOps::Call,
                     "ret" => EffectOps::Return,
                     "print" => EffectOps::Print,
+                    "printstr" => EffectOps::PrintStr,
                     "nop" => EffectOps::Nop,
+                    "abort" => EffectOps::Abort,
                     #[cfg(feature = "memory")]
                     "store" => EffectOps::Store,
                     #[cfg(feature = "memory")]
                     "free" => EffectOps::Free,
+                    #[cfg(feature = "memory")]
+                    "copy" => EffectOps::MemCopy,
+                    #[cfg(feature = "memory")]
+                    "fill" => EffectOps::Fill,
+                    #[cfg(feature = "global")]
+                    "setglobal" => EffectOps::Setglobal,
+                    #[cfg(feature = "vector")]
+                    "vstore" => EffectOps::Vstore,
                     #[cfg(feature = "speculate")]
                     "speculate" => EffectOps::Speculate,
                     #[cfg(feature = "speculate")]
@@ -343,6 +447,10 @@ impl TryFrom<AbstractType> for Type {
             AbstractType::Parameterized(t, ty) if t == "ptr" => {
                 Self::Pointer(Box::new((*ty).try_into()?))
             }
+            #[cfg(feature = "vector")]
+            AbstractType::Parameterized(t, ty) if t == "vec" => {
+                Self::Vector(Box::new((*ty).try_into()?))
+            }
             AbstractType::Parameterized(t, ty) => {
                 return Err(ConversionError::InvalidParameterized(t, ty.to_string()))
             }
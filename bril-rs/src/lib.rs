@@ -5,10 +5,20 @@
 
 /// Provides the unstructured representation of Bril programs
 pub mod abstract_program;
+/// Provides analyses over [Function] and [Program] and ways to compare functions
+pub mod analysis;
+/// Provides checks that a [Program] is well formed before it is compiled
+pub mod check;
 /// Provides the Error handling and conversion between [`AbstractProgram`] and [Program]
 pub mod conversion;
+/// Provides the export of [Program] to MLIR
+pub mod mlir;
+/// Provides transformations over [Function] and [Program]
+pub mod passes;
 /// Provides the structured representation of Bril programs
 pub mod program;
+/// Provides the `.brilrepro` format for recording a run of one of the Rust tools and replaying it later
+pub mod repro;
 pub use abstract_program::*;
 pub use program::*;
 
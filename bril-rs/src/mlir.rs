@@ -0,0 +1,509 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::{Code, EffectOps, Function, Instruction, Literal, Program, Type, ValueOps};
+
+fn mlir_type(t: &Type) -> String {
+    match t {
+        Type::Int => "i64".to_string(),
+        Type::Bool => "i1".to_string(),
+        #[cfg(feature = "float")]
+        Type::Float => "f64".to_string(),
+        #[cfg(feature = "char")]
+        Type::Char => "i32".to_string(),
+        #[cfg(feature = "memory")]
+        Type::Pointer(t) => format!("!bril.ptr<{}>", mlir_type(t)),
+        #[cfg(feature = "vector")]
+        Type::Vector(t) => format!("vector<{}x{}>", crate::VECTOR_WIDTH, mlir_type(t)),
+    }
+}
+
+#[cfg_attr(not(feature = "float"), allow(unused_variables))]
+fn mlir_literal(value: &Literal, const_type: &Type) -> String {
+    match value {
+        #[cfg(feature = "float")]
+        #[allow(clippy::cast_precision_loss)]
+        Literal::Int(i) if const_type == &Type::Float => {
+            mlir_literal(&Literal::Float(*i as f64), const_type)
+        }
+        Literal::Int(i) => format!("{i} : i64"),
+        Literal::Bool(b) => format!("{b}"),
+        #[cfg(feature = "float")]
+        Literal::Float(f) => format!("{} : f64", mlir_float(*f)),
+        #[cfg(feature = "char")]
+        Literal::Char(c) => format!("{} : i32", u32::from(*c)),
+        #[cfg(feature = "memory")]
+        Literal::Null => "#bril.null".to_string(),
+    }
+}
+
+// Floats are written the way MLIR prints them: with 7 digits when that gives back the same value, then with up to 17 digits, and otherwise as their bits
+#[cfg(feature = "float")]
+fn mlir_float(f: f64) -> String {
+    let bits = || format!("0x{:X}", f.to_bits());
+    if !f.is_finite() {
+        return bits();
+    }
+    let short = format!("{f:.5e}");
+    if short.parse::<f64>().map(f64::to_bits) == Ok(f.to_bits()) {
+        let (mantissa, exp) = short.split_once('e').unwrap();
+        let exp: i32 = exp.parse().unwrap();
+        let sign = if exp < 0 { '-' } else { '+' };
+        return format!("{mantissa}0e{sign}{:02}", exp.abs());
+    }
+
+    let long = format!("{:.16e}", f.abs());
+    let (mantissa, exp) = long.split_once('e').unwrap();
+    let digits = mantissa.replace('.', "");
+    let digits = digits.trim_end_matches('0');
+    let n = i32::try_from(digits.len()).unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    let sign = if f < 0.0 { "-" } else { "" };
+    // The power of ten of the last digit decides between scientific and positional notation
+    let last = exp - (n - 1);
+    if (last < 0 && exp < -3) || (last >= 0 && (last > 3 || n + last > 17)) {
+        let (first, rest) = digits.split_at(1);
+        let rest = if rest.is_empty() { "0" } else { rest };
+        let exp_sign = if exp < 0 { '-' } else { '+' };
+        format!("{sign}{first}.{rest}E{exp_sign}{}", exp.abs())
+    } else if last >= 0 {
+        // MLIR can't read this back as a float without a `.`
+        bits()
+    } else if exp >= 0 {
+        let (whole, frac) = digits.split_at(usize::try_from(exp + 1).unwrap());
+        format!("{sign}{whole}.{frac}")
+    } else {
+        let zeros = "0".repeat(usize::try_from(-exp - 1).unwrap());
+        format!("{sign}0.{zeros}{digits}")
+    }
+}
+
+// Strings are escaped the way MLIR prints them, with `\XX` for quotes and anything else that isn't printable ASCII
+fn mlir_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for b in s.bytes() {
+        match b {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\22"),
+            b' '..=b'~' => out.push(char::from(b)),
+            _ => write!(out, "\\{b:02X}").unwrap(),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// The arguments of a block, which are left out completely if there aren't any
+fn block_header(args: &[String]) -> String {
+    if args.is_empty() {
+        String::new()
+    } else {
+        format!("({})", args.join(", "))
+    }
+}
+
+// Function names are quoted unless they are already valid MLIR identifiers
+fn symbol(name: &str) -> String {
+    let mut chars = name.chars();
+    let bare = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || "_$.".contains(c));
+    if bare {
+        format!("@{name}")
+    } else {
+        format!("@{}", mlir_string(name))
+    }
+}
+
+const fn is_terminator(code: &Code) -> bool {
+    matches!(
+        code,
+        Code::Instruction(Instruction::Effect {
+            op: EffectOps::Jump | EffectOps::Branch | EffectOps::Return,
+            ..
+        })
+    )
+}
+
+// Bril variables can be assigned more than once, so every block takes the current value of every variable as a block argument and each branch passes them along. MLIR's canonicalizer can remove the ones that aren't needed.
+struct FunctionEmitter<'a> {
+    func: &'a Function,
+    // Every variable in the function along with its type, in the order they first appear
+    vars: Vec<(&'a String, &'a Type)>,
+    // The SSA value that currently holds each variable
+    current: Vec<String>,
+    // The index of each variable in `vars`
+    index: HashMap<&'a str, usize>,
+    // The ranges of `func.instrs` that make up each block. Block `i` here is `^bb{i + 1}` in MLIR because `^bb0` is the entry block.
+    blocks: Vec<(usize, usize)>,
+    block_of_label: HashMap<&'a str, usize>,
+    next_value: usize,
+}
+
+impl<'a> FunctionEmitter<'a> {
+    fn new(func: &'a Function) -> Self {
+        let mut vars = Vec::new();
+        let mut index = HashMap::new();
+        let mut add = |name: &'a String, t: &'a Type| {
+            index.entry(name.as_str()).or_insert_with(|| {
+                vars.push((name, t));
+                vars.len() - 1
+            });
+        };
+        for a in &func.args {
+            add(&a.name, &a.arg_type);
+        }
+        for code in &func.instrs {
+            match code {
+                Code::Instruction(Instruction::Constant {
+                    dest, const_type, ..
+                }) => add(dest, const_type),
+                Code::Instruction(Instruction::Value { dest, op_type, .. }) => add(dest, op_type),
+                Code::Label { .. } | Code::Instruction(Instruction::Effect { .. }) => {}
+            }
+        }
+
+        let mut blocks = Vec::new();
+        let mut start = 0;
+        for (i, code) in func.instrs.iter().enumerate() {
+            if matches!(code, Code::Label { .. }) && i > start {
+                blocks.push((start, i));
+                start = i;
+            } else if is_terminator(code) {
+                blocks.push((start, i + 1));
+                start = i + 1;
+            }
+        }
+        if start < func.instrs.len() {
+            blocks.push((start, func.instrs.len()));
+        }
+        let block_of_label = blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(b, (start, _))| match &func.instrs[*start] {
+                Code::Label { label, .. } => Some((label.as_str(), b)),
+                Code::Instruction(_) => None,
+            })
+            .collect();
+
+        Self {
+            func,
+            current: vec![String::new(); vars.len()],
+            vars,
+            index,
+            blocks,
+            block_of_label,
+            next_value: 0,
+        }
+    }
+
+    fn fresh(&mut self) -> String {
+        self.next_value += 1;
+        format!("%{}", self.next_value - 1)
+    }
+
+    fn var(&self, name: &str) -> usize {
+        *self.index.get(name).unwrap_or_else(|| {
+            panic!(
+                "`{name}` is used in @{} but never assigned to",
+                self.func.name
+            )
+        })
+    }
+
+    // The operands and their types for the variables in `args`
+    fn operands(&self, args: &[String]) -> (String, String) {
+        let (values, types): (Vec<_>, Vec<_>) = args
+            .iter()
+            .map(|a| {
+                let v = self.var(a);
+                (self.current[v].clone(), mlir_type(self.vars[v].1))
+            })
+            .unzip();
+        (values.join(", "), types.join(", "))
+    }
+
+    // Gives every variable a new block argument and returns them for the block header
+    fn block_args(&mut self) -> Vec<String> {
+        (0..self.vars.len())
+            .map(|v| {
+                self.current[v] = self.fresh();
+                format!("{}: {}", self.current[v], mlir_type(self.vars[v].1))
+            })
+            .collect()
+    }
+
+    fn all_vars(&self) -> Vec<String> {
+        self.vars.iter().map(|(name, _)| (*name).clone()).collect()
+    }
+
+    fn target(&self, label: &str) -> usize {
+        self.block_of_label.get(label).map_or_else(
+            || panic!("`.{label}` is not a label in @{}", self.func.name),
+            |b| b + 1,
+        )
+    }
+
+    fn emit_jump(&self, out: &mut String, block: usize) {
+        let (values, types) = self.operands(&self.all_vars());
+        writeln!(out, "    \"cf.br\"({values})[^bb{block}] : ({types}) -> ()").unwrap();
+    }
+
+    fn emit_return(&self, out: &mut String, args: &[String]) {
+        let (values, types) = self.operands(args);
+        writeln!(out, "    \"func.return\"({values}) : ({types}) -> ()").unwrap();
+    }
+
+    // The MLIR blocks that block `b` can go to next
+    fn successors(&self, b: usize) -> Vec<usize> {
+        let (_, end) = self.blocks[b];
+        match &self.func.instrs[end - 1] {
+            Code::Instruction(Instruction::Effect {
+                op: EffectOps::Jump | EffectOps::Branch,
+                labels,
+                ..
+            }) => labels.iter().map(|l| self.target(l)).collect(),
+            Code::Instruction(Instruction::Effect {
+                op: EffectOps::Return,
+                ..
+            }) => Vec::new(),
+            _ if b + 1 < self.blocks.len() => vec![b + 2],
+            _ => Vec::new(),
+        }
+    }
+
+    // What happens when control reaches the end of a block without a terminator
+    fn emit_fallthrough(&self, out: &mut String, block: usize) {
+        if block + 1 < self.blocks.len() {
+            self.emit_jump(out, block + 2);
+        } else if self.func.return_type.is_none() {
+            self.emit_return(out, &[]);
+        } else {
+            writeln!(out, "    \"bril.unreachable\"() : () -> ()").unwrap();
+        }
+    }
+
+    // Labels and functions that an instruction refers to that don't have their own place in MLIR
+    fn extra_attributes(funcs: &[String], labels: &[String]) -> String {
+        let mut attrs = Vec::new();
+        if !funcs.is_empty() {
+            let funcs: Vec<_> = funcs.iter().map(|f| symbol(f)).collect();
+            attrs.push(format!("funcs = [{}]", funcs.join(", ")));
+        }
+        if !labels.is_empty() {
+            let labels: Vec<_> = labels.iter().map(|l| mlir_string(l)).collect();
+            attrs.push(format!("labels = [{}]", labels.join(", ")));
+        }
+        if attrs.is_empty() {
+            String::new()
+        } else {
+            format!(" {{{}}}", attrs.join(", "))
+        }
+    }
+
+    fn emit_instruction(&mut self, out: &mut String, instr: &Instruction) {
+        match instr {
+            Instruction::Constant {
+                dest,
+                const_type,
+                value,
+                ..
+            } => {
+                let result = self.fresh();
+                writeln!(
+                    out,
+                    "    {result} = \"bril.const\"() {{value = {}}} : () -> {}",
+                    mlir_literal(value, const_type),
+                    mlir_type(const_type)
+                )
+                .unwrap();
+                let v = self.var(dest);
+                self.current[v] = result;
+            }
+            Instruction::Value {
+                args,
+                dest,
+                funcs,
+                labels,
+                op,
+                op_type,
+                ..
+            } => {
+                let (values, types) = self.operands(args);
+                let result = self.fresh();
+                let ty = mlir_type(op_type);
+                if op == &ValueOps::Call {
+                    writeln!(
+                        out,
+                        "    {result} = \"func.call\"({values}) <{{callee = {}}}> : ({types}) -> {ty}",
+                        symbol(&funcs[0])
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(
+                        out,
+                        "    {result} = \"bril.{op}\"({values}){} : ({types}) -> {ty}",
+                        Self::extra_attributes(funcs, labels)
+                    )
+                    .unwrap();
+                }
+                let v = self.var(dest);
+                self.current[v] = result;
+            }
+            Instruction::Effect {
+                args,
+                funcs,
+                labels,
+                op,
+                ..
+            } => match op {
+                EffectOps::Jump => self.emit_jump(out, self.target(&labels[0])),
+                EffectOps::Branch => {
+                    let (cond, _) = self.operands(&args[..1]);
+                    let (values, types) = self.operands(&self.all_vars());
+                    let (values, types) = if self.vars.is_empty() {
+                        (String::new(), String::new())
+                    } else {
+                        (
+                            format!(", {values}, {values}"),
+                            format!(", {types}, {types}"),
+                        )
+                    };
+                    let n = self.vars.len();
+                    writeln!(
+                        out,
+                        "    \"cf.cond_br\"({cond}{values})[^bb{}, ^bb{}] <{{operandSegmentSizes = array<i32: 1, {n}, {n}>}}> : (i1{types}) -> ()",
+                        self.target(&labels[0]),
+                        self.target(&labels[1])
+                    )
+                    .unwrap();
+                }
+                EffectOps::Return => self.emit_return(out, args),
+                EffectOps::Call => {
+                    let (values, types) = self.operands(args);
+                    writeln!(
+                        out,
+                        "    \"func.call\"({values}) <{{callee = {}}}> : ({types}) -> ()",
+                        symbol(&funcs[0])
+                    )
+                    .unwrap();
+                }
+                _ => {
+                    let (values, types) = self.operands(args);
+                    writeln!(
+                        out,
+                        "    \"bril.{op}\"({values}){} : ({types}) -> ()",
+                        Self::extra_attributes(funcs, labels)
+                    )
+                    .unwrap();
+                }
+            },
+        }
+    }
+
+    fn emit(mut self, out: &mut String) {
+        let arg_types: Vec<_> = self
+            .func
+            .args
+            .iter()
+            .map(|a| mlir_type(&a.arg_type))
+            .collect();
+        let return_type = self
+            .func
+            .return_type
+            .as_ref()
+            .map_or_else(|| "()".to_string(), mlir_type);
+        writeln!(
+            out,
+            "  \"func.func\"() <{{function_type = ({}) -> {return_type}, sym_name = {}}}> ({{",
+            arg_types.join(", "),
+            mlir_string(&self.func.name)
+        )
+        .unwrap();
+
+        // The entry block can't have any predecessors, so it sets up the variables that aren't arguments and jumps to the first block. Like in MLIR, its arguments are named `%argN` and it has no header if there aren't any.
+        let args: Vec<_> = self
+            .func
+            .args
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                let v = self.var(&a.name);
+                self.current[v] = format!("%arg{i}");
+                format!("{}: {}", self.current[v], mlir_type(&a.arg_type))
+            })
+            .collect();
+        if !args.is_empty() {
+            writeln!(out, "  ^bb0{}:", block_header(&args)).unwrap();
+        }
+        for v in self.func.args.len()..self.vars.len() {
+            self.current[v] = self.fresh();
+            writeln!(
+                out,
+                "    {} = \"bril.undef\"() : () -> {}",
+                self.current[v],
+                mlir_type(self.vars[v].1)
+            )
+            .unwrap();
+        }
+        if self.blocks.is_empty() {
+            self.emit_fallthrough(out, 0);
+        } else {
+            self.emit_jump(out, 1);
+        }
+
+        // MLIR writes the predecessors of each block after its header, so they are written here too
+        let mut preds = vec![Vec::new(); self.blocks.len() + 1];
+        if !self.blocks.is_empty() {
+            preds[1].push(0);
+        }
+        for b in 0..self.blocks.len() {
+            for s in self.successors(b) {
+                preds[s].push(b + 1);
+            }
+        }
+
+        for b in 0..self.blocks.len() {
+            let (start, end) = self.blocks[b];
+            let header = block_header(&self.block_args());
+            let pred_names: Vec<_> = preds[b + 1].iter().map(|p| format!("^bb{p}")).collect();
+            let comment = match pred_names.as_slice() {
+                [] => "no predecessors".to_string(),
+                [p] => format!("pred: {p}"),
+                _ => format!("{} preds: {}", pred_names.len(), pred_names.join(", ")),
+            };
+            writeln!(out, "  ^bb{}{header}:  // {comment}", b + 1).unwrap();
+            for code in &self.func.instrs[start..end] {
+                if let Code::Instruction(i) = code {
+                    self.emit_instruction(out, i);
+                }
+            }
+            if !is_terminator(&self.func.instrs[end - 1]) {
+                self.emit_fallthrough(out, b);
+            }
+        }
+        writeln!(out, "  }}) : () -> ()").unwrap();
+    }
+}
+
+/// Writes `program` in MLIR's generic op format.
+///
+/// The output is exactly what `mlir-opt --allow-unregistered-dialect --mlir-print-op-generic` prints when it reads it back, down to the value names, float formatting, and predecessor comments.
+///
+/// Each function becomes a `func.func` and each basic block an MLIR block. Jumps, branches, returns, and calls use the `cf` and `func` dialects and everything else becomes an op in an unregistered `bril` dialect with the same name as the Bril instruction, so `mlir-opt` needs `--allow-unregistered-dialect` to read it. Pointers have the type `!bril.ptr<T>`.
+///
+/// Since Bril variables can be assigned more than once, every block takes the value of each variable as an argument, and variables that aren't arguments to the function start out as `bril.undef`.
+///
+/// # Panics
+/// If a variable is used without ever being assigned to or a jump goes to a label that doesn't exist
+#[must_use]
+pub fn to_mlir(program: &Program) -> String {
+    let mut out = String::new();
+    writeln!(out, "\"builtin.module\"() ({{").unwrap();
+    for func in &program.functions {
+        FunctionEmitter::new(func).emit(&mut out);
+    }
+    writeln!(out, "}}) : () -> ()").unwrap();
+    out
+}
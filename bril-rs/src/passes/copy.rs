@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::analysis::blocks;
+use crate::{Code, Function, Instruction, ValueOps};
+
+use super::dce::dead_assignments;
+use super::substitute_copied_args;
+
+// Replaces the operands that are copies made earlier in the same block with the variables they were copied from, and returns how many were replaced
+fn substitute_copies(func: &mut Function) -> usize {
+    let mut replaced = 0;
+    for block in blocks(func) {
+        // The variable that each variable is a copy of, for the copies that both still hold
+        let mut copies: HashMap<String, String> = HashMap::new();
+        for code in &mut func.instrs[block.start..block.end] {
+            let Code::Instruction(instr) = code else {
+                continue;
+            };
+            replaced += substitute_copied_args(instr, &copies);
+            if let Instruction::Constant { dest, .. } | Instruction::Value { dest, .. } = instr {
+                copies.remove(dest);
+                copies.retain(|_, source| source != dest);
+            }
+            if let Instruction::Value {
+                op: ValueOps::Id,
+                args,
+                dest,
+                ..
+            } = instr
+            {
+                if let [source] = args.as_slice() {
+                    if source != dest {
+                        copies.insert(dest.clone(), source.clone());
+                    }
+                }
+            }
+        }
+    }
+    replaced
+}
+
+/// Replaces the uses of each variable assigned by an `id` with the variable it was copied from, and then removes the `id`s whose result is no longer used. Returns how many `id`s were removed.
+///
+/// A use is replaced when the `id` comes earlier in the same block and neither variable has been assigned again in between. Arguments of a `phi` are left alone, since they are read at the end of a predecessor. An `id` that is still used, like one whose result is read in a later block, stays, and so do assignments other than `id` that are unused, which [`super::eliminate_dead_code`] removes.
+///
+/// ```
+/// use bril_rs::passes::propagate_copies;
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "instrs": [
+///     {"op": "const", "dest": "a", "type": "int", "value": 1},
+///     {"op": "id", "dest": "b", "type": "int", "args": ["a"]},
+///     {"op": "id", "dest": "c", "type": "int", "args": ["b"]},
+///     {"op": "print", "args": ["c"]}
+/// ]}]}"#).unwrap();
+/// let mut func = prog.functions[0].clone();
+/// assert_eq!(propagate_copies(&mut func), 2);
+/// assert_eq!(func.instrs.len(), 2);
+/// assert!(func.to_string().contains("print a;"));
+/// ```
+pub fn propagate_copies(func: &mut Function) -> usize {
+    substitute_copies(func);
+    let mut removed = 0;
+    loop {
+        let dead: Vec<bool> = {
+            let dead = dead_assignments(func);
+            func.instrs
+                .iter()
+                .enumerate()
+                .map(|(index, code)| {
+                    dead.contains(&index)
+                        && matches!(
+                            code,
+                            Code::Instruction(Instruction::Value {
+                                op: ValueOps::Id,
+                                ..
+                            })
+                        )
+                })
+                .collect()
+        };
+        let count = dead.iter().filter(|&&d| d).count();
+        if count == 0 {
+            return removed;
+        }
+        removed += count;
+        let mut index = 0;
+        func.instrs.retain(|_| {
+            index += 1;
+            !dead[index - 1]
+        });
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{Argument, Code, EffectOps, Function, Instruction, Type};
+
+use super::{fresh_name, variable_names};
+
+/// The function that [`Function::to_cps`] calls in place of returning. It is passed the continuation followed by the returned value, if there is one.
+pub const APPLY_CONTINUATION: &str = "__apply_k";
+
+// Builds `call @__apply_k k args...;`
+#[cfg_attr(not(feature = "position"), allow(unused_variables))]
+fn apply_continuation(k: &str, args: &[String], instr: Option<&Instruction>) -> Code {
+    Code::Instruction(Instruction::Effect {
+        args: std::iter::once(k.to_string())
+            .chain(args.iter().cloned())
+            .collect(),
+        funcs: vec![APPLY_CONTINUATION.to_string()],
+        labels: Vec::new(),
+        op: EffectOps::Call,
+        #[cfg(feature = "position")]
+        pos: instr.and_then(Instruction::get_pos),
+    })
+}
+
+#[cfg_attr(not(feature = "position"), allow(unused_variables))]
+fn empty_return(instr: Option<&Instruction>) -> Code {
+    Code::Instruction(Instruction::Effect {
+        args: Vec::new(),
+        funcs: Vec::new(),
+        labels: Vec::new(),
+        op: EffectOps::Return,
+        #[cfg(feature = "position")]
+        pos: instr.and_then(Instruction::get_pos),
+    })
+}
+
+const fn is_terminator(code: Option<&Code>) -> bool {
+    matches!(
+        code,
+        Some(Code::Instruction(Instruction::Effect {
+            op: EffectOps::Jump | EffectOps::Branch | EffectOps::Return,
+            ..
+        }))
+    )
+}
+
+impl Function {
+    /// Converts this function into continuation-passing style.
+    ///
+    /// Bril does not have first-class functions, so the continuation is defunctionalized: the new function takes an extra `int` argument `k` identifying the continuation and each `ret` becomes a call to [`APPLY_CONTINUATION`] with `k` and the returned value. The converted function never returns a value. The program is expected to provide [`APPLY_CONTINUATION`] to dispatch on `k`.
+    #[must_use]
+    pub fn to_cps(&self) -> Self {
+        let k = fresh_name("k", &variable_names(self));
+
+        let mut instrs = Vec::with_capacity(self.instrs.len() + 2);
+        for code in &self.instrs {
+            match code {
+                Code::Instruction(
+                    i @ Instruction::Effect {
+                        op: EffectOps::Return,
+                        args,
+                        ..
+                    },
+                ) => {
+                    instrs.push(apply_continuation(&k, args, Some(i)));
+                    instrs.push(empty_return(Some(i)));
+                }
+                _ => instrs.push(code.clone()),
+            }
+        }
+
+        // Falling off the end of the function is an implicit return
+        if !is_terminator(instrs.last()) {
+            instrs.push(apply_continuation(&k, &[], None));
+            instrs.push(empty_return(None));
+        }
+
+        let mut args = self.args.clone();
+        args.push(Argument {
+            name: k,
+            arg_type: Type::Int,
+        });
+
+        Self {
+            args,
+            instrs,
+            name: self.name.clone(),
+            #[cfg(feature = "position")]
+            pos: self.pos.clone(),
+            return_type: None,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use crate::analysis::blocks;
+use crate::{Code, Function, Instruction, ValueOps};
+
+use super::substitute_copied_args;
+
+// Whether two instructions with `op` and the same arguments always give the same result. Calls and loads depend on more than their arguments, an `alloc` gives a new pointer each time, and phis depend on which block came before
+pub(super) const fn is_pure(op: ValueOps) -> bool {
+    match op {
+        ValueOps::Call | ValueOps::Id => false,
+        #[cfg(feature = "ssa")]
+        ValueOps::Phi => false,
+        #[cfg(feature = "memory")]
+        ValueOps::Alloc | ValueOps::Load => false,
+        #[cfg(feature = "global")]
+        ValueOps::Getglobal => false,
+        #[cfg(feature = "vector")]
+        ValueOps::Vload => false,
+        _ => true,
+    }
+}
+
+/// Replaces each value operation in `func` that computes the same thing as one earlier in its block with an `id` of that one's result, and returns how many were replaced.
+///
+/// Two operations compute the same thing when they have the same opcode and arguments, and none of those arguments, nor the result of the first, have been assigned again in between. Calls, loads, `alloc`, `phi`, and `getglobal` are never replaced. The uses of a variable assigned by an `id` later in the block are also replaced with the variable it copies, so that operations on the copies are found too.
+///
+/// ```
+/// use bril_rs::passes::eliminate_common_subexpressions;
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "args": [{"name": "x", "type": "int"}, {"name": "y", "type": "int"}], "instrs": [
+///     {"op": "add", "dest": "a", "type": "int", "args": ["x", "y"]},
+///     {"op": "add", "dest": "b", "type": "int", "args": ["x", "y"]},
+///     {"op": "add", "dest": "z", "type": "int", "args": ["a", "b"]},
+///     {"op": "print", "args": ["z"]}
+/// ]}]}"#).unwrap();
+/// let mut func = prog.functions[0].clone();
+/// assert_eq!(eliminate_common_subexpressions(&mut func), 1);
+/// let text = func.to_string();
+/// assert!(text.contains("a: int = add x y;"));
+/// assert!(text.contains("b: int = id a;"));
+/// assert!(text.contains("z: int = add a a;"));
+/// ```
+pub fn eliminate_common_subexpressions(func: &mut Function) -> usize {
+    let mut replaced = 0;
+    for block in blocks(func) {
+        // The variable holding the result of each operation, by its opcode and arguments
+        let mut computed: HashMap<(ValueOps, Vec<String>), String> = HashMap::new();
+        // The variable that each variable is a copy of
+        let mut copies: HashMap<String, String> = HashMap::new();
+        for code in &mut func.instrs[block.start..block.end] {
+            let Code::Instruction(instr) = code else {
+                continue;
+            };
+            substitute_copied_args(instr, &copies);
+            if let Instruction::Value { args, op, .. } = instr {
+                if let Some(result) = computed.get(&(*op, args.clone())) {
+                    *op = ValueOps::Id;
+                    *args = vec![result.clone()];
+                    replaced += 1;
+                }
+            }
+            if let Instruction::Constant { dest, .. } | Instruction::Value { dest, .. } = instr {
+                copies.remove(dest);
+                copies.retain(|_, source| source != dest);
+                computed.retain(|(_, args), result| result != dest && !args.contains(dest));
+            }
+            if let Instruction::Value { args, dest, op, .. } = instr {
+                match args.as_slice() {
+                    [source] if *op == ValueOps::Id && source != dest => {
+                        copies.insert(dest.clone(), source.clone());
+                    }
+                    _ if is_pure(*op) && !args.contains(dest) => {
+                        computed.insert((*op, args.clone()), dest.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    replaced
+}
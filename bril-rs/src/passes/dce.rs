@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+
+use crate::analysis::blocks;
+use crate::{Code, Function, Instruction, ValueOps};
+
+use super::live_in;
+
+// Whether an assignment of `code` can be removed when nothing reads its result. Calls can have other effects, and an `alloc` that is never freed is a leak that `brili` reports, so those stay
+const fn is_removable(code: &Code) -> bool {
+    match code {
+        Code::Instruction(Instruction::Constant { .. }) => true,
+        Code::Instruction(Instruction::Value { op, .. }) => match op {
+            ValueOps::Call => false,
+            #[cfg(feature = "memory")]
+            ValueOps::Alloc => false,
+            _ => true,
+        },
+        Code::Instruction(Instruction::Effect { .. }) | Code::Label { .. } => false,
+    }
+}
+
+// The indices in `instrs` of the assignments whose result is overwritten or never read on any path after them
+pub(super) fn dead_assignments(func: &Function) -> HashSet<usize> {
+    let blocks = blocks(func);
+    let live = live_in(func, &blocks);
+    let mut dead = HashSet::new();
+    for block in &blocks {
+        let mut live: HashSet<&String> = block
+            .successors
+            .iter()
+            .flat_map(|&s| live[s].iter().copied())
+            .collect();
+        for index in (block.start..block.end).rev() {
+            let code = &func.instrs[index];
+            match code {
+                Code::Instruction(Instruction::Constant { dest, .. }) => {
+                    if !live.remove(dest) && is_removable(code) {
+                        dead.insert(index);
+                    }
+                }
+                Code::Instruction(Instruction::Value { dest, args, .. }) => {
+                    if !live.remove(dest) && is_removable(code) {
+                        dead.insert(index);
+                    } else {
+                        live.extend(args);
+                    }
+                }
+                Code::Instruction(Instruction::Effect { args, .. }) => live.extend(args),
+                Code::Label { .. } => {}
+            }
+        }
+    }
+    dead
+}
+
+/// Removes every `const` and value operation in `func` whose result is never used, and returns how many instructions were removed.
+///
+/// A result is unused when its variable isn't read later in its block before being assigned again, and isn't live going into any block that can come after. Removing one can leave the assignments of its arguments unused too, so this repeats until there is nothing left to remove, which also means that running it again doesn't change anything.
+///
+/// `call` and `alloc` are kept even when their result is unused, since a call can print or change memory and removing an `alloc` could hide a leak.
+///
+/// ```
+/// use bril_rs::passes::eliminate_dead_code;
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "instrs": [
+///     {"op": "const", "dest": "a", "type": "int", "value": 1},
+///     {"op": "const", "dest": "b", "type": "int", "value": 2},
+///     {"op": "add", "dest": "c", "type": "int", "args": ["a", "b"]},
+///     {"op": "const", "dest": "b", "type": "int", "value": 3},
+///     {"op": "print", "args": ["b"]}
+/// ]}]}"#).unwrap();
+/// let mut func = prog.functions[0].clone();
+/// assert_eq!(eliminate_dead_code(&mut func), 3);
+/// assert_eq!(func.instrs.len(), 2);
+/// assert_eq!(eliminate_dead_code(&mut func), 0);
+/// ```
+pub fn eliminate_dead_code(func: &mut Function) -> usize {
+    let mut removed = 0;
+    loop {
+        let dead = dead_assignments(func);
+        if dead.is_empty() {
+            return removed;
+        }
+        removed += dead.len();
+        let mut index = 0;
+        func.instrs.retain(|_| {
+            index += 1;
+            !dead.contains(&(index - 1))
+        });
+    }
+}
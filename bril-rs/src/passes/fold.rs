@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::analysis::blocks;
+use crate::{Code, ConstOps, Function, Instruction, Literal, ValueOps};
+
+// The value of a `float` constant, which is written without a decimal point when it is a whole number and so reads as an `Int`
+#[cfg(feature = "float")]
+#[allow(clippy::cast_precision_loss)]
+const fn as_float(literal: &Literal) -> Option<f64> {
+    match literal {
+        Literal::Float(x) => Some(*x),
+        Literal::Int(i) => Some(*i as f64),
+        _ => None,
+    }
+}
+
+// `evaluate` for the operations on `float`
+#[cfg(feature = "float")]
+fn fold_float(op: ValueOps, args: &[&Literal]) -> Option<Literal> {
+    let [a, b] = args else {
+        return None;
+    };
+    let (a, b) = (as_float(a)?, as_float(b)?);
+    let result = match op {
+        ValueOps::Fadd => a + b,
+        ValueOps::Fsub => a - b,
+        ValueOps::Fmul => a * b,
+        ValueOps::Fdiv => a / b,
+        #[allow(clippy::float_cmp)]
+        ValueOps::Feq => return Some(Literal::Bool(a == b)),
+        ValueOps::Flt => return Some(Literal::Bool(a < b)),
+        ValueOps::Fgt => return Some(Literal::Bool(a > b)),
+        ValueOps::Fle => return Some(Literal::Bool(a <= b)),
+        ValueOps::Fge => return Some(Literal::Bool(a >= b)),
+        _ => return None,
+    };
+    // Infinity and NaN can't be written as a constant
+    result.is_finite().then_some(Literal::Float(result))
+}
+
+// The result of `op` on `args`, or `None` if it isn't an arithmetic or comparison operation or can't be worked out ahead of time. Dividing an `int` by zero is left for when the program runs, where it is an error
+fn evaluate(op: ValueOps, args: &[&Literal]) -> Option<Literal> {
+    use Literal::{Bool, Int};
+    #[cfg(feature = "float")]
+    if let Some(result) = fold_float(op, args) {
+        return Some(result);
+    }
+    Some(match (op, args) {
+        (ValueOps::Add, [Int(a), Int(b)]) => Int(a.wrapping_add(*b)),
+        (ValueOps::Sub, [Int(a), Int(b)]) => Int(a.wrapping_sub(*b)),
+        (ValueOps::Mul, [Int(a), Int(b)]) => Int(a.wrapping_mul(*b)),
+        (ValueOps::Div, [Int(a), Int(b)]) if *b != 0 => Int(a.wrapping_div(*b)),
+        (ValueOps::Eq, [Int(a), Int(b)]) => Bool(a == b),
+        (ValueOps::Lt, [Int(a), Int(b)]) => Bool(a < b),
+        (ValueOps::Gt, [Int(a), Int(b)]) => Bool(a > b),
+        (ValueOps::Le, [Int(a), Int(b)]) => Bool(a <= b),
+        (ValueOps::Ge, [Int(a), Int(b)]) => Bool(a >= b),
+        (ValueOps::Not, [Bool(a)]) => Bool(!a),
+        (ValueOps::And, [Bool(a), Bool(b)]) => Bool(*a && *b),
+        (ValueOps::Or, [Bool(a), Bool(b)]) => Bool(*a || *b),
+        _ => return None,
+    })
+}
+
+/// Replaces each arithmetic or comparison operation in `func` whose arguments are all constants with a `const` of its result, and returns how many were replaced.
+///
+/// This handles `add`, `sub`, `mul`, `div`, `eq`, `lt`, `gt`, `le`, `ge`, `not`, `and`, and `or`, along with the `float` versions of the arithmetic and comparisons. An argument counts as a constant when it was assigned by a `const` earlier in the same block, including one that this pass made. `int` arithmetic wraps around like it does when the program runs, and operations that would divide an `int` by zero, or give a `float` that is infinite or NaN, are left alone.
+///
+/// ```
+/// use bril_rs::passes::fold_constants;
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "instrs": [
+///     {"op": "const", "dest": "x", "type": "int", "value": 3},
+///     {"op": "const", "dest": "y", "type": "int", "value": 4},
+///     {"op": "add", "dest": "z", "type": "int", "args": ["x", "y"]},
+///     {"op": "lt", "dest": "b", "type": "bool", "args": ["z", "y"]},
+///     {"op": "print", "args": ["z", "b"]}
+/// ]}]}"#).unwrap();
+/// let mut func = prog.functions[0].clone();
+/// assert_eq!(fold_constants(&mut func), 2);
+/// let text = func.to_string();
+/// assert!(text.contains("z: int = const 7;"));
+/// assert!(text.contains("b: bool = const false;"));
+/// ```
+pub fn fold_constants(func: &mut Function) -> usize {
+    let mut folded = 0;
+    for block in blocks(func) {
+        let mut constants: HashMap<String, Literal> = HashMap::new();
+        for code in &mut func.instrs[block.start..block.end] {
+            match code {
+                Code::Instruction(Instruction::Constant { dest, value, .. }) => {
+                    constants.insert(dest.clone(), value.clone());
+                }
+                Code::Instruction(Instruction::Value {
+                    args,
+                    dest,
+                    op,
+                    #[cfg(feature = "position")]
+                    pos,
+                    op_type,
+                    ..
+                }) => {
+                    let value = args
+                        .iter()
+                        .map(|a| constants.get(a))
+                        .collect::<Option<Vec<_>>>()
+                        .and_then(|args| evaluate(*op, &args));
+                    let Some(value) = value else {
+                        constants.remove(dest);
+                        continue;
+                    };
+                    constants.insert(dest.clone(), value.clone());
+                    *code = Code::Instruction(Instruction::Constant {
+                        dest: std::mem::take(dest),
+                        op: ConstOps::Const,
+                        #[cfg(feature = "position")]
+                        pos: pos.take(),
+                        const_type: op_type.clone(),
+                        value,
+                    });
+                    folded += 1;
+                }
+                Code::Instruction(Instruction::Effect { .. }) | Code::Label { .. } => {}
+            }
+        }
+    }
+    folded
+}
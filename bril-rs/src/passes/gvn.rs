@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use crate::analysis::{ControlFlowGraph, DominanceTree};
+use crate::{Code, Function, Instruction, ValueOps};
+
+use super::cse::is_pure;
+use super::substitute_copied_args;
+
+// Walks down the dominator tree, keeping the operations computed in the blocks that dominate the current one
+struct Numberer<'a> {
+    cfg: &'a ControlFlowGraph,
+    // The blocks that each block immediately dominates, by their indices in `cfg.blocks`
+    children: Vec<Vec<usize>>,
+    // How many times each variable is assigned, counting arguments of the function
+    assignments: HashMap<String, usize>,
+    // The variable holding the result of each operation, by its opcode and arguments
+    computed: HashMap<(ValueOps, Vec<String>), String>,
+    // The variable that each variable is a copy of
+    copies: HashMap<String, String>,
+    replaced: usize,
+}
+
+impl Numberer<'_> {
+    // Only a variable that is assigned once has the same value everywhere that its assignment dominates
+    fn is_single(&self, var: &str) -> bool {
+        self.assignments.get(var) == Some(&1)
+    }
+
+    fn number(&mut self, func: &mut Function, block: usize) {
+        let info = &self.cfg.blocks[block];
+        let mut inserted = Vec::new();
+        for code in &mut func.instrs[info.start..info.end] {
+            let Code::Instruction(instr) = code else {
+                continue;
+            };
+            substitute_copied_args(instr, &self.copies);
+            let Instruction::Value { args, dest, op, .. } = instr else {
+                continue;
+            };
+            if *op == ValueOps::Id {
+                if let [source] = args.as_slice() {
+                    if self.is_single(source) && self.is_single(dest) {
+                        self.copies.insert(dest.clone(), source.clone());
+                    }
+                }
+                continue;
+            }
+            if !is_pure(*op) || !args.iter().all(|a| self.is_single(a)) {
+                continue;
+            }
+            let key = (*op, args.clone());
+            if let Some(result) = self.computed.get(&key) {
+                *op = ValueOps::Id;
+                *args = vec![result.clone()];
+                if self.is_single(dest) {
+                    self.copies.insert(dest.clone(), result.clone());
+                }
+                self.replaced += 1;
+            } else if self.is_single(dest) {
+                self.computed.insert(key.clone(), dest.clone());
+                inserted.push(key);
+            }
+        }
+
+        for child in self.children[block].clone() {
+            self.number(func, child);
+        }
+
+        // The blocks that this one doesn't dominate can't use what it computed
+        for key in inserted {
+            self.computed.remove(&key);
+        }
+    }
+}
+
+/// Replaces each value operation in `func` that was already computed where it is dominated with an `id` of that result, and returns how many were replaced.
+///
+/// `cfg` and `dom` are built from `func`, and an operation is dominated by the ones earlier in its block and in the blocks that dominate its block. Two operations compute the same thing when they have the same opcode and arguments. Only variables that are assigned once in the whole function, counting its arguments, are numbered, since those have the same value everywhere that their assignment dominates, so this finds the most in SSA form. The uses of a variable that is an `id` of another one are replaced with that variable too. Like [`super::eliminate_common_subexpressions`], calls, loads, `alloc`, `phi`, and `getglobal` are never replaced.
+///
+/// ```
+/// use bril_rs::analysis::{ControlFlowGraph, DominanceTree};
+/// use bril_rs::passes::global_value_number;
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "args": [{"name": "x", "type": "int"}, {"name": "c", "type": "bool"}], "instrs": [
+///     {"label": "entry"},
+///     {"op": "add", "dest": "a", "type": "int", "args": ["x", "x"]},
+///     {"op": "br", "args": ["c"], "labels": ["left", "right"]},
+///     {"label": "left"},
+///     {"op": "add", "dest": "b", "type": "int", "args": ["x", "x"]},
+///     {"op": "print", "args": ["b"]},
+///     {"op": "jmp", "labels": ["join"]},
+///     {"label": "right"},
+///     {"op": "mul", "dest": "d", "type": "int", "args": ["x", "x"]},
+///     {"op": "print", "args": ["d"]},
+///     {"op": "jmp", "labels": ["join"]},
+///     {"label": "join"},
+///     {"op": "mul", "dest": "e", "type": "int", "args": ["x", "x"]},
+///     {"op": "print", "args": ["e"]}
+/// ]}]}"#).unwrap();
+/// let mut func = prog.functions[0].clone();
+/// let cfg = ControlFlowGraph::build(&func);
+/// assert_eq!(global_value_number(&mut func, &cfg, &DominanceTree::build(&cfg)), 1);
+/// let text = func.to_string();
+/// assert!(text.contains("b: int = id a;"));
+/// assert!(text.contains("print a;"));
+/// assert!(text.contains("e: int = mul x x;"));
+/// ```
+///
+/// # Panics
+/// If `cfg` or `dom` wasn't built from `func`
+pub fn global_value_number(
+    func: &mut Function,
+    cfg: &ControlFlowGraph,
+    dom: &DominanceTree,
+) -> usize {
+    if cfg.blocks.is_empty() {
+        return 0;
+    }
+    let mut assignments: HashMap<String, usize> = HashMap::new();
+    let dests = func.instrs.iter().filter_map(|code| match code {
+        Code::Instruction(Instruction::Constant { dest, .. } | Instruction::Value { dest, .. }) => {
+            Some(dest)
+        }
+        _ => None,
+    });
+    for var in func.args.iter().map(|a| &a.name).chain(dests) {
+        *assignments.entry(var.clone()).or_default() += 1;
+    }
+
+    let index: HashMap<&str, usize> = cfg
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(b, info)| (info.label.as_str(), b))
+        .collect();
+    let mut children = vec![Vec::new(); cfg.blocks.len()];
+    for (b, info) in cfg.blocks.iter().enumerate() {
+        if let Some(idom) = dom.idom(&info.label) {
+            children[index[idom]].push(b);
+        }
+    }
+
+    let mut numberer = Numberer {
+        cfg,
+        children,
+        assignments,
+        computed: HashMap::new(),
+        copies: HashMap::new(),
+        replaced: 0,
+    };
+    numberer.number(func, 0);
+    numberer.replaced
+}
@@ -0,0 +1,295 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{Code, EffectOps, Function, Instruction, Program, ValueOps};
+
+use super::{fresh_name, label_names, variable_names};
+
+// Gives every variable and label of `callee` a name that is not used in `host`
+struct Renaming {
+    vars: HashMap<String, String>,
+    labels: HashMap<String, String>,
+}
+
+impl Renaming {
+    fn new(host: &Function, callee: &Function) -> Self {
+        let mut taken: HashSet<String> = variable_names(host);
+        taken.extend(label_names(host));
+        let mut rename = |names: HashSet<String>| -> HashMap<String, String> {
+            let mut names: Vec<String> = names.into_iter().collect();
+            names.sort();
+            names
+                .into_iter()
+                .map(|n| {
+                    let fresh = fresh_name(&format!("{}.{n}", callee.name), &taken);
+                    taken.insert(fresh.clone());
+                    (n, fresh)
+                })
+                .collect()
+        };
+        let vars = rename(variable_names(callee));
+        let labels = rename(label_names(callee));
+        Self { vars, labels }
+    }
+
+    fn var(&self, v: &str) -> String {
+        self.vars[v].clone()
+    }
+
+    fn vars(&self, vs: &[String]) -> Vec<String> {
+        vs.iter().map(|v| self.var(v)).collect()
+    }
+
+    fn labels(&self, ls: &[String]) -> Vec<String> {
+        ls.iter().map(|l| self.labels[l].clone()).collect()
+    }
+
+    fn instruction(&self, i: &Instruction) -> Instruction {
+        match i.clone() {
+            Instruction::Constant {
+                dest,
+                op,
+                #[cfg(feature = "position")]
+                pos,
+                const_type,
+                value,
+            } => Instruction::Constant {
+                dest: self.var(&dest),
+                op,
+                #[cfg(feature = "position")]
+                pos,
+                const_type,
+                value,
+            },
+            Instruction::Value {
+                args,
+                dest,
+                funcs,
+                labels,
+                op,
+                #[cfg(feature = "position")]
+                pos,
+                op_type,
+            } => Instruction::Value {
+                args: self.vars(&args),
+                dest: self.var(&dest),
+                funcs,
+                labels: self.labels(&labels),
+                op,
+                #[cfg(feature = "position")]
+                pos,
+                op_type,
+            },
+            Instruction::Effect {
+                args,
+                funcs,
+                labels,
+                op,
+                #[cfg(feature = "position")]
+                pos,
+            } => Instruction::Effect {
+                args: self.vars(&args),
+                funcs,
+                labels: self.labels(&labels),
+                op,
+                #[cfg(feature = "position")]
+                pos,
+            },
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "position"), allow(unused_variables))]
+fn id(dest: String, arg: String, op_type: crate::Type, instr: &Instruction) -> Code {
+    Code::Instruction(Instruction::Value {
+        args: vec![arg],
+        dest,
+        funcs: Vec::new(),
+        labels: Vec::new(),
+        op: ValueOps::Id,
+        #[cfg(feature = "position")]
+        pos: instr.get_pos(),
+        op_type,
+    })
+}
+
+#[cfg_attr(not(feature = "position"), allow(unused_variables))]
+fn jump(label: String, instr: &Instruction) -> Code {
+    Code::Instruction(Instruction::Effect {
+        args: Vec::new(),
+        funcs: Vec::new(),
+        labels: vec![label],
+        op: EffectOps::Jump,
+        #[cfg(feature = "position")]
+        pos: instr.get_pos(),
+    })
+}
+
+impl Function {
+    /// Replaces the call to `callee` at index `call_site` of `instrs` with the body of `callee`.
+    ///
+    /// The variables and labels of `callee` are renamed so they don't conflict with those of `self`. The call's arguments are copied into the callee's renamed parameters with `id`, and each `ret` becomes an `id` into the call's destination, if there is one, followed by a jump to a new label placed after the inlined body.
+    ///
+    /// # Panics
+    /// If the instruction at `call_site` is not a call to `callee`, or if the number of arguments doesn't match the callee's parameters.
+    #[must_use]
+    pub fn inline_into(&self, callee: &Self, call_site: usize) -> Self {
+        let call = match &self.instrs[call_site] {
+            Code::Instruction(
+                i @ (Instruction::Value {
+                    op: ValueOps::Call,
+                    funcs,
+                    ..
+                }
+                | Instruction::Effect {
+                    op: EffectOps::Call,
+                    funcs,
+                    ..
+                }),
+            ) if funcs.first() == Some(&callee.name) => i,
+            c => panic!("expected a call to @{} but found {c}", callee.name),
+        };
+        let (call_args, call_dest) = match call {
+            Instruction::Value {
+                args,
+                dest,
+                op_type,
+                ..
+            } => (args, Some((dest, op_type))),
+            Instruction::Effect { args, .. } => (args, None),
+            Instruction::Constant { .. } => unreachable!(),
+        };
+        assert_eq!(
+            call_args.len(),
+            callee.args.len(),
+            "call to @{} has the wrong number of arguments",
+            callee.name
+        );
+
+        let renaming = Renaming::new(self, callee);
+        let mut taken = label_names(self);
+        taken.extend(renaming.labels.values().cloned());
+        let end_label = fresh_name(&format!("{}.ret", callee.name), &taken);
+
+        let mut instrs = self.instrs[..call_site].to_vec();
+        instrs.extend(callee.args.iter().zip(call_args).map(|(param, arg)| {
+            id(
+                renaming.var(&param.name),
+                arg.clone(),
+                param.arg_type.clone(),
+                call,
+            )
+        }));
+        for code in &callee.instrs {
+            match code {
+                Code::Label {
+                    label,
+                    #[cfg(feature = "position")]
+                    pos,
+                } => instrs.push(Code::Label {
+                    label: renaming.labels[label].clone(),
+                    #[cfg(feature = "position")]
+                    pos: pos.clone(),
+                }),
+                Code::Instruction(
+                    ret @ Instruction::Effect {
+                        op: EffectOps::Return,
+                        args,
+                        ..
+                    },
+                ) => {
+                    if let (Some((dest, op_type)), Some(arg)) = (call_dest, args.first()) {
+                        instrs.push(id(dest.clone(), renaming.var(arg), op_type.clone(), ret));
+                    }
+                    instrs.push(jump(end_label.clone(), ret));
+                }
+                Code::Instruction(i) => instrs.push(Code::Instruction(renaming.instruction(i))),
+            }
+        }
+        instrs.push(Code::Label {
+            label: end_label,
+            #[cfg(feature = "position")]
+            pos: call.get_pos(),
+        });
+        instrs.extend_from_slice(&self.instrs[call_site + 1..]);
+
+        Self {
+            args: self.args.clone(),
+            instrs,
+            name: self.name.clone(),
+            #[cfg(feature = "position")]
+            pos: self.pos.clone(),
+            return_type: self.return_type.clone(),
+        }
+    }
+}
+
+// Whether `code` is a call to the function named `callee`
+fn is_call_to(code: &Code, callee: &str) -> bool {
+    matches!(
+        code,
+        Code::Instruction(
+            Instruction::Value {
+                op: ValueOps::Call,
+                funcs,
+                ..
+            } | Instruction::Effect {
+                op: EffectOps::Call,
+                funcs,
+                ..
+            },
+        ) if funcs.first().is_some_and(|f| f == callee)
+    )
+}
+
+/// Replaces every call to the function named `callee_name` in the other functions of `program` with the body of that function, using [`Function::inline_into`], and returns how many calls were replaced.
+///
+/// Calls inside `callee_name` itself are left alone, and so are the calls to it in the bodies that were just inlined, so a recursive function is only inlined one level deep. `callee_name` stays in `program`, and nothing changes if there is no function with that name.
+///
+/// ```
+/// use bril_rs::passes::inline_function;
+/// use bril_rs::Program;
+/// let mut prog: Program = serde_json::from_str(r#"{"functions": [
+///     {"name": "main", "instrs": [
+///         {"op": "const", "dest": "x", "type": "int", "value": 2},
+///         {"op": "call", "dest": "y", "type": "int", "funcs": ["double"], "args": ["x"]},
+///         {"op": "call", "dest": "z", "type": "int", "funcs": ["double"], "args": ["y"]},
+///         {"op": "print", "args": ["z"]}
+///     ]},
+///     {"name": "double", "args": [{"name": "x", "type": "int"}], "type": "int", "instrs": [
+///         {"op": "add", "dest": "x", "type": "int", "args": ["x", "x"]},
+///         {"op": "ret", "args": ["x"]}
+///     ]}
+/// ]}"#).unwrap();
+/// assert_eq!(inline_function(&mut prog, "double"), 2);
+/// assert!(!prog.functions[0].to_string().contains("call"));
+/// ```
+pub fn inline_function(program: &mut Program, callee_name: &str) -> usize {
+    let Some(callee) = program
+        .functions
+        .iter()
+        .find(|f| f.name == callee_name)
+        .cloned()
+    else {
+        return 0;
+    };
+    let mut inlined = 0;
+    for func in program
+        .functions
+        .iter_mut()
+        .filter(|f| f.name != callee_name)
+    {
+        let mut index = 0;
+        while let Some(call_site) = func.instrs[index..]
+            .iter()
+            .position(|code| is_call_to(code, callee_name))
+            .map(|i| index + i)
+        {
+            let len = func.instrs.len();
+            *func = func.inline_into(&callee, call_site);
+            // Carry on after the inlined body, which ends where the instruction after the call now is
+            index = call_site + func.instrs.len() - len + 1;
+            inlined += 1;
+        }
+    }
+    inlined
+}
@@ -0,0 +1,244 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::analysis::{Block, ControlFlowGraph, DominanceTree, NaturalLoop};
+use crate::{Code, EffectOps, Function, Instruction, ValueOps};
+
+use super::cse::is_pure;
+use super::split_loop::{falls_through, jump, label};
+use super::{fresh_name, label_names, live_in};
+
+// Whether `op` can stop the program with an error, which would happen even when the loop body wouldn't run if it were hoisted
+const fn can_fail(op: ValueOps) -> bool {
+    match op {
+        ValueOps::Div | ValueOps::Rem | ValueOps::Udiv | ValueOps::Urem => true,
+        #[cfg(feature = "char")]
+        ValueOps::Int2char => true,
+        _ => false,
+    }
+}
+
+// The blocks of `cfg` for `live_in`, in the same order
+fn cfg_blocks(cfg: &ControlFlowGraph) -> Vec<Block> {
+    let index: HashMap<&str, usize> = cfg
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(b, info)| (info.label.as_str(), b))
+        .collect();
+    cfg.blocks
+        .iter()
+        .map(|info| Block {
+            start: info.start,
+            end: info.end,
+            successors: cfg.successors[&info.label]
+                .iter()
+                .map(|s| index[s.as_str()])
+                .collect(),
+        })
+        .collect()
+}
+
+// The indices in `func.instrs` of the instructions of the loop with `header` and `body` that can move to its preheader, in an order where each one comes after the ones that assign its arguments
+fn invariants(
+    func: &Function,
+    cfg: &ControlFlowGraph,
+    dom: &DominanceTree,
+    header: &str,
+    body: &HashSet<String>,
+) -> Vec<usize> {
+    let blocks = cfg_blocks(cfg);
+    let live = live_in(func, &blocks);
+    let in_body: Vec<bool> = cfg.blocks.iter().map(|b| body.contains(&b.label)).collect();
+    let Some(header_index) = cfg.blocks.iter().position(|b| b.label == header) else {
+        return Vec::new();
+    };
+
+    // The blocks in the loop that can leave it, and the variables that are live where they go
+    let mut exiting = Vec::new();
+    let mut live_out = HashSet::new();
+    for (b, block) in blocks.iter().enumerate().filter(|&(b, _)| in_body[b]) {
+        for &s in block.successors.iter().filter(|&&s| !in_body[s]) {
+            exiting.push(cfg.blocks[b].label.as_str());
+            live_out.extend(live[s].iter().copied());
+        }
+    }
+
+    let mut assignments: HashMap<&String, usize> = HashMap::new();
+    for info in cfg.blocks.iter().filter(|b| body.contains(&b.label)) {
+        for code in &func.instrs[info.start..info.end] {
+            if let Code::Instruction(
+                Instruction::Constant { dest, .. } | Instruction::Value { dest, .. },
+            ) = code
+            {
+                *assignments.entry(dest).or_default() += 1;
+            }
+        }
+    }
+
+    let mut hoisted = Vec::new();
+    let mut invariant: HashSet<&String> = HashSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for info in cfg.blocks.iter().filter(|b| body.contains(&b.label)) {
+            // A value that is still used after the loop has to be the one it would have had, so it can only move if it is computed on every way out
+            let reaches_exits = exiting.iter().all(|e| dom.dominates(&info.label, e));
+            for index in info.start..info.end {
+                let (dest, args) = match &func.instrs[index] {
+                    Code::Instruction(Instruction::Constant { dest, .. }) => (dest, &[][..]),
+                    Code::Instruction(Instruction::Value { dest, args, op, .. })
+                        if (is_pure(*op) || *op == ValueOps::Id) && !can_fail(*op) =>
+                    {
+                        (dest, args.as_slice())
+                    }
+                    _ => continue,
+                };
+                if invariant.contains(dest)
+                    || assignments[dest] != 1
+                    || live[header_index].contains(dest)
+                    || (live_out.contains(dest) && !reaches_exits)
+                {
+                    continue;
+                }
+                if args
+                    .iter()
+                    .all(|a| !assignments.contains_key(a) || invariant.contains(a))
+                {
+                    invariant.insert(dest);
+                    hoisted.push(index);
+                    changed = true;
+                }
+            }
+        }
+    }
+    hoisted
+}
+
+/// Moves the instructions in `loops` that compute the same value on every iteration into a new block before their loop, and returns how many were moved.
+///
+/// `loops` and `dom` are built from `func`, like by [`crate::analysis::find_natural_loops`]. A `const`, or a value operation other than a call, load, `alloc`, `phi`, or `getglobal`, is moved when none of its arguments are assigned in the loop, except by instructions that are moved too. Its destination must also only be assigned there in the loop, must not be read in the loop before it is assigned, and must either not be used after the loop or be assigned in a block that dominates every way out of it. Operations that can fail, like `div`, stay, since the loop might not have reached them.
+///
+/// The new block is named after the loop header, like `loop.preheader`, and every jump into the loop from outside of it goes there instead, while the back edges still go to the header. Loops with the same header are treated as one, inner loops are handled before the ones around them, and loops whose header has a `phi` are left alone.
+///
+/// ```
+/// use bril_rs::analysis::{find_natural_loops, ControlFlowGraph, DominanceTree};
+/// use bril_rs::passes::hoist_loop_invariants;
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "args": [{"name": "n", "type": "int"}], "instrs": [
+///     {"op": "const", "dest": "i", "type": "int", "value": 0},
+///     {"label": "loop"},
+///     {"op": "lt", "dest": "more", "type": "bool", "args": ["i", "n"]},
+///     {"op": "br", "args": ["more"], "labels": ["body", "done"]},
+///     {"label": "body"},
+///     {"op": "const", "dest": "a", "type": "int", "value": 2},
+///     {"op": "mul", "dest": "b", "type": "int", "args": ["a", "n"]},
+///     {"op": "add", "dest": "i", "type": "int", "args": ["i", "b"]},
+///     {"op": "jmp", "labels": ["loop"]},
+///     {"label": "done"},
+///     {"op": "print", "args": ["i"]}
+/// ]}]}"#).unwrap();
+/// let mut func = prog.functions[0].clone();
+/// let cfg = ControlFlowGraph::build(&func);
+/// let dom = DominanceTree::build(&cfg);
+/// assert_eq!(hoist_loop_invariants(&mut func, &find_natural_loops(&cfg, &dom), &dom), 2);
+/// let text = func.to_string();
+/// assert!(text.contains(".loop.preheader:\n  a: int = const 2;\n  b: int = mul a n;\n.loop:"));
+/// ```
+///
+/// # Panics
+/// If `loops` or `dom` wasn't built from `func`
+pub fn hoist_loop_invariants(
+    func: &mut Function,
+    loops: &[NaturalLoop],
+    dom: &DominanceTree,
+) -> usize {
+    let mut bodies: HashMap<&str, HashSet<String>> = HashMap::new();
+    for l in loops {
+        bodies
+            .entry(&l.header)
+            .or_default()
+            .extend(l.body.iter().cloned());
+    }
+    let mut headers: Vec<&str> = bodies.keys().copied().collect();
+    headers.sort_by_key(|h| (bodies[h].len(), *h));
+
+    let mut moved = 0;
+    // The preheader made for each header, which is part of every loop around that header's loop
+    let mut preheaders: HashMap<&str, String> = HashMap::new();
+    for header in headers {
+        let mut body = bodies[header].clone();
+        let inner: Vec<String> = preheaders
+            .iter()
+            .filter(|(h, _)| body.contains(**h))
+            .map(|(_, p)| p.clone())
+            .collect();
+        body.extend(inner);
+        let cfg = ControlFlowGraph::build(func);
+        let Some(header_info) = cfg.blocks.iter().find(|b| b.label == header) else {
+            continue;
+        };
+        // The phis would need their labels for the blocks outside the loop changed, and might need a phi in the preheader to merge them
+        #[cfg(feature = "ssa")]
+        if func.instrs[header_info.start..header_info.end]
+            .iter()
+            .any(|code| {
+                matches!(
+                    code,
+                    Code::Instruction(Instruction::Value {
+                        op: ValueOps::Phi,
+                        ..
+                    })
+                )
+            })
+        {
+            continue;
+        }
+        let hoisted = invariants(func, &cfg, dom, header, &body);
+        if hoisted.is_empty() {
+            continue;
+        }
+
+        let preheader = fresh_name(&format!("{header}.preheader"), &label_names(func));
+        let outside: HashSet<usize> = cfg
+            .blocks
+            .iter()
+            .filter(|b| !body.contains(&b.label))
+            .flat_map(|b| b.start..b.end)
+            .collect();
+        let hoisted_set: HashSet<usize> = hoisted.iter().copied().collect();
+        let mut instrs = Vec::with_capacity(func.instrs.len() + 2);
+        for (index, code) in func.instrs.iter().enumerate() {
+            if index == header_info.start {
+                // Control that fell into the header from inside the loop has to skip the preheader
+                let previous = (0..index).rev().find(|i| !hoisted_set.contains(i));
+                if previous.is_some_and(|i| !outside.contains(&i) && falls_through(&func.instrs[i]))
+                {
+                    instrs.push(jump(header));
+                }
+                instrs.push(label(&preheader));
+                instrs.extend(hoisted.iter().map(|&i| func.instrs[i].clone()));
+            }
+            if hoisted_set.contains(&index) {
+                continue;
+            }
+            let mut code = code.clone();
+            if outside.contains(&index) {
+                if let Code::Instruction(Instruction::Effect {
+                    op: EffectOps::Jump | EffectOps::Branch,
+                    labels,
+                    ..
+                }) = &mut code
+                {
+                    for l in labels.iter_mut().filter(|l| *l == header) {
+                        l.clone_from(&preheader);
+                    }
+                }
+            }
+            instrs.push(code);
+        }
+        func.instrs = instrs;
+        moved += hoisted.len();
+        preheaders.insert(header, preheader);
+    }
+    moved
+}
@@ -0,0 +1,162 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::analysis::Block;
+use crate::{Code, Function, Instruction, Type};
+
+mod copy;
+mod cps;
+mod cse;
+mod dce;
+mod fold;
+mod gvn;
+mod inline;
+mod licm;
+mod split_loop;
+/// Conversion of functions into SSA form
+#[cfg(feature = "ssa")]
+pub mod ssa;
+pub use copy::propagate_copies;
+pub use cps::APPLY_CONTINUATION;
+pub use cse::eliminate_common_subexpressions;
+pub use dce::eliminate_dead_code;
+pub use fold::fold_constants;
+pub use gvn::global_value_number;
+pub use inline::inline_function;
+pub use licm::hoist_loop_invariants;
+pub use split_loop::SplitLoopError;
+
+// Every variable name that is an argument, destination, or operand in `func`
+fn variable_names(func: &Function) -> HashSet<String> {
+    let mut names: HashSet<String> = func.args.iter().map(|a| a.name.clone()).collect();
+    for code in &func.instrs {
+        match code {
+            Code::Label { .. } => {}
+            Code::Instruction(Instruction::Constant { dest, .. }) => {
+                names.insert(dest.clone());
+            }
+            Code::Instruction(Instruction::Value { dest, args, .. }) => {
+                names.insert(dest.clone());
+                names.extend(args.iter().cloned());
+            }
+            Code::Instruction(Instruction::Effect { args, .. }) => {
+                names.extend(args.iter().cloned());
+            }
+        }
+    }
+    names
+}
+
+// The type of every argument and destination in `func`
+fn variable_types(func: &Function) -> HashMap<&String, &Type> {
+    let mut types: HashMap<&String, &Type> =
+        func.args.iter().map(|a| (&a.name, &a.arg_type)).collect();
+    for code in &func.instrs {
+        match code {
+            Code::Instruction(Instruction::Constant {
+                dest, const_type, ..
+            }) => {
+                types.insert(dest, const_type);
+            }
+            Code::Instruction(Instruction::Value { dest, op_type, .. }) => {
+                types.insert(dest, op_type);
+            }
+            _ => {}
+        }
+    }
+    types
+}
+
+// Replaces the operands of `instr` that are keys of `copies` with the variables they are copies of, and returns how many were replaced
+fn substitute_copied_args(instr: &mut Instruction, copies: &HashMap<String, String>) -> usize {
+    match instr {
+        // A phi reads its arguments at the end of a predecessor, where a copy from this block may not have happened yet
+        #[cfg(feature = "ssa")]
+        Instruction::Value {
+            op: crate::ValueOps::Phi,
+            ..
+        } => 0,
+        Instruction::Constant { .. } => 0,
+        Instruction::Value { args, .. } | Instruction::Effect { args, .. } => {
+            let mut replaced = 0;
+            for arg in args {
+                if let Some(source) = copies.get(arg) {
+                    arg.clone_from(source);
+                    replaced += 1;
+                }
+            }
+            replaced
+        }
+    }
+}
+
+// The variables that `block` reads before writing them, and the ones that it writes
+fn uses_and_defs<'a>(
+    func: &'a Function,
+    block: &Block,
+) -> (HashSet<&'a String>, HashSet<&'a String>) {
+    let mut uses = HashSet::new();
+    let mut defs = HashSet::new();
+    for code in &func.instrs[block.start..block.end] {
+        match code {
+            Code::Instruction(Instruction::Value { args, dest, .. }) => {
+                uses.extend(args.iter().filter(|a| !defs.contains(a)));
+                defs.insert(dest);
+            }
+            Code::Instruction(Instruction::Effect { args, .. }) => {
+                uses.extend(args.iter().filter(|a| !defs.contains(a)));
+            }
+            Code::Instruction(Instruction::Constant { dest, .. }) => {
+                defs.insert(dest);
+            }
+            Code::Label { .. } => {}
+        }
+    }
+    (uses, defs)
+}
+
+// The variables that are live when control reaches the start of each block
+fn live_in<'a>(func: &'a Function, blocks: &[Block]) -> Vec<HashSet<&'a String>> {
+    let uses_and_defs: Vec<_> = blocks.iter().map(|b| uses_and_defs(func, b)).collect();
+    let mut live = vec![HashSet::new(); blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for b in (0..blocks.len()).rev() {
+            let (uses, defs) = &uses_and_defs[b];
+            let mut new: HashSet<&String> = blocks[b]
+                .successors
+                .iter()
+                .flat_map(|&s| live[s].iter().copied())
+                .filter(|v| !defs.contains(v))
+                .collect();
+            new.extend(uses);
+            if new != live[b] {
+                live[b] = new;
+                changed = true;
+            }
+        }
+    }
+    live
+}
+
+// Every label in `func`
+fn label_names(func: &Function) -> HashSet<String> {
+    func.instrs
+        .iter()
+        .filter_map(|code| match code {
+            Code::Label { label, .. } => Some(label.clone()),
+            Code::Instruction(_) => None,
+        })
+        .collect()
+}
+
+// Picks a name starting with `base` that is not already in `taken`
+fn fresh_name(base: &str, taken: &HashSet<String>) -> String {
+    let mut name = base.to_string();
+    let mut count = 0;
+    while taken.contains(&name) {
+        name = format!("{base}.{count}");
+        count += 1;
+    }
+    name
+}
@@ -0,0 +1,299 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use thiserror::Error;
+
+use crate::analysis::{blocks, find_natural_loops, ControlFlowGraph, DominanceTree};
+use crate::{Argument, Code, EffectOps, Function, Instruction, ValueOps};
+
+use super::{fresh_name, label_names, live_in, variable_types};
+
+// Whether control can go on to the next instruction after `code`
+pub(super) const fn falls_through(code: &Code) -> bool {
+    !matches!(
+        code,
+        Code::Instruction(Instruction::Effect {
+            op: EffectOps::Jump | EffectOps::Branch | EffectOps::Return | EffectOps::Abort,
+            ..
+        })
+    )
+}
+
+pub(super) fn label(label: &str) -> Code {
+    Code::Label {
+        label: label.to_string(),
+        #[cfg(feature = "position")]
+        pos: None,
+    }
+}
+
+pub(super) fn jump(label: &str) -> Code {
+    Code::Instruction(Instruction::Effect {
+        args: Vec::new(),
+        funcs: Vec::new(),
+        labels: vec![label.to_string()],
+        op: EffectOps::Jump,
+        #[cfg(feature = "position")]
+        pos: None,
+    })
+}
+
+fn ret(arg: Option<&String>) -> Code {
+    Code::Instruction(Instruction::Effect {
+        args: arg.into_iter().cloned().collect(),
+        funcs: Vec::new(),
+        labels: Vec::new(),
+        op: EffectOps::Return,
+        #[cfg(feature = "position")]
+        pos: None,
+    })
+}
+
+/// The reason that [`Function::split_loop_body`] couldn't move a loop into a function of its own
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SplitLoopError {
+    /// The label isn't the header of a natural loop
+    #[error("the label is not the header of a loop")]
+    NotALoop,
+    /// The loop exits to more than one block, so there is no single place to go after calling the loop function
+    #[error("the loop exits to more than one block")]
+    MultipleExits,
+    /// The loop contains a `ret`
+    #[error("the loop returns from the function")]
+    Returns,
+    /// More than one variable modified in the loop is live after it, but a function can only return one of them. These are their names in sorted order.
+    #[error("more than one variable modified in the loop is live after it: {}", .0.join(", "))]
+    MultipleResults(Vec<String>),
+}
+
+impl Function {
+    /// Moves the natural loop whose header is the label `loop_header` into a function of its own, returning this function with the loop replaced by a call along with the new loop function.
+    ///
+    /// The loop function is named `<function>.<loop_header>`. Its arguments are the variables that are live at the loop header, in sorted order, and it runs the loop until it exits. Since Bril functions return at most one value, it returns the variable modified in the loop that is live after it, if there is one. In this function, the loop header's block becomes a call to the loop function followed by a jump to where the loop exits to.
+    ///
+    /// ```
+    /// use bril_rs::{passes::SplitLoopError, Program};
+    /// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "instrs": [
+    ///     {"op": "const", "dest": "i", "type": "int", "value": 0},
+    ///     {"op": "const", "dest": "n", "type": "int", "value": 10},
+    ///     {"label": "loop"},
+    ///     {"op": "lt", "dest": "more", "type": "bool", "args": ["i", "n"]},
+    ///     {"op": "br", "args": ["more"], "labels": ["body", "done"]},
+    ///     {"label": "body"},
+    ///     {"op": "add", "dest": "i", "type": "int", "args": ["i", "i"]},
+    ///     {"op": "jmp", "labels": ["loop"]},
+    ///     {"label": "done"},
+    ///     {"op": "print", "args": ["i"]}
+    /// ]}]}"#).unwrap();
+    /// let (outer, body) = prog.functions[0].split_loop_body("loop").unwrap();
+    /// assert_eq!(body.name, "main.loop");
+    /// let params: Vec<_> = body.args.iter().map(|a| a.name.as_str()).collect();
+    /// assert_eq!(params, ["i", "n"]);
+    /// assert!(outer.to_string().contains("i: int = call @main.loop i n;"));
+    ///
+    /// let both: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "instrs": [
+    ///     {"op": "const", "dest": "i", "type": "int", "value": 0},
+    ///     {"op": "const", "dest": "s", "type": "int", "value": 0},
+    ///     {"label": "loop"},
+    ///     {"op": "add", "dest": "s", "type": "int", "args": ["s", "i"]},
+    ///     {"op": "add", "dest": "i", "type": "int", "args": ["i", "s"]},
+    ///     {"op": "lt", "dest": "more", "type": "bool", "args": ["i", "s"]},
+    ///     {"op": "br", "args": ["more"], "labels": ["loop", "done"]},
+    ///     {"label": "done"},
+    ///     {"op": "print", "args": ["i", "s"]}
+    /// ]}]}"#).unwrap();
+    /// assert_eq!(
+    ///     both.functions[0].split_loop_body("loop").unwrap_err(),
+    ///     SplitLoopError::MultipleResults(vec!["i".to_string(), "s".to_string()])
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// A [`SplitLoopError`] if `loop_header` isn't the label of a loop header, if the loop exits to more than one block, if it contains a `ret`, or if more than one variable that it modifies is live after it.
+    pub fn split_loop_body(&self, loop_header: &str) -> Result<(Self, Self), SplitLoopError> {
+        let blocks = blocks(self);
+        let cfg = ControlFlowGraph::build(self);
+        let index: HashMap<&str, usize> = cfg
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(b, info)| (info.label.as_str(), b))
+            .collect();
+        // Back edges to the same header make up one loop
+        let body: BTreeSet<usize> = find_natural_loops(&cfg, &DominanceTree::build(&cfg))
+            .into_iter()
+            .filter(|l| l.header == loop_header)
+            .flat_map(|l| l.body)
+            .map(|b| index[b.as_str()])
+            .collect();
+        if body.is_empty() {
+            return Err(SplitLoopError::NotALoop);
+        }
+        let header = index[loop_header];
+        let code = |b: usize| &self.instrs[blocks[b].start..blocks[b].end];
+
+        let exits: BTreeSet<usize> = body
+            .iter()
+            .flat_map(|&b| blocks[b].successors.iter().copied())
+            .filter(|s| !body.contains(s))
+            .collect();
+        if exits.len() > 1 {
+            return Err(SplitLoopError::MultipleExits);
+        }
+        let exit = exits.first().copied();
+        if body.iter().flat_map(|&b| code(b)).any(|c| {
+            matches!(
+                c,
+                Code::Instruction(Instruction::Effect {
+                    op: EffectOps::Return,
+                    ..
+                })
+            )
+        }) {
+            return Err(SplitLoopError::Returns);
+        }
+
+        let live = live_in(self, &blocks);
+        let types = variable_types(self);
+        let mut params: Vec<&String> = live[header].iter().copied().collect();
+        params.sort();
+        let modified: HashSet<&String> = body
+            .iter()
+            .flat_map(|&b| code(b))
+            .filter_map(|c| match c {
+                Code::Instruction(
+                    Instruction::Constant { dest, .. } | Instruction::Value { dest, .. },
+                ) => Some(dest),
+                _ => None,
+            })
+            .collect();
+        let mut results: Vec<&String> = exit.map_or_else(Vec::new, |e| {
+            live[e]
+                .iter()
+                .copied()
+                .filter(|v| modified.contains(v))
+                .collect()
+        });
+        if results.len() > 1 {
+            results.sort();
+            return Err(SplitLoopError::MultipleResults(
+                results.into_iter().cloned().collect(),
+            ));
+        }
+        let result = results.first().copied();
+
+        let taken = label_names(self);
+        let exit_label = exit.map(|e| match &self.instrs[blocks[e].start] {
+            Code::Label { label, .. } => label.clone(),
+            Code::Instruction(_) => fresh_name(&format!("{loop_header}.exit"), &taken),
+        });
+        let done = fresh_name("done", &taken);
+        let name = format!("{}.{loop_header}", self.name);
+
+        // The loop function starts at the header and goes to `done` wherever the loop exits
+        let mut loop_instrs = Vec::new();
+        if body.first() != Some(&header) {
+            loop_instrs.push(jump(loop_header));
+        }
+        for &b in &body {
+            loop_instrs.extend(code(b).iter().map(|c| {
+                match c {
+                    Code::Instruction(Instruction::Effect {
+                        args,
+                        funcs,
+                        labels,
+                        op,
+                        #[cfg(feature = "position")]
+                        pos,
+                    }) => Code::Instruction(Instruction::Effect {
+                        args: args.clone(),
+                        funcs: funcs.clone(),
+                        labels: labels
+                            .iter()
+                            .map(|l| {
+                                if Some(l) == exit_label.as_ref() {
+                                    done.clone()
+                                } else {
+                                    l.clone()
+                                }
+                            })
+                            .collect(),
+                        op: *op,
+                        #[cfg(feature = "position")]
+                        pos: pos.clone(),
+                    }),
+                    c => c.clone(),
+                }
+            }));
+            if code(b).last().is_none_or(falls_through) && !body.contains(&(b + 1)) {
+                loop_instrs.push(jump(&done));
+            }
+        }
+        if exit.is_some() {
+            loop_instrs.push(label(&done));
+            loop_instrs.push(ret(result));
+        }
+        let loop_func = Self {
+            args: params
+                .iter()
+                .map(|&p| Argument {
+                    name: p.clone(),
+                    arg_type: types[p].clone(),
+                })
+                .collect(),
+            instrs: loop_instrs,
+            name: name.clone(),
+            #[cfg(feature = "position")]
+            pos: None,
+            return_type: result.map(|r| types[r].clone()),
+        };
+
+        // This function keeps everything outside of the loop and calls the loop function in place of the header
+        let args: Vec<String> = params.into_iter().cloned().collect();
+        let call = match result {
+            Some(r) => Instruction::Value {
+                args,
+                dest: r.clone(),
+                funcs: vec![name],
+                labels: Vec::new(),
+                op: ValueOps::Call,
+                #[cfg(feature = "position")]
+                pos: None,
+                op_type: types[r].clone(),
+            },
+            None => Instruction::Effect {
+                args,
+                funcs: vec![name],
+                labels: Vec::new(),
+                op: EffectOps::Call,
+                #[cfg(feature = "position")]
+                pos: None,
+            },
+        };
+        let mut instrs = Vec::with_capacity(self.instrs.len());
+        for b in 0..blocks.len() {
+            if b == header {
+                instrs.extend(
+                    code(b)
+                        .first()
+                        .filter(|c| matches!(c, Code::Label { .. }))
+                        .cloned(),
+                );
+                instrs.push(Code::Instruction(call.clone()));
+                if let Some(l) = &exit_label {
+                    instrs.push(jump(l));
+                }
+            } else if !body.contains(&b) {
+                if Some(b) == exit && !matches!(code(b).first(), Some(Code::Label { .. })) {
+                    instrs.extend(exit_label.as_deref().map(label));
+                }
+                instrs.extend(code(b).iter().cloned());
+            }
+        }
+        let outer = Self {
+            instrs,
+            ..self.clone()
+        };
+
+        Ok((outer, loop_func))
+    }
+}
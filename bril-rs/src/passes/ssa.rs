@@ -0,0 +1,291 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::analysis::{ControlFlowGraph, DominanceTree};
+use crate::{Code, Function, Instruction, Type, ValueOps};
+
+use super::{fresh_name, label_names, variable_names, variable_types};
+
+/// The argument that a `phi` gets for a path where its variable was never assigned, like in `examples/to_ssa.py`
+pub const UNDEFINED: &str = "__undefined";
+
+// Gives each assignment a new name while walking down the dominator tree, keeping the names that are in scope for each variable on a stack
+struct Renamer<'a> {
+    func: &'a Function,
+    cfg: &'a ControlFlowGraph,
+    // The blocks that each block immediately dominates, by their indices in `cfg.blocks`
+    children: Vec<Vec<usize>>,
+    // The variables that need a phi at the start of each block
+    phis: &'a HashMap<&'a str, BTreeSet<&'a str>>,
+    stacks: HashMap<&'a str, Vec<String>>,
+    counters: HashMap<&'a str, usize>,
+    taken: HashSet<String>,
+    // The renamed instructions of each block, without its label
+    blocks: HashMap<&'a str, Vec<Code>>,
+    // The new name of the phi for each block and variable
+    phi_dests: HashMap<(&'a str, &'a str), String>,
+    // The predecessors that the phi for each block and variable gets values from, and those values
+    phi_args: HashMap<(&'a str, &'a str), Vec<(String, String)>>,
+}
+
+impl<'a> Renamer<'a> {
+    // A new name for an assignment to `var`, like `x.0`, which isn't used anywhere else in the function
+    fn push_fresh(&mut self, var: &'a str, pushed: &mut Vec<&'a str>) -> String {
+        let counter = self.counters.entry(var).or_default();
+        let name = loop {
+            let name = format!("{var}.{counter}");
+            *counter += 1;
+            if self.taken.insert(name.clone()) {
+                break name;
+            }
+        };
+        self.stacks.entry(var).or_default().push(name.clone());
+        pushed.push(var);
+        name
+    }
+
+    // The name in scope for `var`, or `None` if it hasn't been assigned on this path
+    fn current(&self, var: &str) -> Option<&String> {
+        self.stacks.get(var).and_then(|s| s.last())
+    }
+
+    fn rename(&mut self, block: usize) {
+        let (func, cfg, phis) = (self.func, self.cfg, self.phis);
+        let info = &cfg.blocks[block];
+        let label = info.label.as_str();
+        let mut pushed = Vec::new();
+
+        for &var in phis.get(label).into_iter().flatten() {
+            let name = self.push_fresh(var, &mut pushed);
+            self.phi_dests.insert((label, var), name);
+        }
+
+        let mut renamed = Vec::with_capacity(info.end - info.start);
+        for code in &func.instrs[info.start..info.end] {
+            let Code::Instruction(instr) = code else {
+                continue;
+            };
+            let mut instr = instr.clone();
+            if let Instruction::Value { args, .. } | Instruction::Effect { args, .. } = &mut instr {
+                for arg in args {
+                    if let Some(name) = self.current(arg) {
+                        *arg = name.clone();
+                    }
+                }
+            }
+            if let Code::Instruction(
+                Instruction::Constant { dest, .. } | Instruction::Value { dest, .. },
+            ) = code
+            {
+                let name = self.push_fresh(dest, &mut pushed);
+                if let Instruction::Constant { dest, .. } | Instruction::Value { dest, .. } =
+                    &mut instr
+                {
+                    *dest = name;
+                }
+            }
+            renamed.push(Code::Instruction(instr));
+        }
+        self.blocks.insert(label, renamed);
+
+        let mut seen = HashSet::new();
+        for succ in &cfg.successors[label] {
+            let succ = succ.as_str();
+            if !seen.insert(succ) {
+                continue;
+            }
+            for &var in phis.get(succ).into_iter().flatten() {
+                let arg = self
+                    .current(var)
+                    .map_or_else(|| UNDEFINED.to_string(), Clone::clone);
+                self.phi_args
+                    .entry((succ, var))
+                    .or_default()
+                    .push((label.to_string(), arg));
+            }
+        }
+
+        for child in self.children[block].clone() {
+            self.rename(child);
+        }
+
+        for var in pushed {
+            self.stacks.get_mut(var).unwrap().pop();
+        }
+    }
+}
+
+/// Converts `func` into SSA form, where each variable is assigned exactly once, using `cfg` and `dom` built from it. This follows `examples/to_ssa.py`.
+///
+/// A `phi` for a variable goes at the start of each block in the iterated dominance frontier of the blocks that assign to it. Then every assignment gets a new name like `x.0`, and each use gets the name of the assignment that reaches it along the dominator tree. Arguments of the function keep their names. A `phi` gets [`UNDEFINED`] from a predecessor where its variable hasn't been assigned.
+///
+/// Blocks without a label get the name they have in `cfg` when a `phi` needs to refer to them, and when the entry block needs a `phi` because it is the target of a jump, a new empty block is added before it. Blocks that can't be reached are removed.
+///
+/// ```
+/// use bril_rs::analysis::{ControlFlowGraph, DominanceTree};
+/// use bril_rs::passes::ssa::convert_to_ssa;
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(r#"{"functions": [{"name": "main", "args": [{"name": "c", "type": "bool"}], "instrs": [
+///     {"label": "entry"},
+///     {"op": "br", "args": ["c"], "labels": ["left", "right"]},
+///     {"label": "left"},
+///     {"op": "const", "dest": "x", "type": "int", "value": 1},
+///     {"op": "jmp", "labels": ["join"]},
+///     {"label": "right"},
+///     {"op": "const", "dest": "x", "type": "int", "value": 2},
+///     {"op": "jmp", "labels": ["join"]},
+///     {"label": "join"},
+///     {"op": "print", "args": ["x"]}
+/// ]}]}"#).unwrap();
+/// let mut func = prog.functions[0].clone();
+/// let cfg = ControlFlowGraph::build(&func);
+/// convert_to_ssa(&mut func, &cfg, &DominanceTree::build(&cfg));
+/// let text = func.to_string();
+/// assert!(text.contains("x.0: int = const 1;"));
+/// assert!(text.contains("x.1: int = const 2;"));
+/// assert!(text.contains("x.2: int = phi x.0 x.1 .left .right;"));
+/// assert!(text.contains("print x.2;"));
+/// ```
+///
+/// # Panics
+/// If `cfg` or `dom` wasn't built from `func`
+pub fn convert_to_ssa(func: &mut Function, cfg: &ControlFlowGraph, dom: &DominanceTree) {
+    let Some(entry) = cfg.entry_label() else {
+        return;
+    };
+    let reachable: Vec<usize> = (0..cfg.blocks.len())
+        .filter(|&b| dom.dominates(&cfg.blocks[b].label, &cfg.blocks[b].label))
+        .collect();
+
+    // The blocks that assign to each variable
+    let mut defs: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for &b in &reachable {
+        let info = &cfg.blocks[b];
+        for code in &func.instrs[info.start..info.end] {
+            if let Code::Instruction(
+                Instruction::Constant { dest, .. } | Instruction::Value { dest, .. },
+            ) = code
+            {
+                let blocks = defs.entry(dest).or_default();
+                if blocks.last() != Some(&info.label.as_str()) {
+                    blocks.push(&info.label);
+                }
+            }
+        }
+    }
+
+    let mut phis: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    for (&var, blocks) in &defs {
+        let mut work = blocks.clone();
+        while let Some(b) = work.pop() {
+            for f in dom.dominance_frontier(b) {
+                // A phi is an assignment too, so its block's frontier needs one as well
+                if phis.entry(f).or_default().insert(var) {
+                    work.push(f);
+                }
+            }
+        }
+    }
+
+    let index: HashMap<&str, usize> = cfg
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(b, info)| (info.label.as_str(), b))
+        .collect();
+    let mut children = vec![Vec::new(); cfg.blocks.len()];
+    for &b in &reachable {
+        if let Some(idom) = dom.idom(&cfg.blocks[b].label) {
+            children[index[idom]].push(b);
+        }
+    }
+
+    let mut renamer = Renamer {
+        func: &*func,
+        cfg,
+        children,
+        phis: &phis,
+        stacks: func
+            .args
+            .iter()
+            .map(|a| (a.name.as_str(), vec![a.name.clone()]))
+            .collect(),
+        counters: HashMap::new(),
+        taken: variable_names(func),
+        blocks: HashMap::new(),
+        phi_dests: HashMap::new(),
+        phi_args: HashMap::new(),
+    };
+
+    // Control also comes into the entry from the start of the function, which needs a block of its own to be a predecessor of a phi
+    let mut taken_labels = label_names(func);
+    taken_labels.extend(cfg.blocks.iter().map(|b| b.label.clone()));
+    let pre_entry = phis.get(entry).map(|vars| {
+        let label = fresh_name(entry, &taken_labels);
+        for &var in vars {
+            let arg = renamer
+                .current(var)
+                .map_or_else(|| UNDEFINED.to_string(), Clone::clone);
+            renamer
+                .phi_args
+                .entry((entry, var))
+                .or_default()
+                .push((label.clone(), arg));
+        }
+        label
+    });
+    renamer.rename(0);
+
+    let Renamer {
+        mut blocks,
+        mut phi_dests,
+        mut phi_args,
+        ..
+    } = renamer;
+    let phi_preds: HashSet<String> = phi_args
+        .values()
+        .flatten()
+        .map(|(label, _)| label.clone())
+        .collect();
+    let types: HashMap<&str, &Type> = variable_types(func)
+        .into_iter()
+        .map(|(var, ty)| (var.as_str(), ty))
+        .collect();
+    let mut instrs = Vec::with_capacity(func.instrs.len());
+    if let Some(label) = &pre_entry {
+        instrs.push(Code::Label {
+            label: label.clone(),
+            #[cfg(feature = "position")]
+            pos: None,
+        });
+    }
+    for &b in &reachable {
+        let info = &cfg.blocks[b];
+        let label = info.label.as_str();
+        match &func.instrs[info.start] {
+            code @ Code::Label { .. } => instrs.push(code.clone()),
+            Code::Instruction(_) if phi_preds.contains(&info.label) => {
+                instrs.push(Code::Label {
+                    label: info.label.clone(),
+                    #[cfg(feature = "position")]
+                    pos: None,
+                });
+            }
+            Code::Instruction(_) => {}
+        }
+        for &var in phis.get(label).into_iter().flatten() {
+            let (labels, args) = phi_args.remove(&(label, var)).unwrap().into_iter().unzip();
+            instrs.push(Code::Instruction(Instruction::Value {
+                args,
+                dest: phi_dests.remove(&(label, var)).unwrap(),
+                funcs: Vec::new(),
+                labels,
+                op: ValueOps::Phi,
+                #[cfg(feature = "position")]
+                pos: None,
+                op_type: types[var].clone(),
+            }));
+        }
+        instrs.extend(blocks.remove(label).unwrap());
+    }
+    func.instrs = instrs;
+}
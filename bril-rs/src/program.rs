@@ -15,6 +15,14 @@ pub struct Program {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     /// A list of imports for this program
     pub imports: Vec<Import>,
+    #[cfg(feature = "global")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// A list of global variables, which every function can read with `getglobal` and write with `setglobal`
+    pub globals: Vec<GlobalVar>,
+    #[cfg(feature = "extern")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// A list of functions defined outside of Bril, like in C, that can be called by name
+    pub externs: Vec<ExternFunc>,
 }
 
 impl Display for Program {
@@ -23,6 +31,14 @@ impl Display for Program {
         for i in &self.imports {
             writeln!(f, "{i}")?;
         }
+        #[cfg(feature = "global")]
+        for g in &self.globals {
+            writeln!(f, "{g}")?;
+        }
+        #[cfg(feature = "extern")]
+        for e in &self.externs {
+            writeln!(f, "{e}")?;
+        }
         for func in &self.functions {
             writeln!(f, "{func}")?;
         }
@@ -44,7 +60,7 @@ pub struct Import {
 #[cfg(feature = "import")]
 impl Display for Import {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "from {}", self.path.display())?;
+        write!(f, "from \"{}\"", self.path.display())?;
         if !self.functions.is_empty() {
             write!(f, " import ")?;
             for (i, name) in self.functions.iter().enumerate() {
@@ -73,14 +89,98 @@ pub struct ImportedFunction {
 #[cfg(feature = "import")]
 impl Display for ImportedFunction {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.name)?;
+        write!(f, "@{}", self.name)?;
         if let Some(a) = self.alias.as_ref() {
-            write!(f, " as {a}")?;
+            write!(f, " as @{a}")?;
         }
         Ok(())
     }
 }
 
+/// A variable declared outside of any function, which keeps its value for the whole run of the program
+///
+/// In JSON, it is written like a constant without the `op`, as in `{"name": "count", "type": "int", "value": 0}`, and instructions refer to it by name in their `funcs`
+///
+/// ```
+/// use bril_rs::Program;
+/// let json = r#"{"functions": [{"name": "main", "instrs": [
+///     {"op": "getglobal", "dest": "c", "type": "int", "funcs": ["count"]},
+///     {"op": "setglobal", "args": ["c"], "funcs": ["count"]}
+/// ]}], "globals": [{"name": "count", "type": "int", "value": 0}]}"#;
+/// let prog: Program = serde_json::from_str(json).unwrap();
+/// assert_eq!(prog.globals[0].to_string(), "@count: int = 0;");
+/// assert_eq!(prog.functions[0].instrs[0].to_string(), "  c: int = getglobal @count;");
+/// let round_trip: Program = serde_json::from_value(serde_json::to_value(&prog).unwrap()).unwrap();
+/// assert_eq!(round_trip, prog);
+/// ```
+#[cfg(feature = "global")]
+#[cfg_attr(not(feature = "float"), derive(Eq))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GlobalVar {
+    /// The name of the global variable
+    pub name: String,
+    /// The type of the global variable
+    #[serde(rename = "type")]
+    pub ty: Type,
+    /// The value of the global variable when the program starts
+    #[serde(rename = "value")]
+    pub init: Literal,
+}
+
+#[cfg(feature = "global")]
+impl Display for GlobalVar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "@{}: {} = {};", self.name, self.ty, self.init)
+    }
+}
+
+/// The signature of a function without a Bril body, which is defined elsewhere, like in a C library. `call` refers to it by name like any other function
+///
+/// In JSON, it is written like a [Function] whose `args` are just types, as in `{"name": "llabs", "args": ["int"], "type": "int"}`
+///
+/// ```
+/// use bril_rs::Program;
+/// let prog: Program = serde_json::from_str(
+///     r#"{"functions": [], "externs": [{"name": "hypot", "args": ["float", "float"], "type": "float"}]}"#,
+/// )
+/// .unwrap();
+/// assert_eq!(prog.to_string(), "extern @hypot(float, float): float;\n");
+/// ```
+#[cfg(feature = "extern")]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExternFunc {
+    /// The name of the function, which is also its symbol when linking
+    pub name: String,
+    /// The types of the arguments of the function
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<Type>,
+    /// The possible return type of this function
+    #[serde(rename = "type")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_type: Option<Type>,
+}
+
+#[cfg(feature = "extern")]
+impl Display for ExternFunc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "extern @{}", self.name)?;
+        if !self.args.is_empty() {
+            write!(f, "(")?;
+            for (i, arg) in self.args.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{arg}")?;
+            }
+            write!(f, ")")?;
+        }
+        if let Some(tpe) = self.return_type.as_ref() {
+            write!(f, ": {tpe}")?;
+        }
+        write!(f, ";")
+    }
+}
+
 /// <https://capra.cs.cornell.edu/bril/lang/syntax.html#function>
 #[cfg_attr(not(feature = "float"), derive(Eq))]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -255,6 +355,35 @@ impl Instruction {
     }
 }
 
+impl Instruction {
+    /// The same instruction with its two arguments swapped, if its operation is commutative. Otherwise, like for `sub` or `lt`, this is `None`.
+    ///
+    /// This lets passes like value numbering put the arguments of commutative operations in a canonical order.
+    ///
+    /// ```
+    /// use bril_rs::Instruction;
+    /// let add: Instruction =
+    ///     serde_json::from_str(r#"{"op": "add", "dest": "c", "type": "int", "args": ["a", "b"]}"#).unwrap();
+    /// assert_eq!(add.commute().unwrap().to_string(), "c: int = add b a;");
+    /// let sub: Instruction =
+    ///     serde_json::from_str(r#"{"op": "sub", "dest": "c", "type": "int", "args": ["a", "b"]}"#).unwrap();
+    /// assert_eq!(sub.commute(), None);
+    /// ```
+    #[must_use]
+    pub fn commute(&self) -> Option<Self> {
+        match self {
+            Self::Value { op, args, .. } if args.len() == 2 && op.is_commutative() => {
+                let mut instr = self.clone();
+                if let Self::Value { args, .. } = &mut instr {
+                    args.swap(0, 1);
+                }
+                Some(instr)
+            }
+            _ => None,
+        }
+    }
+}
+
 impl Display for Instruction {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -314,6 +443,39 @@ impl Display for Instruction {
     }
 }
 
+/// The ways that [`format_instruction`] can write out an [Instruction]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Format {
+    /// The JSON object of the instruction, on one line
+    Json,
+    /// The Bril text format, which is also what [Display] uses for [Instruction]
+    #[default]
+    Text,
+}
+
+/// Writes out `instr` in the given [Format], for example in error messages
+///
+/// ```
+/// use bril_rs::{format_instruction, Format, Instruction};
+/// let instr: Instruction =
+///     serde_json::from_str(r#"{"op": "add", "dest": "c", "type": "int", "args": ["a", "b"]}"#)
+///         .unwrap();
+/// assert_eq!(format_instruction(&instr, Format::Text), "c: int = add a b;");
+/// assert_eq!(
+///     format_instruction(&instr, Format::Json),
+///     r#"{"args":["a","b"],"dest":"c","op":"add","type":"int"}"#
+/// );
+/// ```
+/// # Panics
+/// This can panic, though I'm not sure when since serialization should always succeed
+#[must_use]
+pub fn format_instruction(instr: &Instruction, fmt: Format) -> String {
+    match fmt {
+        Format::Json => serde_json::to_string(instr).unwrap(),
+        Format::Text => instr.to_string(),
+    }
+}
+
 /// <https://capra.cs.cornell.edu/bril/lang/syntax.html#constant>
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ConstOps {
@@ -331,6 +493,21 @@ impl Display for ConstOps {
 }
 
 /// <https://capra.cs.cornell.edu/bril/lang/syntax.html#effect-operation>
+///
+/// Each operation is written the same way in JSON as by [Display]:
+///
+/// ```
+/// use bril_rs::EffectOps;
+/// for name in [
+///     "jmp", "br", "call", "ret", "print", "printstr", "nop", "abort", "store", "free", "copy",
+///     "fill", "setglobal", "speculate", "commit", "guard",
+/// ] {
+///     let op: EffectOps = serde_json::from_value(name.into()).unwrap();
+///     assert_eq!(op.to_string(), name);
+///     assert_eq!(serde_json::to_value(op).unwrap(), name);
+/// }
+/// assert!(serde_json::from_value::<EffectOps>("jump".into()).is_err());
+/// ```
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum EffectOps {
@@ -347,14 +524,32 @@ pub enum EffectOps {
     Return,
     /// <https://capra.cs.cornell.edu/bril/lang/core.html#miscellaneous>
     Print,
+    /// Outputs the literal text in `funcs`, without a newline, so that output can be labeled like `print` can't
+    #[serde(rename = "printstr")]
+    PrintStr,
     /// <https://capra.cs.cornell.edu/bril/lang/core.html#miscellaneous>
     Nop,
+    /// Stops the program with an error, for paths that should never be taken
+    Abort,
     /// <https://capra.cs.cornell.edu/bril/lang/memory.html#operations>
     #[cfg(feature = "memory")]
     Store,
     /// <https://capra.cs.cornell.edu/bril/lang/memory.html#operations>
     #[cfg(feature = "memory")]
     Free,
+    /// Copies a number of values from one pointer to another
+    #[cfg(feature = "memory")]
+    #[serde(rename = "copy")]
+    MemCopy,
+    /// Sets a number of values starting at a pointer to the same value
+    #[cfg(feature = "memory")]
+    Fill,
+    /// Writes its argument to the global variable named in `funcs`
+    #[cfg(feature = "global")]
+    Setglobal,
+    /// Stores the vector in its second argument to the [`VECTOR_WIDTH`] elements starting at the pointer in its first
+    #[cfg(feature = "vector")]
+    Vstore,
     /// <https://capra.cs.cornell.edu/bril/lang/spec.html#operations>
     #[cfg(feature = "speculate")]
     Speculate,
@@ -374,11 +569,21 @@ impl Display for EffectOps {
             Self::Call => write!(f, "call"),
             Self::Return => write!(f, "ret"),
             Self::Print => write!(f, "print"),
+            Self::PrintStr => write!(f, "printstr"),
             Self::Nop => write!(f, "nop"),
+            Self::Abort => write!(f, "abort"),
             #[cfg(feature = "memory")]
             Self::Store => write!(f, "store"),
             #[cfg(feature = "memory")]
             Self::Free => write!(f, "free"),
+            #[cfg(feature = "memory")]
+            Self::MemCopy => write!(f, "copy"),
+            #[cfg(feature = "memory")]
+            Self::Fill => write!(f, "fill"),
+            #[cfg(feature = "global")]
+            Self::Setglobal => write!(f, "setglobal"),
+            #[cfg(feature = "vector")]
+            Self::Vstore => write!(f, "vstore"),
             #[cfg(feature = "speculate")]
             Self::Speculate => write!(f, "speculate"),
             #[cfg(feature = "speculate")]
@@ -390,6 +595,28 @@ impl Display for EffectOps {
 }
 
 /// <https://capra.cs.cornell.edu/bril/lang/syntax.html#value-operation>
+///
+/// Each operation is written the same way in JSON as by [Display], which is its name in the Bril specification:
+///
+/// ```
+/// use bril_rs::ValueOps;
+/// for name in [
+///     "add", "sub", "mul", "div", "eq", "lt", "gt", "le", "ge", "not", "and", "or", "call", "id",
+///     "select", "smax", "smin", "shl", "shr", "popcnt", "clz", "ctz", "bswap", "rotl", "rotr",
+///     "rem", "udiv", "urem", "ult", "ugt", "ule", "uge", "zext", "phi", "fadd", "fsub", "fmul",
+///     "fdiv", "feq", "flt", "fgt", "fle", "fge", "fne", "fmax", "fmin", "maxnum", "minnum",
+///     "fsqrt", "fabs", "fneg", "ceil", "floor", "round", "trunc", "pow", "fsin", "fcos", "fexp",
+///     "flog", "fma", "i2f", "f2i", "float2bits", "bits2float", "fcopysign", "isnan", "isinf",
+///     "ceq", "clt", "cgt", "cle", "cge", "char2int", "int2char", "alloc", "load", "ptradd",
+///     "ptrdiff", "peq", "plt", "pgt", "ple", "pge", "ptr2int", "int2ptr", "getglobal", "vadd",
+///     "vsub", "vmul", "vload",
+/// ] {
+///     let op: ValueOps = serde_json::from_value(name.into()).unwrap();
+///     assert_eq!(op.to_string(), name);
+///     assert_eq!(serde_json::to_value(op).unwrap(), name);
+/// }
+/// assert!(serde_json::from_value::<ValueOps>("ptr_add".into()).is_err());
+/// ```
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum ValueOps {
@@ -431,6 +658,34 @@ pub enum ValueOps {
     Shl,
     /// Shift right
     Shr,
+    /// Population count
+    Popcnt,
+    /// Count leading zeros
+    Clz,
+    /// Count trailing zeros
+    Ctz,
+    /// Reverse the order of the bytes
+    Bswap,
+    /// Rotate left by the second argument modulo 64
+    Rotl,
+    /// Rotate right by the second argument modulo 64
+    Rotr,
+    /// Signed remainder, which has the same sign as the first argument
+    Rem,
+    /// Division treating both arguments as unsigned
+    Udiv,
+    /// Remainder treating both arguments as unsigned
+    Urem,
+    /// Less than treating both arguments as unsigned
+    Ult,
+    /// Greater than treating both arguments as unsigned
+    Ugt,
+    /// Less than or equal treating both arguments as unsigned
+    Ule,
+    /// Greater than or equal treating both arguments as unsigned
+    Uge,
+    /// Converts a bool to an int, where `true` is 1 and `false` is 0
+    Zext,
     /// <https://capra.cs.cornell.edu/bril/lang/ssa.html#operations>
     #[cfg(feature = "ssa")]
     Phi,
@@ -461,12 +716,81 @@ pub enum ValueOps {
     /// <https://capra.cs.cornell.edu/bril/lang/float.html#operations>
     #[cfg(feature = "float")]
     Fge,
+    /// Float not equal. This is false when either argument is NaN, unlike `not` of `feq`
+    #[cfg(feature = "float")]
+    Fne,
     /// Float max
     #[cfg(feature = "float")]
     Fmax,
     /// Float min
     #[cfg(feature = "float")]
     Fmin,
+    /// Float max following IEEE 754-2008, where a NaN argument is ignored in favor of the other one
+    #[cfg(feature = "float")]
+    Maxnum,
+    /// Float min following IEEE 754-2008, where a NaN argument is ignored in favor of the other one
+    #[cfg(feature = "float")]
+    Minnum,
+    /// Float square root
+    #[cfg(feature = "float")]
+    Fsqrt,
+    /// Float absolute value
+    #[cfg(feature = "float")]
+    Fabs,
+    /// Float negation
+    #[cfg(feature = "float")]
+    Fneg,
+    /// Rounds a float up to the nearest integer
+    #[cfg(feature = "float")]
+    Ceil,
+    /// Rounds a float down to the nearest integer
+    #[cfg(feature = "float")]
+    Floor,
+    /// Rounds a float to the nearest integer, with halfway cases rounded away from zero
+    #[cfg(feature = "float")]
+    Round,
+    /// Rounds a float towards zero to the nearest integer
+    #[cfg(feature = "float")]
+    Trunc,
+    /// Raises the first float argument to the power of the second
+    #[cfg(feature = "float")]
+    Pow,
+    /// Float sine, in radians
+    #[cfg(feature = "float")]
+    Fsin,
+    /// Float cosine, in radians
+    #[cfg(feature = "float")]
+    Fcos,
+    /// Float natural exponential
+    #[cfg(feature = "float")]
+    Fexp,
+    /// Float natural logarithm
+    #[cfg(feature = "float")]
+    Flog,
+    /// Fused multiply-add, the first float argument times the second plus the third with a single rounding
+    #[cfg(feature = "float")]
+    Fma,
+    /// Converts an int to the nearest float
+    #[cfg(feature = "float")]
+    I2f,
+    /// Converts a float to an int by rounding towards zero. Out of range values saturate to the smallest or largest int and NaN becomes 0
+    #[cfg(feature = "float")]
+    F2i,
+    /// Reinterprets the bits of a float as an int
+    #[cfg(feature = "float")]
+    Float2bits,
+    /// Reinterprets the bits of an int as a float
+    #[cfg(feature = "float")]
+    Bits2float,
+    /// The magnitude of the first float argument with the sign of the second
+    #[cfg(feature = "float")]
+    Fcopysign,
+    /// Whether a float is NaN
+    #[cfg(feature = "float")]
+    Isnan,
+    /// Whether a float is positive or negative infinity
+    #[cfg(feature = "float")]
+    Isinf,
     /// <https://capra.cs.cornell.edu/bril/lang/char.html#operations>
     #[cfg(feature = "char")]
     Ceq,
@@ -497,6 +821,68 @@ pub enum ValueOps {
     /// <https://capra.cs.cornell.edu/bril/lang/memory.html#operations>
     #[cfg(feature = "memory")]
     PtrAdd,
+    /// How many elements the first pointer is past the second
+    #[cfg(feature = "memory")]
+    PtrDiff,
+    /// Whether two pointers refer to the same location
+    ///
+    /// `ptreq` is accepted as another name for it
+    #[cfg(feature = "memory")]
+    #[serde(alias = "ptreq")]
+    Peq,
+    /// Whether the first pointer comes before the second in their allocation
+    #[cfg(feature = "memory")]
+    Plt,
+    /// Whether the first pointer comes after the second in their allocation
+    #[cfg(feature = "memory")]
+    Pgt,
+    /// Whether the first pointer doesn't come after the second in their allocation
+    #[cfg(feature = "memory")]
+    Ple,
+    /// Whether the first pointer doesn't come before the second in their allocation
+    #[cfg(feature = "memory")]
+    Pge,
+    /// Turns a pointer into an `int`
+    #[cfg(feature = "memory")]
+    Ptr2int,
+    /// Turns an `int` back into a pointer
+    #[cfg(feature = "memory")]
+    Int2ptr,
+    /// Reads the global variable named in `funcs`
+    #[cfg(feature = "global")]
+    Getglobal,
+    /// Adds two vectors lane by lane
+    #[cfg(feature = "vector")]
+    Vadd,
+    /// Subtracts two vectors lane by lane
+    #[cfg(feature = "vector")]
+    Vsub,
+    /// Multiplies two vectors lane by lane
+    #[cfg(feature = "vector")]
+    Vmul,
+    /// Loads the [`VECTOR_WIDTH`] elements starting at its pointer argument into a vector
+    #[cfg(feature = "vector")]
+    Vload,
+}
+
+impl ValueOps {
+    // Whether swapping the two arguments never changes the result. `fmax`, `fmin`, `maxnum`, and `minnum` aren't, since they can pick differently between NaNs or between `0.0` and `-0.0`
+    const fn is_commutative(self) -> bool {
+        match self {
+            Self::Add | Self::Mul | Self::Eq | Self::And | Self::Or | Self::Smax | Self::Smin => {
+                true
+            }
+            #[cfg(feature = "float")]
+            Self::Fadd | Self::Fmul | Self::Feq | Self::Fne => true,
+            #[cfg(feature = "char")]
+            Self::Ceq => true,
+            #[cfg(feature = "memory")]
+            Self::Peq => true,
+            #[cfg(feature = "vector")]
+            Self::Vadd | Self::Vmul => true,
+            _ => false,
+        }
+    }
 }
 
 impl Display for ValueOps {
@@ -521,6 +907,20 @@ impl Display for ValueOps {
             Self::Smin => write!(f, "smin"),
             Self::Shl => write!(f, "shl"),
             Self::Shr => write!(f, "shr"),
+            Self::Popcnt => write!(f, "popcnt"),
+            Self::Clz => write!(f, "clz"),
+            Self::Ctz => write!(f, "ctz"),
+            Self::Bswap => write!(f, "bswap"),
+            Self::Rotl => write!(f, "rotl"),
+            Self::Rotr => write!(f, "rotr"),
+            Self::Rem => write!(f, "rem"),
+            Self::Udiv => write!(f, "udiv"),
+            Self::Urem => write!(f, "urem"),
+            Self::Ult => write!(f, "ult"),
+            Self::Ugt => write!(f, "ugt"),
+            Self::Ule => write!(f, "ule"),
+            Self::Uge => write!(f, "uge"),
+            Self::Zext => write!(f, "zext"),
             #[cfg(feature = "ssa")]
             Self::Phi => write!(f, "phi"),
             #[cfg(feature = "float")]
@@ -542,9 +942,55 @@ impl Display for ValueOps {
             #[cfg(feature = "float")]
             Self::Fge => write!(f, "fge"),
             #[cfg(feature = "float")]
+            Self::Fne => write!(f, "fne"),
+            #[cfg(feature = "float")]
             Self::Fmax => write!(f, "fmax"),
             #[cfg(feature = "float")]
             Self::Fmin => write!(f, "fmin"),
+            #[cfg(feature = "float")]
+            Self::Maxnum => write!(f, "maxnum"),
+            #[cfg(feature = "float")]
+            Self::Minnum => write!(f, "minnum"),
+            #[cfg(feature = "float")]
+            Self::Fsqrt => write!(f, "fsqrt"),
+            #[cfg(feature = "float")]
+            Self::Fabs => write!(f, "fabs"),
+            #[cfg(feature = "float")]
+            Self::Fneg => write!(f, "fneg"),
+            #[cfg(feature = "float")]
+            Self::Ceil => write!(f, "ceil"),
+            #[cfg(feature = "float")]
+            Self::Floor => write!(f, "floor"),
+            #[cfg(feature = "float")]
+            Self::Round => write!(f, "round"),
+            #[cfg(feature = "float")]
+            Self::Trunc => write!(f, "trunc"),
+            #[cfg(feature = "float")]
+            Self::Pow => write!(f, "pow"),
+            #[cfg(feature = "float")]
+            Self::Fsin => write!(f, "fsin"),
+            #[cfg(feature = "float")]
+            Self::Fcos => write!(f, "fcos"),
+            #[cfg(feature = "float")]
+            Self::Fexp => write!(f, "fexp"),
+            #[cfg(feature = "float")]
+            Self::Flog => write!(f, "flog"),
+            #[cfg(feature = "float")]
+            Self::Fma => write!(f, "fma"),
+            #[cfg(feature = "float")]
+            Self::I2f => write!(f, "i2f"),
+            #[cfg(feature = "float")]
+            Self::F2i => write!(f, "f2i"),
+            #[cfg(feature = "float")]
+            Self::Float2bits => write!(f, "float2bits"),
+            #[cfg(feature = "float")]
+            Self::Bits2float => write!(f, "bits2float"),
+            #[cfg(feature = "float")]
+            Self::Fcopysign => write!(f, "fcopysign"),
+            #[cfg(feature = "float")]
+            Self::Isnan => write!(f, "isnan"),
+            #[cfg(feature = "float")]
+            Self::Isinf => write!(f, "isinf"),
             #[cfg(feature = "char")]
             Self::Ceq => write!(f, "ceq"),
             #[cfg(feature = "char")]
@@ -565,6 +1011,32 @@ impl Display for ValueOps {
             Self::Load => write!(f, "load"),
             #[cfg(feature = "memory")]
             Self::PtrAdd => write!(f, "ptradd"),
+            #[cfg(feature = "memory")]
+            Self::PtrDiff => write!(f, "ptrdiff"),
+            #[cfg(feature = "memory")]
+            Self::Peq => write!(f, "peq"),
+            #[cfg(feature = "memory")]
+            Self::Plt => write!(f, "plt"),
+            #[cfg(feature = "memory")]
+            Self::Pgt => write!(f, "pgt"),
+            #[cfg(feature = "memory")]
+            Self::Ple => write!(f, "ple"),
+            #[cfg(feature = "memory")]
+            Self::Pge => write!(f, "pge"),
+            #[cfg(feature = "memory")]
+            Self::Ptr2int => write!(f, "ptr2int"),
+            #[cfg(feature = "memory")]
+            Self::Int2ptr => write!(f, "int2ptr"),
+            #[cfg(feature = "global")]
+            Self::Getglobal => write!(f, "getglobal"),
+            #[cfg(feature = "vector")]
+            Self::Vadd => write!(f, "vadd"),
+            #[cfg(feature = "vector")]
+            Self::Vsub => write!(f, "vsub"),
+            #[cfg(feature = "vector")]
+            Self::Vmul => write!(f, "vmul"),
+            #[cfg(feature = "vector")]
+            Self::Vload => write!(f, "vload"),
         }
     }
 }
@@ -587,8 +1059,16 @@ pub enum Type {
     #[cfg(feature = "memory")]
     #[serde(rename = "ptr")]
     Pointer(Box<Self>),
+    /// [`VECTOR_WIDTH`] lanes of `int` or `float`, like `vec<int>`
+    #[cfg(feature = "vector")]
+    #[serde(rename = "vec")]
+    Vector(Box<Self>),
 }
 
+/// The number of lanes in a [`Type::Vector`]
+#[cfg(feature = "vector")]
+pub const VECTOR_WIDTH: usize = 4;
+
 impl Display for Type {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -600,10 +1080,46 @@ impl Display for Type {
             Self::Char => write!(f, "char"),
             #[cfg(feature = "memory")]
             Self::Pointer(tpe) => write!(f, "ptr<{tpe}>"),
+            #[cfg(feature = "vector")]
+            Self::Vector(tpe) => write!(f, "vec<{tpe}>"),
         }
     }
 }
 
+/// The number of bytes that a value of type `ty` takes up in memory, following how the compilers lay out Bril values.
+///
+/// A `bool` is one byte and a `char` is a four byte Unicode scalar value. A vector is as big as its lanes put together. Pointers are the size of a native pointer whatever they point to, which is 8 bytes on 64-bit targets.
+///
+/// ```
+/// use bril_rs::{size_of_type, Type};
+/// assert_eq!(size_of_type(&Type::Int), 8);
+/// assert_eq!(size_of_type(&Type::Bool), 1);
+/// let nested = Type::Pointer(Box::new(Type::Pointer(Box::new(Type::Bool))));
+/// assert_eq!(size_of_type(&nested), std::mem::size_of::<usize>());
+/// assert_eq!(size_of_type(&Type::Vector(Box::new(Type::Float))), 32);
+/// ```
+#[must_use]
+pub const fn size_of_type(ty: &Type) -> usize {
+    match ty {
+        Type::Int => 8,
+        Type::Bool => 1,
+        #[cfg(feature = "float")]
+        Type::Float => 8,
+        #[cfg(feature = "char")]
+        Type::Char => 4,
+        #[cfg(feature = "memory")]
+        Type::Pointer(_) => std::mem::size_of::<usize>(),
+        #[cfg(feature = "vector")]
+        Type::Vector(ty) => VECTOR_WIDTH * size_of_type(ty),
+    }
+}
+
+/// The alignment in bytes of a value of type `ty` in memory. Every Bril type is aligned to its size, so arrays of them need no padding.
+#[must_use]
+pub const fn align_of_type(ty: &Type) -> usize {
+    size_of_type(ty)
+}
+
 /// A JSON number/value
 #[cfg_attr(not(feature = "float"), derive(Eq, Hash))]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -619,6 +1135,9 @@ pub enum Literal {
     /// UTF-16 Characters
     #[cfg(feature = "char")]
     Char(char),
+    /// The null pointer, which is written as `null` and can have any pointer type
+    #[cfg(feature = "memory")]
+    Null,
 }
 
 impl Display for Literal {
@@ -626,10 +1145,13 @@ impl Display for Literal {
         match self {
             Self::Int(i) => write!(f, "{i}"),
             Self::Bool(b) => write!(f, "{b}"),
+            // `Debug` keeps the decimal point of whole numbers like `2.0`, so they are read back as a `float`, and uses an exponent for very large and small ones
             #[cfg(feature = "float")]
-            Self::Float(x) => write!(f, "{x}"),
+            Self::Float(x) => write!(f, "{x:?}"),
             #[cfg(feature = "char")]
             Self::Char(c) => write!(f, "\'{}\'", escape_char(*c)),
+            #[cfg(feature = "memory")]
+            Self::Null => write!(f, "null"),
         }
     }
 }
@@ -651,6 +1173,8 @@ fn escape_char(c: char) -> String {
 
 impl Literal {
     /// A helper function to get the type of literal values
+    /// # Panics
+    /// For [`Literal::Null`], which can be any pointer type. Use [`Literal::fits_type`] to check it against a type instead
     #[must_use]
     pub const fn get_type(&self) -> Type {
         match self {
@@ -660,6 +1184,20 @@ impl Literal {
             Self::Float(_) => Type::Float,
             #[cfg(feature = "char")]
             Self::Char(_) => Type::Char,
+            #[cfg(feature = "memory")]
+            Self::Null => panic!("null does not have a single type"),
+        }
+    }
+
+    /// Whether this literal can be the value of a constant of type `ty`. Integers can also be floats, and `null` can be any pointer
+    #[must_use]
+    pub fn fits_type(&self, ty: &Type) -> bool {
+        match (self, ty) {
+            #[cfg(feature = "float")]
+            (Self::Int(_), Type::Float) => true,
+            #[cfg(feature = "memory")]
+            (Self::Null, ty) => matches!(ty, Type::Pointer(_)),
+            _ => &self.get_type() == ty,
         }
     }
 }
@@ -0,0 +1,108 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::AbstractProgram;
+
+/// A `.brilrepro` file: everything needed to run a program again with one of the Rust tools and check that it does the same thing
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Repro {
+    /// The program that was run
+    pub program: AbstractProgram,
+    /// The name of the tool that ran it, like `brilirs` or `brillvm`
+    pub tool: String,
+    /// The command line options given to the tool, not including where the program came from or the arguments to main
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    /// The arguments to main
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// What was on stdin, for tools that support the input extension
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdin: Option<String>,
+    /// The seed for the random number generator, for tools that support the rand extension
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// What happened when the program was recorded
+    pub expected: Outcome,
+}
+
+/// What a run of a program looks like from the outside
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// Everything the tool printed to stdout
+    pub stdout: String,
+    /// The exit code of the tool. Tools that can be killed by a signal use -1 in that case
+    pub exit_code: i32,
+}
+
+/// The ways that a replayed run differed from a [`Repro`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The expected and actual exit codes, if they were different
+    pub exit_code: Option<(i32, i32)>,
+    /// The first line of stdout that was different along with the expected and actual line. A missing line is [`None`]
+    pub stdout: Option<(usize, Option<String>, Option<String>)>,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some((expected, actual)) = self.exit_code {
+            writeln!(f, "exit code: expected {expected}, got {actual}")?;
+        }
+        if let Some((line, expected, actual)) = &self.stdout {
+            let show = |l: &Option<String>| {
+                l.as_ref()
+                    .map_or_else(|| "nothing".to_string(), |l| format!("{l:?}"))
+            };
+            writeln!(
+                f,
+                "stdout line {line}: expected {}, got {}",
+                show(expected),
+                show(actual)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Repro {
+    /// Reads a [`Repro`] from ```input``` in JSON format
+    /// # Errors
+    /// If ```input``` can't be read or is not a well-formed `.brilrepro` file
+    pub fn from_read<R: Read>(input: R) -> Result<Self, serde_json::Error> {
+        serde_json::from_reader(input)
+    }
+
+    /// Writes this [`Repro`] to ```out``` in JSON format
+    /// # Errors
+    /// If writing to ```out``` fails
+    pub fn write<W: Write>(&self, mut out: W) -> Result<(), serde_json::Error> {
+        serde_json::to_writer_pretty(&mut out, self)?;
+        writeln!(out).map_err(serde_json::Error::io)
+    }
+
+    /// Compares the ```actual``` outcome of replaying this [`Repro`] against the one that was recorded
+    /// # Errors
+    /// A [`Mismatch`] describing the differences if there are any
+    pub fn check(&self, actual: &Outcome) -> Result<(), Mismatch> {
+        let exit_code = (self.expected.exit_code != actual.exit_code)
+            .then_some((self.expected.exit_code, actual.exit_code));
+        let stdout = if self.expected.stdout == actual.stdout {
+            None
+        } else {
+            let mut expected = self.expected.stdout.split_inclusive('\n');
+            let mut actual = actual.stdout.split_inclusive('\n');
+            (1..).find_map(|line| match (expected.next(), actual.next()) {
+                (e, a) if e == a => None,
+                (e, a) => Some((line, e.map(ToString::to_string), a.map(ToString::to_string))),
+            })
+        };
+        if exit_code.is_none() && stdout.is_none() {
+            Ok(())
+        } else {
+            Err(Mismatch { exit_code, stdout })
+        }
+    }
+}
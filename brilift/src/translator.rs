@@ -357,7 +357,10 @@ impl CompileEnv<'_> {
                 };
                 builder.ins().iconst(ir::types::I32, val as i64)
             }
-            bril::Type::Pointer(_) => panic!("pointer literals not allowed"),
+            bril::Type::Pointer(_) => match lit {
+                bril::Literal::Null => builder.ins().iconst(self.pointer_type, 0),
+                _ => panic!("incorrect literal type for pointer"),
+            },
         }
     }
 
@@ -368,6 +371,9 @@ impl CompileEnv<'_> {
             bril::ValueOps::Sub => ir::Opcode::Isub,
             bril::ValueOps::Mul => ir::Opcode::Imul,
             bril::ValueOps::Div => ir::Opcode::Sdiv,
+            bril::ValueOps::Rem => ir::Opcode::Srem,
+            bril::ValueOps::Udiv => ir::Opcode::Udiv,
+            bril::ValueOps::Urem => ir::Opcode::Urem,
             bril::ValueOps::And => ir::Opcode::Band,
             bril::ValueOps::Or => ir::Opcode::Bor,
             bril::ValueOps::Fadd => ir::Opcode::Fadd,
@@ -376,6 +382,8 @@ impl CompileEnv<'_> {
             bril::ValueOps::Fdiv => ir::Opcode::Fdiv,
             bril::ValueOps::Shl => ir::Opcode::Ishl,
             bril::ValueOps::Shr => ir::Opcode::Ushr,
+            bril::ValueOps::Rotl => ir::Opcode::Rotl,
+            bril::ValueOps::Rotr => ir::Opcode::Rotr,
             _ => panic!("not a translatable opcode: {op}"),
         }
     }
@@ -388,6 +396,10 @@ impl CompileEnv<'_> {
             bril::ValueOps::Eq => IntCC::Equal,
             bril::ValueOps::Ge => IntCC::SignedGreaterThanOrEqual,
             bril::ValueOps::Gt => IntCC::SignedGreaterThan,
+            bril::ValueOps::Ult => IntCC::UnsignedLessThan,
+            bril::ValueOps::Ule => IntCC::UnsignedLessThanOrEqual,
+            bril::ValueOps::Uge => IntCC::UnsignedGreaterThanOrEqual,
+            bril::ValueOps::Ugt => IntCC::UnsignedGreaterThan,
             _ => panic!("not a comparison opcode: {op}"),
         }
     }
@@ -400,6 +412,7 @@ impl CompileEnv<'_> {
             bril::ValueOps::Feq => FloatCC::Equal,
             bril::ValueOps::Fge => FloatCC::GreaterThanOrEqual,
             bril::ValueOps::Fgt => FloatCC::GreaterThan,
+            bril::ValueOps::Fne => FloatCC::OrderedNotEqual,
             _ => panic!("not a comparison opcode: {op}"),
         }
     }
@@ -451,6 +464,9 @@ impl CompileEnv<'_> {
                     }
                 }
                 bril::EffectOps::Nop => {}
+                bril::EffectOps::Abort => {
+                    builder.ins().trap(ir::TrapCode::UnreachableCodeReached);
+                }
                 bril::EffectOps::Store => {
                     let ptr_arg = builder.use_var(self.vars[&args[0]]);
                     let val_arg = builder.use_var(self.vars[&args[1]]);
@@ -460,7 +476,12 @@ impl CompileEnv<'_> {
                     let ptr_arg = builder.use_var(self.vars[&args[0]]);
                     builder.ins().call(self.rt_refs[RTFunc::Free], &[ptr_arg]);
                 }
-                bril::EffectOps::Speculate | bril::EffectOps::Commit | bril::EffectOps::Guard => {
+                bril::EffectOps::MemCopy
+                | bril::EffectOps::Fill
+                | bril::EffectOps::PrintStr
+                | bril::EffectOps::Speculate
+                | bril::EffectOps::Commit
+                | bril::EffectOps::Guard => {
                     unimplemented!()
                 }
             },
@@ -477,10 +498,15 @@ impl CompileEnv<'_> {
                 | bril::ValueOps::Sub
                 | bril::ValueOps::Mul
                 | bril::ValueOps::Div
+                | bril::ValueOps::Rem
+                | bril::ValueOps::Udiv
+                | bril::ValueOps::Urem
                 | bril::ValueOps::And
                 | bril::ValueOps::Or
                 | bril::ValueOps::Shl
-                | bril::ValueOps::Shr => {
+                | bril::ValueOps::Shr
+                | bril::ValueOps::Rotl
+                | bril::ValueOps::Rotr => {
                     self.gen_binary(builder, args, dest, op_type, Self::translate_op(*op));
                 }
                 bril::ValueOps::Select => {
@@ -504,13 +530,42 @@ impl CompileEnv<'_> {
                     let res = builder.ins().select(cmp, a, b);
                     builder.def_var(self.vars[dest], res);
                 }
+                bril::ValueOps::Popcnt => {
+                    let a = builder.use_var(self.vars[&args[0]]);
+                    let res = builder.ins().popcnt(a);
+                    builder.def_var(self.vars[dest], res);
+                }
+                bril::ValueOps::Clz => {
+                    let a = builder.use_var(self.vars[&args[0]]);
+                    let res = builder.ins().clz(a);
+                    builder.def_var(self.vars[dest], res);
+                }
+                bril::ValueOps::Ctz => {
+                    let a = builder.use_var(self.vars[&args[0]]);
+                    let res = builder.ins().ctz(a);
+                    builder.def_var(self.vars[dest], res);
+                }
+                bril::ValueOps::Bswap => {
+                    let a = builder.use_var(self.vars[&args[0]]);
+                    let res = builder.ins().bswap(a);
+                    builder.def_var(self.vars[dest], res);
+                }
                 bril::ValueOps::Lt
                 | bril::ValueOps::Le
                 | bril::ValueOps::Eq
                 | bril::ValueOps::Ge
-                | bril::ValueOps::Gt => {
+                | bril::ValueOps::Gt
+                | bril::ValueOps::Ult
+                | bril::ValueOps::Ule
+                | bril::ValueOps::Uge
+                | bril::ValueOps::Ugt => {
                     self.gen_icmp(builder, args, dest, Self::translate_intcc(*op))
                 }
+                bril::ValueOps::Zext => {
+                    let arg = builder.use_var(self.vars[&args[0]]);
+                    let res = builder.ins().uextend(ir::types::I64, arg);
+                    builder.def_var(self.vars[dest], res);
+                }
                 bril::ValueOps::Not => {
                     let arg = builder.use_var(self.vars[&args[0]]);
 
@@ -534,6 +589,11 @@ impl CompileEnv<'_> {
                     let arg = builder.use_var(self.vars[&args[0]]);
                     builder.def_var(self.vars[dest], arg);
                 }
+                // Pointers are already represented as 64-bit integers
+                bril::ValueOps::Ptr2int | bril::ValueOps::Int2ptr => {
+                    let arg = builder.use_var(self.vars[&args[0]]);
+                    builder.def_var(self.vars[dest], arg);
+                }
 
                 // Floating point extension.
                 bril::ValueOps::Fadd
@@ -546,7 +606,8 @@ impl CompileEnv<'_> {
                 | bril::ValueOps::Fle
                 | bril::ValueOps::Feq
                 | bril::ValueOps::Fge
-                | bril::ValueOps::Fgt => {
+                | bril::ValueOps::Fgt
+                | bril::ValueOps::Fne => {
                     self.gen_fcmp(builder, args, dest, Self::translate_floatcc(*op))
                 }
 
@@ -566,6 +627,58 @@ impl CompileEnv<'_> {
                     builder.def_var(self.vars[dest], res);
                 }
 
+                // Cranelift's fmax and fmin return NaN if either argument is NaN, so pick the other argument in that case
+                bril::ValueOps::Maxnum | bril::ValueOps::Minnum => {
+                    let a = builder.use_var(self.vars[&args[0]]);
+                    let b = builder.use_var(self.vars[&args[1]]);
+                    let res = if *op == bril::ValueOps::Maxnum {
+                        builder.ins().fmax(a, b)
+                    } else {
+                        builder.ins().fmin(a, b)
+                    };
+                    let a_nan = builder.ins().fcmp(FloatCC::Unordered, a, a);
+                    let b_nan = builder.ins().fcmp(FloatCC::Unordered, b, b);
+                    let res = builder.ins().select(b_nan, a, res);
+                    let res = builder.ins().select(a_nan, b, res);
+                    builder.def_var(self.vars[dest], res);
+                }
+
+                bril::ValueOps::Fcopysign => {
+                    let a = builder.use_var(self.vars[&args[0]]);
+                    let b = builder.use_var(self.vars[&args[1]]);
+                    let res = builder.ins().fcopysign(a, b);
+                    builder.def_var(self.vars[dest], res);
+                }
+
+                // NaN is the only value that is unordered with itself
+                bril::ValueOps::Isnan => {
+                    let a = builder.use_var(self.vars[&args[0]]);
+                    let res = builder.ins().fcmp(FloatCC::Unordered, a, a);
+                    builder.def_var(self.vars[dest], res);
+                }
+                bril::ValueOps::Isinf => {
+                    let a = builder.use_var(self.vars[&args[0]]);
+                    let abs = builder.ins().fabs(a);
+                    let inf = builder.ins().f64const(f64::INFINITY);
+                    let res = builder.ins().fcmp(FloatCC::Equal, abs, inf);
+                    builder.def_var(self.vars[dest], res);
+                }
+
+                bril::ValueOps::Float2bits => {
+                    let a = builder.use_var(self.vars[&args[0]]);
+                    let res = builder
+                        .ins()
+                        .bitcast(ir::types::I64, ir::MemFlags::new(), a);
+                    builder.def_var(self.vars[dest], res);
+                }
+                bril::ValueOps::Bits2float => {
+                    let a = builder.use_var(self.vars[&args[0]]);
+                    let res = builder
+                        .ins()
+                        .bitcast(ir::types::F64, ir::MemFlags::new(), a);
+                    builder.def_var(self.vars[dest], res);
+                }
+
                 // Memory extension.
                 bril::ValueOps::Alloc => {
                     // The number of elements to allocate comes from the program.
@@ -604,7 +717,28 @@ impl CompileEnv<'_> {
                 | bril::ValueOps::Cle
                 | bril::ValueOps::Cge
                 | bril::ValueOps::Char2int
-                | bril::ValueOps::Int2char => unimplemented!(),
+                | bril::ValueOps::Int2char
+                | bril::ValueOps::Fsqrt
+                | bril::ValueOps::Fabs
+                | bril::ValueOps::Fneg
+                | bril::ValueOps::Ceil
+                | bril::ValueOps::Floor
+                | bril::ValueOps::Round
+                | bril::ValueOps::Trunc
+                | bril::ValueOps::Pow
+                | bril::ValueOps::Fsin
+                | bril::ValueOps::Fcos
+                | bril::ValueOps::Fexp
+                | bril::ValueOps::Flog
+                | bril::ValueOps::Fma
+                | bril::ValueOps::I2f
+                | bril::ValueOps::F2i
+                | bril::ValueOps::PtrDiff
+                | bril::ValueOps::Peq
+                | bril::ValueOps::Plt
+                | bril::ValueOps::Pgt
+                | bril::ValueOps::Ple
+                | bril::ValueOps::Pge => unimplemented!(),
             },
         }
     }
@@ -621,7 +755,10 @@ impl CompileEnv<'_> {
         {
             matches!(
                 op,
-                bril::EffectOps::Branch | bril::EffectOps::Jump | bril::EffectOps::Return
+                bril::EffectOps::Branch
+                    | bril::EffectOps::Jump
+                    | bril::EffectOps::Return
+                    | bril::EffectOps::Abort
             )
         } else {
             false
@@ -1078,6 +1215,7 @@ impl Translator<JITModule> {
                 bril::Literal::Bool(b) => b as *const bool as *const u8,
                 bril::Literal::Float(f) => f as *const f64 as *const u8,
                 bril::Literal::Char(c) => c as *const char as *const u8,
+                bril::Literal::Null => unimplemented!("pointer arguments to main"),
             })
             .collect()
     }
@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+
+use bril_rs::Program;
+use brilirs::basic_block::BBProgram;
+use brilirs::check::type_check;
+use brilirs::interp::{execute_main_with_limit, Interpreter};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// The benchmark suites that brilirs supports, the same as in the Makefile
+const SUITES: [&str; 4] = ["core", "float", "mem", "mixed"];
+
+// A small program that is run over and over, like a candidate in a superoptimizer
+const SMALL: &str = "core/euclid.bril";
+
+const RUNS: usize = 10_000;
+
+fn benchmarks_dir() -> PathBuf {
+  Path::new(env!("CARGO_MANIFEST_DIR")).join("../benchmarks")
+}
+
+// Returns the program along with the arguments from its `ARGS:` comment, if it has one
+fn load(file: &Path) -> (Program, Vec<String>) {
+  let src = std::fs::read_to_string(file).unwrap();
+  let args = src
+    .lines()
+    .find_map(|l| l.trim().strip_prefix('#')?.trim().strip_prefix("ARGS:"))
+    .map(|a| a.split_whitespace().map(ToString::to_string).collect())
+    .unwrap_or_default();
+  let prog = bril2json::parse_abstract_program_from_read(src.as_bytes(), false, false, None)
+    .try_into()
+    .unwrap();
+  (prog, args)
+}
+
+// Runs a program from scratch the way that the command line does
+fn run_once(prog: &Program, args: &[String], out: &mut Vec<u8>) -> usize {
+  let bbprog: BBProgram = prog.clone().try_into().unwrap();
+  type_check(&bbprog).unwrap();
+  execute_main_with_limit(&bbprog, out, args, None).unwrap()
+}
+
+// Reusing an interpreter has to give exactly the same results as starting from scratch, including after a run has left things on the stack and heap
+fn check_same_results() {
+  let mut files: Vec<PathBuf> = SUITES
+    .iter()
+    .flat_map(|suite| std::fs::read_dir(benchmarks_dir().join(suite)).unwrap())
+    .map(|entry| entry.unwrap().path())
+    .filter(|path| path.extension().is_some_and(|ext| ext == "bril"))
+    .collect();
+  files.sort();
+  for file in files {
+    let (prog, args) = load(&file);
+    let mut expected = Vec::new();
+    let count = run_once(&prog, &args, &mut expected);
+    let mut interp = Interpreter::load(&prog).unwrap();
+    for _ in 0..2 {
+      let mut out = Vec::new();
+      let stats = interp.run(&args, &mut out).unwrap();
+      assert_eq!(out, expected, "{}", file.display());
+      assert_eq!(stats.total_dyn_inst, count, "{}", file.display());
+    }
+  }
+}
+
+fn repeated_runs(c: &mut Criterion) {
+  check_same_results();
+
+  let (prog, args) = load(&benchmarks_dir().join(SMALL));
+  let mut group = c.benchmark_group("repeated_runs");
+  group.sample_size(10);
+  group.bench_function("one_shot", |b| {
+    b.iter(|| {
+      for _ in 0..RUNS {
+        let mut out = Vec::new();
+        black_box(run_once(&prog, &args, &mut out));
+      }
+    });
+  });
+  group.bench_function("interpreter", |b| {
+    let mut interp = Interpreter::load(&prog).unwrap();
+    b.iter(|| {
+      for _ in 0..RUNS {
+        let mut out = Vec::new();
+        black_box(interp.run(&args, &mut out).unwrap());
+      }
+    });
+  });
+  group.finish();
+}
+
+criterion_group!(benches, repeated_runs);
+criterion_main!(benches);
@@ -1,4 +1,4 @@
-use bril_rs::{Function, Instruction, Position, Program};
+use bril_rs::{EffectOps, Function, Instruction, Position, Program};
 use fxhash::FxHashMap;
 
 use crate::error::{InterpError, PositionalInterpError};
@@ -147,6 +147,19 @@ impl NumifiedInstruction {
           })
           .collect::<Result<Vec<usize>, PositionalInterpError>>()?,
       },
+      // The text of `printstr` is in its `funcs`, but it isn't the name of a function
+      Instruction::Effect {
+        args,
+        op: EffectOps::PrintStr,
+        ..
+      } => Self {
+        dest: None,
+        args: args
+          .iter()
+          .map(|v| get_num_from_map(v, num_of_vars, num_var_map))
+          .collect(),
+        funcs: Vec::new(),
+      },
       Instruction::Effect {
         args, funcs, pos, ..
       } => Self {
@@ -2,7 +2,7 @@ use crate::{
   basic_block::{BBFunction, BBProgram, NumifiedInstruction},
   error::{InterpError, PositionalInterpError},
 };
-use bril_rs::{ConstOps, EffectOps, Instruction, Type, ValueOps};
+use bril_rs::{ConstOps, EffectOps, Instruction, Literal, Type, ValueOps};
 
 use fxhash::FxHashMap;
 
@@ -82,6 +82,19 @@ fn type_check_instruction<'a>(
   env: &mut FxHashMap<&'a str, &'a Type>,
 ) -> Result<(), InterpError> {
   match instr {
+    // `null` can be any pointer, so it has no type of its own to check against
+    Instruction::Constant {
+      op: ConstOps::Const,
+      dest,
+      const_type,
+      value: value @ Literal::Null,
+      pos: _,
+    } => {
+      if !value.fits_type(const_type) {
+        return Err(InterpError::ExpectedPointerType(const_type.clone()));
+      }
+      update_env(env, dest, const_type)
+    }
     Instruction::Constant {
       op: ConstOps::Const,
       dest,
@@ -100,10 +113,15 @@ fn type_check_instruction<'a>(
         | ValueOps::Sub
         | ValueOps::Mul
         | ValueOps::Div
+        | ValueOps::Rem
+        | ValueOps::Udiv
+        | ValueOps::Urem
         | ValueOps::Smax
         | ValueOps::Smin
         | ValueOps::Shl
-        | ValueOps::Shr,
+        | ValueOps::Shr
+        | ValueOps::Rotl
+        | ValueOps::Rotr,
       dest,
       op_type,
       args,
@@ -120,7 +138,32 @@ fn type_check_instruction<'a>(
       update_env(env, dest, op_type)
     }
     Instruction::Value {
-      op: ValueOps::Eq | ValueOps::Lt | ValueOps::Gt | ValueOps::Le | ValueOps::Ge,
+      op: ValueOps::Popcnt | ValueOps::Clz | ValueOps::Ctz | ValueOps::Bswap,
+      dest,
+      op_type,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(1, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      check_asmt_type(&Type::Int, get_type(env, 0, args)?)?;
+      check_asmt_type(&Type::Int, op_type)?;
+      update_env(env, dest, op_type)
+    }
+    Instruction::Value {
+      op:
+        ValueOps::Eq
+        | ValueOps::Lt
+        | ValueOps::Gt
+        | ValueOps::Le
+        | ValueOps::Ge
+        | ValueOps::Ult
+        | ValueOps::Ugt
+        | ValueOps::Ule
+        | ValueOps::Uge,
       dest,
       op_type,
       args,
@@ -136,6 +179,22 @@ fn type_check_instruction<'a>(
       check_asmt_type(&Type::Bool, op_type)?;
       update_env(env, dest, op_type)
     }
+    Instruction::Value {
+      op: ValueOps::Zext,
+      dest,
+      op_type,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(1, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      check_asmt_type(&Type::Bool, get_type(env, 0, args)?)?;
+      check_asmt_type(&Type::Int, op_type)?;
+      update_env(env, dest, op_type)
+    }
     Instruction::Value {
       op: ValueOps::Not,
       dest,
@@ -208,7 +267,11 @@ fn type_check_instruction<'a>(
         | ValueOps::Fmul
         | ValueOps::Fdiv
         | ValueOps::Fmax
-        | ValueOps::Fmin,
+        | ValueOps::Fmin
+        | ValueOps::Maxnum
+        | ValueOps::Minnum
+        | ValueOps::Pow
+        | ValueOps::Fcopysign,
       dest,
       op_type,
       args,
@@ -225,7 +288,101 @@ fn type_check_instruction<'a>(
       update_env(env, dest, op_type)
     }
     Instruction::Value {
-      op: ValueOps::Feq | ValueOps::Flt | ValueOps::Fgt | ValueOps::Fle | ValueOps::Fge,
+      op: ValueOps::Fma,
+      dest,
+      op_type,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(3, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      check_asmt_type(&Type::Float, get_type(env, 0, args)?)?;
+      check_asmt_type(&Type::Float, get_type(env, 1, args)?)?;
+      check_asmt_type(&Type::Float, get_type(env, 2, args)?)?;
+      check_asmt_type(&Type::Float, op_type)?;
+      update_env(env, dest, op_type)
+    }
+    Instruction::Value {
+      op: ValueOps::I2f | ValueOps::Bits2float,
+      dest,
+      op_type,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(1, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      check_asmt_type(&Type::Int, get_type(env, 0, args)?)?;
+      check_asmt_type(&Type::Float, op_type)?;
+      update_env(env, dest, op_type)
+    }
+    Instruction::Value {
+      op: ValueOps::F2i | ValueOps::Float2bits,
+      dest,
+      op_type,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(1, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      check_asmt_type(&Type::Float, get_type(env, 0, args)?)?;
+      check_asmt_type(&Type::Int, op_type)?;
+      update_env(env, dest, op_type)
+    }
+    Instruction::Value {
+      op:
+        ValueOps::Fsqrt
+        | ValueOps::Fabs
+        | ValueOps::Fneg
+        | ValueOps::Ceil
+        | ValueOps::Floor
+        | ValueOps::Round
+        | ValueOps::Trunc
+        | ValueOps::Fsin
+        | ValueOps::Fcos
+        | ValueOps::Fexp
+        | ValueOps::Flog,
+      dest,
+      op_type,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(1, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      check_asmt_type(&Type::Float, get_type(env, 0, args)?)?;
+      check_asmt_type(&Type::Float, op_type)?;
+      update_env(env, dest, op_type)
+    }
+    Instruction::Value {
+      op: ValueOps::Isnan | ValueOps::Isinf,
+      dest,
+      op_type,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(1, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      check_asmt_type(&Type::Float, get_type(env, 0, args)?)?;
+      check_asmt_type(&Type::Bool, op_type)?;
+      update_env(env, dest, op_type)
+    }
+    Instruction::Value {
+      op:
+        ValueOps::Feq | ValueOps::Flt | ValueOps::Fgt | ValueOps::Fle | ValueOps::Fge | ValueOps::Fne,
       dest,
       op_type,
       args,
@@ -290,6 +447,22 @@ fn type_check_instruction<'a>(
       check_asmt_type(&Type::Char, op_type)?;
       update_env(env, dest, op_type)
     }
+    Instruction::Value {
+      op: ValueOps::Ptr2int,
+      args,
+      dest,
+      funcs,
+      labels,
+      pos: _,
+      op_type,
+    } => {
+      check_num_args(1, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      get_ptr_type(get_type(env, 0, args)?)?;
+      check_asmt_type(&Type::Int, op_type)?;
+      update_env(env, dest, op_type)
+    }
     Instruction::Value {
       op: ValueOps::Call,
       dest,
@@ -344,7 +517,7 @@ fn type_check_instruction<'a>(
       update_env(env, dest, op_type)
     }
     Instruction::Value {
-      op: ValueOps::Alloc,
+      op: ValueOps::Alloc | ValueOps::Int2ptr,
       dest,
       op_type,
       args,
@@ -393,6 +566,42 @@ fn type_check_instruction<'a>(
       check_asmt_type(ty0, op_type)?;
       update_env(env, dest, op_type)
     }
+    Instruction::Value {
+      op: ValueOps::PtrDiff,
+      dest,
+      op_type,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(2, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      let ty0 = get_type(env, 0, args)?;
+      get_ptr_type(ty0)?;
+      check_asmt_type(ty0, get_type(env, 1, args)?)?;
+      check_asmt_type(&Type::Int, op_type)?;
+      update_env(env, dest, op_type)
+    }
+    Instruction::Value {
+      op: ValueOps::Peq | ValueOps::Plt | ValueOps::Pgt | ValueOps::Ple | ValueOps::Pge,
+      dest,
+      op_type,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(2, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      let ty0 = get_type(env, 0, args)?;
+      get_ptr_type(ty0)?;
+      check_asmt_type(ty0, get_type(env, 1, args)?)?;
+      check_asmt_type(&Type::Bool, op_type)?;
+      update_env(env, dest, op_type)
+    }
     Instruction::Effect {
       op: EffectOps::Jump,
       args,
@@ -457,7 +666,7 @@ fn type_check_instruction<'a>(
       })
     }
     Instruction::Effect {
-      op: EffectOps::Nop,
+      op: EffectOps::Nop | EffectOps::Abort,
       args,
       funcs,
       labels,
@@ -513,6 +722,49 @@ fn type_check_instruction<'a>(
       let ty1 = get_type(env, 1, args)?;
       check_asmt_type(get_ptr_type(ty0)?, ty1)
     }
+    Instruction::Effect {
+      op: EffectOps::MemCopy,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(3, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      let ty0 = get_type(env, 0, args)?;
+      get_ptr_type(ty0)?;
+      check_asmt_type(ty0, get_type(env, 1, args)?)?;
+      check_asmt_type(&Type::Int, get_type(env, 2, args)?)?;
+      Ok(())
+    }
+    Instruction::Effect {
+      op: EffectOps::Fill,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(3, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      let ty0 = get_type(env, 0, args)?;
+      check_asmt_type(get_ptr_type(ty0)?, get_type(env, 1, args)?)?;
+      check_asmt_type(&Type::Int, get_type(env, 2, args)?)?;
+      Ok(())
+    }
+    Instruction::Effect {
+      op: EffectOps::PrintStr,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(0, args)?;
+      check_num_funcs(1, funcs)?;
+      check_num_labels(0, labels)?;
+      Ok(())
+    }
     Instruction::Effect {
       op: EffectOps::Free,
       args,
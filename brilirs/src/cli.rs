@@ -3,6 +3,7 @@ use clap::Parser;
 #[derive(Parser)]
 #[command(about, version, author)] // keeps the cli synced with Cargo.toml
 #[command(allow_hyphen_values(true))]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Cli {
   /// Flag to output the total number of dynamic instructions
   #[arg(short, long, action)]
@@ -20,7 +21,34 @@ pub struct Cli {
   #[arg(short, long, action)]
   pub text: bool,
 
+  /// Run as a server that reads newline-delimited JSON requests from stdin and writes one JSON response per line to stdout
+  #[arg(long, action)]
+  pub server: bool,
+
+  /// After running, write the program, its arguments and its output to this file so that the run can be replayed
+  #[arg(long, action, value_name = "FILE")]
+  pub record_repro: Option<String>,
+
+  /// Run a file written by --record-repro and report whether the output still matches
+  #[arg(long, action, value_name = "FILE")]
+  pub replay: Option<String>,
+
   /// Arguments for the main function
   #[arg(action)]
   pub args: Vec<String>,
 }
+
+impl Cli {
+  /// The options that change how a program is run, in the form that they are recorded by --record-repro
+  #[must_use]
+  pub fn repro_options(&self) -> Vec<String> {
+    let mut options = Vec::new();
+    if self.profile {
+      options.push("--profile".to_string());
+    }
+    if self.check {
+      options.push("--check".to_string());
+    }
+    options
+  }
+}
@@ -29,10 +29,18 @@ pub enum InterpError {
   NonEmptyRetForFunc(String),
   #[error("cannot allocate `{0}` entries")]
   CannotAllocSize(i64),
+  #[error("cannot copy `{0}` entries")]
+  CannotCopySize(i64),
+  #[error("cannot fill `{0}` entries")]
+  CannotFillSize(i64),
   #[error("Tried to free illegal memory location base: `{0}`, offset: `{1}`. Offset must be 0.")]
   IllegalFree(usize, i64), // (base, offset)
   #[error("Uninitialized heap location `{0}` and/or illegal offset `{1}`")]
   InvalidMemoryAccess(usize, i64), // (base, offset)
+  #[error(
+    "Tried to subtract or order pointers into different allocations with bases `{0}` and `{1}`"
+  )]
+  DifferentAllocations(usize, usize), // (base, base)
   #[error("Expected `{0}` function arguments, found `{1}`")]
   BadNumFuncArgs(usize, usize), // (expected, actual)
   #[error("Expected `{0}` instruction arguments, found `{1}`")]
@@ -57,6 +65,10 @@ pub enum InterpError {
   IoError(#[from] std::io::Error),
   #[error("value ${0} cannot be converted to char")]
   ToCharError(i64),
+  #[error("exceeded the limit of {0} steps")]
+  StepLimitExceeded(usize),
+  #[error("reached an abort instruction")]
+  Aborted,
   #[error("You probably shouldn't see this error, this is here to handle conversions between InterpError and PositionalError")]
   PositionalInterpErrorConversion(#[from] PositionalInterpError),
 }
@@ -1,7 +1,8 @@
 use crate::basic_block::{BBFunction, BBProgram, BasicBlock};
+use crate::check;
 use crate::error::{InterpError, PositionalInterpError};
 use bril2json::escape_control_chars;
-use bril_rs::Instruction;
+use bril_rs::{Instruction, Program};
 
 use fxhash::FxHashMap;
 
@@ -23,6 +24,7 @@ use std::fmt;
 //  |        Call "foo" pointer(frame size 2)
 //  |        |
 // [a, b, c, a, b]
+#[derive(Default)]
 struct Environment {
   // Pointer into env for the start of the current frame
   current_pointer: usize,
@@ -85,6 +87,18 @@ impl Environment {
   pub fn pop_frame(&mut self) {
     (self.current_pointer, self.current_frame_size) = self.stack_pointers.pop().unwrap();
   }
+
+  // Start over with a single frame of `size` while keeping the stack that has already been allocated
+  pub fn reset(&mut self, size: usize) {
+    self.current_pointer = 0;
+    self.current_frame_size = size;
+    self.stack_pointers.clear();
+    // Clearing out old values means that a run can't observe anything left behind by the previous one
+    self.env.fill(Value::default());
+    if size > self.env.len() {
+      self.env.resize(size, Value::default());
+    }
+  }
 }
 
 // todo: This is basically a copy of the heap implement in brili and we could probably do something smarter. This currently isn't that worth it to optimize because most benchmarks do not use the memory extension nor do they run for very long. You (the reader in the future) may be working with bril programs that you would like to speed up that extensively use the bril memory extension. In that case, it would be worth seeing how to implement Heap without a map based memory. Maybe try to re-implement malloc for a large Vec<Value>?
@@ -107,6 +121,12 @@ impl Heap {
     self.memory.is_empty()
   }
 
+  // Frees everything without giving up the capacity of the map. Base numbers start over so pointers are the same as in a fresh heap
+  fn reset(&mut self) {
+    self.memory.clear();
+    self.base_num_counter = 0;
+  }
+
   fn alloc(&mut self, amount: i64) -> Result<Value, InterpError> {
     let amount: usize = amount
       .try_into()
@@ -140,6 +160,27 @@ impl Heap {
     }
   }
 
+  fn copy(&mut self, dest: &Pointer, src: &Pointer, count: i64) -> Result<(), InterpError> {
+    if count < 0 {
+      return Err(InterpError::CannotCopySize(count));
+    }
+    for i in 0..count {
+      let val = *self.read(&src.add(i))?;
+      self.write(&dest.add(i), val)?;
+    }
+    Ok(())
+  }
+
+  fn fill(&mut self, dest: &Pointer, val: Value, count: i64) -> Result<(), InterpError> {
+    if count < 0 {
+      return Err(InterpError::CannotFillSize(count));
+    }
+    for i in 0..count {
+      self.write(&dest.add(i), val)?;
+    }
+    Ok(())
+  }
+
   fn read(&self, key: &Pointer) -> Result<&Value, InterpError> {
     // Will check that key.offset is >=0
     let offset: usize = key
@@ -183,12 +224,41 @@ struct Pointer {
 }
 
 impl Pointer {
+  // No allocation ever gets this base, so every access through it is invalid
+  const NULL: Self = Self {
+    base: usize::MAX,
+    offset: 0,
+  };
+
   const fn add(&self, offset: i64) -> Self {
     Self {
       base: self.base,
       offset: self.offset + offset,
     }
   }
+
+  // Packs the pointer into an int as `(base + 1) * 2^32 + offset`, so that null is 0 and `from_int` can unpack it as long as the offset fits in an `i32`
+  #[allow(clippy::cast_possible_wrap)]
+  const fn to_int(self) -> i64 {
+    ((self.base.wrapping_add(1) as i64) << 32).wrapping_add(self.offset)
+  }
+
+  #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+  const fn from_int(i: i64) -> Self {
+    let base = i.wrapping_add(1 << 31) >> 32;
+    Self {
+      base: (base as usize).wrapping_sub(1),
+      offset: i.wrapping_sub(base << 32),
+    }
+  }
+
+  const fn diff(&self, other: &Self) -> Result<i64, InterpError> {
+    if self.base == other.base {
+      Ok(self.offset - other.offset)
+    } else {
+      Err(InterpError::DifferentAllocations(self.base, other.base))
+    }
+  }
 }
 
 impl fmt::Display for Value {
@@ -230,6 +300,7 @@ impl From<&bril_rs::Literal> for Value {
       bril_rs::Literal::Bool(b) => Self::Bool(*b),
       bril_rs::Literal::Float(f) => Self::Float(*f),
       bril_rs::Literal::Char(c) => Self::Char(*c),
+      bril_rs::Literal::Null => Self::Pointer(Pointer::NULL),
     }
   }
 }
@@ -241,6 +312,7 @@ impl From<bril_rs::Literal> for Value {
       bril_rs::Literal::Bool(b) => Self::Bool(b),
       bril_rs::Literal::Float(f) => Self::Float(f),
       bril_rs::Literal::Char(c) => Self::Char(c),
+      bril_rs::Literal::Null => Self::Pointer(Pointer::NULL),
     }
   }
 }
@@ -324,9 +396,12 @@ fn execute_value_op<T: std::io::Write>(
   last_label: Option<&String>,
 ) -> Result<(), InterpError> {
   use bril_rs::ValueOps::{
-    Add, Alloc, And, Call, Ceq, Cge, Cgt, Char2int, Cle, Clt, Div, Eq, Fadd, Fdiv, Feq, Fge, Fgt,
-    Fle, Flt, Fmax, Fmin, Fmul, Fsub, Ge, Gt, Id, Int2char, Le, Load, Lt, Mul, Not, Or, Phi,
-    PtrAdd, Select, Shl, Shr, Smax, Smin, Sub,
+    Add, Alloc, And, Bits2float, Bswap, Call, Ceil, Ceq, Cge, Cgt, Char2int, Cle, Clt, Clz, Ctz,
+    Div, Eq, F2i, Fabs, Fadd, Fcopysign, Fcos, Fdiv, Feq, Fexp, Fge, Fgt, Fle, Float2bits, Flog,
+    Floor, Flt, Fma, Fmax, Fmin, Fmul, Fne, Fneg, Fsin, Fsqrt, Fsub, Ge, Gt, I2f, Id, Int2char,
+    Int2ptr, Isinf, Isnan, Le, Load, Lt, Maxnum, Minnum, Mul, Not, Or, Peq, Pge, Pgt, Phi, Ple,
+    Plt, Popcnt, Pow, Ptr2int, PtrAdd, PtrDiff, Rem, Rotl, Rotr, Round, Select, Shl, Shr, Smax,
+    Smin, Sub, Trunc, Udiv, Uge, Ugt, Ule, Ult, Urem, Zext,
   };
   match op {
     Add => {
@@ -352,6 +427,34 @@ fn execute_value_op<T: std::io::Write>(
       }
       state.env.set(dest, Value::Int(arg0.wrapping_div(arg1)));
     }
+    Rem => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      let arg1 = get_arg::<i64>(&state.env, 1, args);
+      if arg1 == 0 {
+        return Err(InterpError::DivisionByZero);
+      }
+      state.env.set(dest, Value::Int(arg0.wrapping_rem(arg1)));
+    }
+    Udiv => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      let arg1 = get_arg::<i64>(&state.env, 1, args);
+      if arg1 == 0 {
+        return Err(InterpError::DivisionByZero);
+      }
+      #[allow(clippy::cast_sign_loss, clippy::cast_possible_wrap)]
+      let res = ((arg0 as u64) / (arg1 as u64)) as i64;
+      state.env.set(dest, Value::Int(res));
+    }
+    Urem => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      let arg1 = get_arg::<i64>(&state.env, 1, args);
+      if arg1 == 0 {
+        return Err(InterpError::DivisionByZero);
+      }
+      #[allow(clippy::cast_sign_loss, clippy::cast_possible_wrap)]
+      let res = ((arg0 as u64) % (arg1 as u64)) as i64;
+      state.env.set(dest, Value::Int(res));
+    }
     Eq => {
       let arg0 = get_arg::<i64>(&state.env, 0, args);
       let arg1 = get_arg::<i64>(&state.env, 1, args);
@@ -362,11 +465,28 @@ fn execute_value_op<T: std::io::Write>(
       let arg1 = get_arg::<i64>(&state.env, 1, args);
       state.env.set(dest, Value::Bool(arg0 < arg1));
     }
+    Ult | Ugt | Ule | Uge => {
+      #[allow(clippy::cast_sign_loss)]
+      let arg0 = get_arg::<i64>(&state.env, 0, args) as u64;
+      #[allow(clippy::cast_sign_loss)]
+      let arg1 = get_arg::<i64>(&state.env, 1, args) as u64;
+      let res = match op {
+        Ult => arg0 < arg1,
+        Ugt => arg0 > arg1,
+        Ule => arg0 <= arg1,
+        _ => arg0 >= arg1,
+      };
+      state.env.set(dest, Value::Bool(res));
+    }
     Gt => {
       let arg0 = get_arg::<i64>(&state.env, 0, args);
       let arg1 = get_arg::<i64>(&state.env, 1, args);
       state.env.set(dest, Value::Bool(arg0 > arg1));
     }
+    Zext => {
+      let arg0 = get_arg::<bool>(&state.env, 0, args);
+      state.env.set(dest, Value::Int(i64::from(arg0)));
+    }
     Le => {
       let arg0 = get_arg::<i64>(&state.env, 0, args);
       let arg1 = get_arg::<i64>(&state.env, 1, args);
@@ -426,6 +546,42 @@ fn execute_value_op<T: std::io::Write>(
       let res = arg0 >> arg1;
       state.env.set(dest, Value::Int(res));
     }
+    Rotl => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      let arg1 = get_arg::<i64>(&state.env, 1, args);
+      #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+      let res = arg0.rotate_left(arg1.rem_euclid(64) as u32);
+      state.env.set(dest, Value::Int(res));
+    }
+    Rotr => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      let arg1 = get_arg::<i64>(&state.env, 1, args);
+      #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+      let res = arg0.rotate_right(arg1.rem_euclid(64) as u32);
+      state.env.set(dest, Value::Int(res));
+    }
+    Popcnt => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      state
+        .env
+        .set(dest, Value::Int(i64::from(arg0.count_ones())));
+    }
+    Clz => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      state
+        .env
+        .set(dest, Value::Int(i64::from(arg0.leading_zeros())));
+    }
+    Ctz => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      state
+        .env
+        .set(dest, Value::Int(i64::from(arg0.trailing_zeros())));
+    }
+    Bswap => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      state.env.set(dest, Value::Int(arg0.swap_bytes()));
+    }
     Fadd => {
       let arg0 = get_arg::<f64>(&state.env, 0, args);
       let arg1 = get_arg::<f64>(&state.env, 1, args);
@@ -471,18 +627,129 @@ fn execute_value_op<T: std::io::Write>(
       let arg1 = get_arg::<f64>(&state.env, 1, args);
       state.env.set(dest, Value::Bool(arg0 >= arg1));
     }
+    Fne => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      let arg1 = get_arg::<f64>(&state.env, 1, args);
+      // Unlike `!=`, this is false when either argument is NaN
+      let ordered_ne = arg0
+        .partial_cmp(&arg1)
+        .is_some_and(std::cmp::Ordering::is_ne);
+      state.env.set(dest, Value::Bool(ordered_ne));
+    }
     Fmax => {
       let arg0 = get_arg::<f64>(&state.env, 0, args);
       let arg1 = get_arg::<f64>(&state.env, 1, args);
       let res = if arg0 > arg1 { arg0 } else { arg1 };
       state.env.set(dest, Value::Float(res));
     }
+    Fcopysign => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      let arg1 = get_arg::<f64>(&state.env, 1, args);
+      state.env.set(dest, Value::Float(arg0.copysign(arg1)));
+    }
     Fmin => {
       let arg0 = get_arg::<f64>(&state.env, 0, args);
       let arg1 = get_arg::<f64>(&state.env, 1, args);
       let res = if arg0 < arg1 { arg0 } else { arg1 };
       state.env.set(dest, Value::Float(res));
     }
+    // Rust's `max` and `min` already ignore NaN like IEEE 754-2008
+    Maxnum => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      let arg1 = get_arg::<f64>(&state.env, 1, args);
+      state.env.set(dest, Value::Float(arg0.max(arg1)));
+    }
+    Minnum => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      let arg1 = get_arg::<f64>(&state.env, 1, args);
+      state.env.set(dest, Value::Float(arg0.min(arg1)));
+    }
+    Fsqrt => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Float(arg0.sqrt()));
+    }
+    Fabs => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Float(arg0.abs()));
+    }
+    Fneg => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Float(-arg0));
+    }
+    Isnan => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Bool(arg0.is_nan()));
+    }
+    Isinf => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Bool(arg0.is_infinite()));
+    }
+    Ceil => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Float(arg0.ceil()));
+    }
+    Floor => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Float(arg0.floor()));
+    }
+    Round => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Float(arg0.round()));
+    }
+    Trunc => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Float(arg0.trunc()));
+    }
+    Pow => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      let arg1 = get_arg::<f64>(&state.env, 1, args);
+      state.env.set(dest, Value::Float(arg0.powf(arg1)));
+    }
+    Fma => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      let arg1 = get_arg::<f64>(&state.env, 1, args);
+      let arg2 = get_arg::<f64>(&state.env, 2, args);
+      state.env.set(dest, Value::Float(arg0.mul_add(arg1, arg2)));
+    }
+    I2f => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      #[allow(clippy::cast_precision_loss)]
+      state.env.set(dest, Value::Float(arg0 as f64));
+    }
+    F2i => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      // Casting saturates and turns NaN into 0, which is the behavior that f2i specifies
+      #[allow(clippy::cast_possible_truncation)]
+      state.env.set(dest, Value::Int(arg0 as i64));
+    }
+    Float2bits => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      #[allow(clippy::cast_possible_wrap)]
+      state.env.set(dest, Value::Int(arg0.to_bits() as i64));
+    }
+    Bits2float => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      #[allow(clippy::cast_sign_loss)]
+      state
+        .env
+        .set(dest, Value::Float(f64::from_bits(arg0 as u64)));
+    }
+    Fsin => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Float(arg0.sin()));
+    }
+    Fcos => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Float(arg0.cos()));
+    }
+    Fexp => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Float(arg0.exp()));
+    }
+    Flog => {
+      let arg0 = get_arg::<f64>(&state.env, 0, args);
+      state.env.set(dest, Value::Float(arg0.ln()));
+    }
     Ceq => {
       let arg0 = get_arg::<char>(&state.env, 0, args);
       let arg1 = get_arg::<char>(&state.env, 1, args);
@@ -560,6 +827,45 @@ fn execute_value_op<T: std::io::Write>(
       let res = Value::Pointer(arg0.add(arg1));
       state.env.set(dest, res);
     }
+    PtrDiff => {
+      let arg0 = get_arg::<&Pointer>(&state.env, 0, args);
+      let arg1 = get_arg::<&Pointer>(&state.env, 1, args);
+      let res = arg0.diff(arg1)?;
+      state.env.set(dest, Value::Int(res));
+    }
+    Ptr2int => {
+      let arg0 = get_arg::<&Pointer>(&state.env, 0, args);
+      state.env.set(dest, Value::Int(arg0.to_int()));
+    }
+    Int2ptr => {
+      let arg0 = get_arg::<i64>(&state.env, 0, args);
+      state.env.set(dest, Value::Pointer(Pointer::from_int(arg0)));
+    }
+    Peq => {
+      let arg0 = get_arg::<&Pointer>(&state.env, 0, args);
+      let arg1 = get_arg::<&Pointer>(&state.env, 1, args);
+      state.env.set(dest, Value::Bool(arg0 == arg1));
+    }
+    Plt => {
+      let arg0 = get_arg::<&Pointer>(&state.env, 0, args);
+      let arg1 = get_arg::<&Pointer>(&state.env, 1, args);
+      state.env.set(dest, Value::Bool(arg0.diff(arg1)? < 0));
+    }
+    Pgt => {
+      let arg0 = get_arg::<&Pointer>(&state.env, 0, args);
+      let arg1 = get_arg::<&Pointer>(&state.env, 1, args);
+      state.env.set(dest, Value::Bool(arg0.diff(arg1)? > 0));
+    }
+    Ple => {
+      let arg0 = get_arg::<&Pointer>(&state.env, 0, args);
+      let arg1 = get_arg::<&Pointer>(&state.env, 1, args);
+      state.env.set(dest, Value::Bool(arg0.diff(arg1)? <= 0));
+    }
+    Pge => {
+      let arg0 = get_arg::<&Pointer>(&state.env, 0, args);
+      let arg1 = get_arg::<&Pointer>(&state.env, 1, args);
+      state.env.set(dest, Value::Bool(arg0.diff(arg1)? >= 0));
+    }
   }
   Ok(())
 }
@@ -575,7 +881,8 @@ fn execute_effect_op<T: std::io::Write>(
   result: &mut Option<Value>,
 ) -> Result<(), InterpError> {
   use bril_rs::EffectOps::{
-    Branch, Call, Commit, Free, Guard, Jump, Nop, Print, Return, Speculate, Store,
+    Abort, Branch, Call, Commit, Fill, Free, Guard, Jump, MemCopy, Nop, Print, PrintStr, Return,
+    Speculate, Store,
   };
   match op {
     Jump => {
@@ -611,6 +918,7 @@ fn execute_effect_op<T: std::io::Write>(
       }
     }
     Nop => {}
+    Abort => return Err(InterpError::Aborted),
     Call => {
       let callee_func = state.prog.get(funcs[0]).unwrap();
 
@@ -628,7 +936,21 @@ fn execute_effect_op<T: std::io::Write>(
       let arg0 = get_arg::<&Pointer>(&state.env, 0, args);
       state.heap.free(arg0)?;
     }
+    MemCopy => {
+      let arg0 = get_arg::<&Pointer>(&state.env, 0, args);
+      let arg1 = get_arg::<&Pointer>(&state.env, 1, args);
+      let arg2 = get_arg::<i64>(&state.env, 2, args);
+      state.heap.copy(arg0, arg1, arg2)?;
+    }
+    Fill => {
+      let arg0 = get_arg::<&Pointer>(&state.env, 0, args);
+      let arg1 = get_arg::<Value>(&state.env, 1, args);
+      let arg2 = get_arg::<i64>(&state.env, 2, args);
+      state.heap.fill(arg0, arg1, arg2)?;
+    }
     Speculate | Commit | Guard => unimplemented!(),
+    // `execute` prints the text itself, since it isn't numified
+    PrintStr => unreachable!(),
   }
   Ok(())
 }
@@ -649,6 +971,15 @@ fn execute<'a, T: std::io::Write>(
     let curr_numified_instrs = &curr_block.numified_instrs;
     // WARNING!!! We can add the # of instructions at once because you can only jump to a new block at the end. This may need to be changed if speculation is implemented
     state.instruction_count += curr_instrs.len();
+    // Since the count is updated a block at a time, the limit is enforced before running any of the block that would exceed it
+    if let Some(max_steps) = state.max_steps {
+      if state.instruction_count > max_steps {
+        return Err(
+          InterpError::StepLimitExceeded(max_steps)
+            .add_pos(curr_instrs.first().and_then(Instruction::get_pos)),
+        );
+      }
+    }
     last_label = current_label;
     current_label = curr_block.label.as_ref();
 
@@ -676,7 +1007,9 @@ fn execute<'a, T: std::io::Write>(
               bril_rs::Literal::Float(f) => {
                 state.env.set(numified_code.dest.unwrap(), Value::Float(*f));
               }
-              bril_rs::Literal::Char(_) | bril_rs::Literal::Bool(_) => unreachable!(),
+              bril_rs::Literal::Char(_) | bril_rs::Literal::Bool(_) | bril_rs::Literal::Null => {
+                unreachable!()
+              }
             }
           } else {
             state
@@ -704,6 +1037,18 @@ fn execute<'a, T: std::io::Write>(
           )
           .map_err(|e| e.add_pos(pos.clone()))?;
         }
+        Instruction::Effect {
+          op: bril_rs::EffectOps::PrintStr,
+          args: _,
+          labels: _,
+          funcs,
+          pos,
+        } => {
+          state
+            .out
+            .write_all(funcs[0].as_bytes())
+            .map_err(|e| InterpError::from(e).add_pos(pos.clone()))?;
+        }
         Instruction::Effect {
           op,
           args: _,
@@ -737,13 +1082,13 @@ fn execute<'a, T: std::io::Write>(
 }
 
 fn parse_args(
-  mut env: Environment,
+  env: &mut Environment,
   args: &[bril_rs::Argument],
   args_as_nums: &[usize],
   inputs: &[String],
-) -> Result<Environment, InterpError> {
+) -> Result<(), InterpError> {
   if args.is_empty() && inputs.is_empty() {
-    Ok(env)
+    Ok(())
   } else if inputs.len() != args.len() {
     Err(InterpError::BadNumFuncArgs(args.len(), inputs.len()))
   } else {
@@ -797,11 +1142,55 @@ fn parse_args(
               Ok(())
             },
           ),
-      })?;
-    Ok(env)
+      })
   }
 }
 
+// Like parse_args, but the arguments of main are looked up by name in `bindings` and don't need to be parsed
+fn bind_args(
+  env: &mut Environment,
+  args: &[bril_rs::Argument],
+  args_as_nums: &[usize],
+  bindings: &[(String, bril_rs::Literal)],
+) -> Result<(), InterpError> {
+  if bindings.len() != args.len() {
+    return Err(InterpError::BadNumFuncArgs(args.len(), bindings.len()));
+  }
+  args
+    .iter()
+    .zip(args_as_nums.iter())
+    .try_for_each(|(arg, arg_as_num)| {
+      let (_, literal) = bindings
+        .iter()
+        .find(|(name, _)| name == &arg.name)
+        .ok_or_else(|| InterpError::VarUndefined(arg.name.clone()))?;
+      let value = match (&arg.arg_type, literal) {
+        // Integer literals can be promoted to Floating point, just like for constants
+        #[allow(clippy::cast_precision_loss)]
+        (bril_rs::Type::Float, bril_rs::Literal::Int(i)) => Value::Float(*i as f64),
+        // `null` has no type of its own to compare against
+        (arg_type, literal @ bril_rs::Literal::Null) => {
+          if !literal.fits_type(arg_type) {
+            return Err(InterpError::BadFuncArgType(
+              arg_type.clone(),
+              literal.to_string(),
+            ));
+          }
+          Value::from(literal)
+        }
+        (arg_type, literal) if arg_type == &literal.get_type() => Value::from(literal),
+        (arg_type, literal) => {
+          return Err(InterpError::BadFuncArgType(
+            arg_type.clone(),
+            literal.to_string(),
+          ))
+        }
+      };
+      env.set(*arg_as_num, value);
+      Ok(())
+    })
+}
+
 // State captures the parts of the interpreter that are used across function boundaries
 struct State<'a, T: std::io::Write> {
   prog: &'a BBProgram,
@@ -809,16 +1198,24 @@ struct State<'a, T: std::io::Write> {
   heap: Heap,
   out: T,
   instruction_count: usize,
+  max_steps: Option<usize>,
 }
 
 impl<'a, T: std::io::Write> State<'a, T> {
-  const fn new(prog: &'a BBProgram, env: Environment, heap: Heap, out: T) -> Self {
+  const fn new(
+    prog: &'a BBProgram,
+    env: Environment,
+    heap: Heap,
+    out: T,
+    max_steps: Option<usize>,
+  ) -> Self {
     Self {
       prog,
       env,
       heap,
       out,
       instruction_count: 0,
+      max_steps,
     }
   }
 }
@@ -835,20 +1232,61 @@ pub fn execute_main<T: std::io::Write, U: std::io::Write>(
   profiling: bool,
   mut profiling_out: U,
 ) -> Result<(), PositionalInterpError> {
-  let main_func = prog
-    .index_of_main
-    .map(|i| prog.get(i).unwrap())
-    .ok_or(InterpError::NoMainFunction)?;
+  let instruction_count = execute_main_with_limit(prog, out, input_args, None)?;
+
+  if profiling {
+    writeln!(profiling_out, "total_dyn_inst: {instruction_count}")
+      // We call flush here in case `profiling_out` is a https://doc.rust-lang.org/std/io/struct.BufWriter.html
+      // Otherwise we would expect this flush to be a nop.
+      .and_then(|()| profiling_out.flush())
+      .map_err(InterpError::IoError)?;
+  }
+
+  Ok(())
+}
+
+/// Like [`execute_main`], but stops with an error once more than ```max_steps``` instructions have been run. Returns the number of instructions that were run.
+/// # Panics
+/// This should not panic with normal use except if there is a bug or if you are using an unimplemented feature
+/// # Errors
+/// Will error on malformed `BBProgram`, like if the original Bril program was not well-formed, or if the step limit is exceeded
+pub fn execute_main_with_limit<T: std::io::Write>(
+  prog: &BBProgram,
+  out: T,
+  input_args: &[String],
+  max_steps: Option<usize>,
+) -> Result<usize, PositionalInterpError> {
+  let main_func = get_main(prog)?;
 
   let mut env = Environment::new(main_func.num_of_vars);
   let heap = Heap::default();
 
-  env = parse_args(env, &main_func.args, &main_func.args_as_nums, input_args)
-    .map_err(|e| e.add_pos(main_func.pos.clone()))?;
+  parse_args(
+    &mut env,
+    &main_func.args,
+    &main_func.args_as_nums,
+    input_args,
+  )
+  .map_err(|e| e.add_pos(main_func.pos.clone()))?;
 
-  let mut state = State::new(prog, env, heap, out);
+  let mut state = State::new(prog, env, heap, out, max_steps);
 
-  execute(&mut state, main_func)?;
+  run_main(&mut state, main_func)
+}
+
+fn get_main(prog: &BBProgram) -> Result<&BBFunction, InterpError> {
+  prog
+    .index_of_main
+    .map(|i| prog.get(i).unwrap())
+    .ok_or(InterpError::NoMainFunction)
+}
+
+// Runs main once its arguments have been placed in the environment of `state`
+fn run_main<'a, T: std::io::Write>(
+  state: &mut State<'a, T>,
+  main_func: &'a BBFunction,
+) -> Result<usize, PositionalInterpError> {
+  execute(state, main_func)?;
 
   if !state.heap.is_empty() {
     return Err(InterpError::MemLeak).map_err(|e| e.add_pos(main_func.pos.clone()));
@@ -856,13 +1294,130 @@ pub fn execute_main<T: std::io::Write, U: std::io::Write>(
 
   state.out.flush().map_err(InterpError::IoError)?;
 
-  if profiling {
-    writeln!(profiling_out, "total_dyn_inst: {}", state.instruction_count)
-      // We call flush here in case `profiling_out` is a https://doc.rust-lang.org/std/io/struct.BufWriter.html
-      // Otherwise we would expect this flush to be a nop.
-      .and_then(|()| profiling_out.flush())
-      .map_err(InterpError::IoError)?;
+  Ok(state.instruction_count)
+}
+
+/// Statistics about a single run of an [`Interpreter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecStats {
+  /// The number of instructions that were run, which is what ```--profile``` reports as ```total_dyn_inst```
+  pub total_dyn_inst: usize,
+}
+
+/// An interpreter for running the same program many times in one process, like in a superoptimizer or a server.
+///
+/// The program is converted and type checked once by [`Interpreter::load`]. The stack and heap are kept between runs and reset at the start of each one instead of being allocated again. Each run gives the same output and errors as [`execute_main_with_limit`].
+///
+/// ```
+/// use brilirs::interp::{execute_main_with_limit, Interpreter};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let src = "@main(n: int) { two: int = const 2; x: int = mul n two; print x; }";
+/// let prog: bril_rs::Program =
+///   bril2json::parse_abstract_program_from_read(src.as_bytes(), false, false, None).try_into()?;
+/// let mut interp = Interpreter::load(&prog)?;
+///
+/// let args = ["21".to_string()];
+/// let mut once = Vec::new();
+/// let count = execute_main_with_limit(&prog.clone().try_into()?, &mut once, &args, None)?;
+/// for _ in 0..3 {
+///   let mut out = Vec::new();
+///   let stats = interp.run(&args, &mut out)?;
+///   assert_eq!(out, once);
+///   assert_eq!(stats.total_dyn_inst, count);
+/// }
+///
+/// let mut out = Vec::new();
+/// interp.run_with_env(&[("n".to_string(), bril_rs::Literal::Int(21))], &mut out)?;
+/// assert_eq!(out, once);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Interpreter {
+  prog: BBProgram,
+  env: Environment,
+  heap: Heap,
+  max_steps: Option<usize>,
+}
+
+impl Interpreter {
+  /// Converts and type checks ```prog``` so that it can be run
+  /// # Errors
+  /// If ```prog``` can't be converted into a [`BBProgram`] or is not well typed
+  pub fn load(prog: &Program) -> Result<Self, PositionalInterpError> {
+    let bbprog: BBProgram = prog.clone().try_into()?;
+    check::type_check(&bbprog)?;
+    Ok(Self {
+      prog: bbprog,
+      env: Environment::new(0),
+      heap: Heap::default(),
+      max_steps: None,
+    })
   }
 
-  Ok(())
+  /// Stops each run with an error once more than ```max_steps``` instructions have been run, like [`execute_main_with_limit`]
+  #[must_use]
+  pub const fn with_max_steps(mut self, max_steps: Option<usize>) -> Self {
+    self.max_steps = max_steps;
+    self
+  }
+
+  /// Runs main with ```input_args``` as its arguments, printing to ```out```
+  /// # Errors
+  /// The same errors as [`execute_main_with_limit`]
+  pub fn run<T: std::io::Write>(
+    &mut self,
+    input_args: &[String],
+    out: T,
+  ) -> Result<ExecStats, PositionalInterpError> {
+    let main_func = get_main(&self.prog)?;
+    self.env.reset(main_func.num_of_vars);
+    self.heap.reset();
+    parse_args(
+      &mut self.env,
+      &main_func.args,
+      &main_func.args_as_nums,
+      input_args,
+    )
+    .map_err(|e| e.add_pos(main_func.pos.clone()))?;
+    self.run_main(out)
+  }
+
+  /// Like [`Interpreter::run`], but each argument of main is given by a binding from its name to a value instead of a string that needs to be parsed. An ```int``` value can be given for a ```float``` argument.
+  /// # Errors
+  /// If there isn't exactly one binding of the right type for each argument of main, or any error from [`Interpreter::run`]
+  pub fn run_with_env<T: std::io::Write>(
+    &mut self,
+    bindings: &[(String, bril_rs::Literal)],
+    out: T,
+  ) -> Result<ExecStats, PositionalInterpError> {
+    let main_func = get_main(&self.prog)?;
+    self.env.reset(main_func.num_of_vars);
+    self.heap.reset();
+    bind_args(
+      &mut self.env,
+      &main_func.args,
+      &main_func.args_as_nums,
+      bindings,
+    )
+    .map_err(|e| e.add_pos(main_func.pos.clone()))?;
+    self.run_main(out)
+  }
+
+  fn run_main<T: std::io::Write>(&mut self, out: T) -> Result<ExecStats, PositionalInterpError> {
+    let main_func = get_main(&self.prog)?;
+    // The stack and heap are handed back once the run is over, even if it failed
+    let mut state = State::new(
+      &self.prog,
+      std::mem::take(&mut self.env),
+      std::mem::take(&mut self.heap),
+      out,
+      self.max_steps,
+    );
+    let result = run_main(&mut state, main_func);
+    self.env = state.env;
+    self.heap = state.heap;
+    Ok(ExecStats {
+      total_dyn_inst: result?,
+    })
+  }
 }
@@ -8,7 +8,7 @@
 #![doc = include_str!("../README.md")]
 
 use basic_block::BBProgram;
-use bril_rs::Program;
+use bril_rs::{AbstractProgram, Program};
 use error::PositionalInterpError;
 
 /// The internal representation of brilirs, provided a ```TryFrom<Program>``` conversion
@@ -21,6 +21,8 @@ pub mod cli;
 pub mod error;
 /// Provides ```interp::execute_main``` to execute [Program] that have been converted into [`BBProgram`]
 pub mod interp;
+/// The request and response types of the ```--server``` protocol, along with ```server::serve``` which implements it
+pub mod server;
 
 #[doc(hidden)]
 pub fn run_input<T: std::io::Write, U: std::io::Write>(
@@ -33,14 +35,42 @@ pub fn run_input<T: std::io::Write, U: std::io::Write>(
   text: bool,
   src_name: Option<String>,
 ) -> Result<(), PositionalInterpError> {
+  run_program(
+    read_input(input, text, src_name),
+    out,
+    input_args,
+    profiling,
+    profiling_out,
+    check,
+  )
+}
+
+#[doc(hidden)]
+pub fn read_input(
+  input: impl std::io::Read,
+  text: bool,
+  src_name: Option<String>,
+) -> AbstractProgram {
   // It's a little confusing because of the naming conventions.
   //      - bril_rs takes file.json as input
   //      - bril2json takes file.bril as input
-  let prog: Program = if text {
-    bril2json::parse_abstract_program_from_read(input, true, true, src_name).try_into()?
+  if text {
+    bril2json::parse_abstract_program_from_read(input, true, true, src_name)
   } else {
-    bril_rs::load_abstract_program_from_read(input).try_into()?
-  };
+    bril_rs::load_abstract_program_from_read(input)
+  }
+}
+
+#[doc(hidden)]
+pub fn run_program<T: std::io::Write, U: std::io::Write>(
+  prog: AbstractProgram,
+  out: T,
+  input_args: &[String],
+  profiling: bool,
+  profiling_out: U,
+  check: bool,
+) -> Result<(), PositionalInterpError> {
+  let prog: Program = prog.try_into()?;
   let bbprog: BBProgram = prog.try_into()?;
   check::type_check(&bbprog)?;
 
@@ -1,19 +1,36 @@
+use bril_rs::repro::{Outcome, Repro};
 use bril_rs::Position;
 use brilirs::cli::Cli;
 use brilirs::error::PositionalInterpError;
 use clap::Parser;
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
 
 fn main() {
   let args = Cli::parse();
 
+  if args.server {
+    brilirs::server::serve(std::io::stdin().lock(), std::io::stdout().lock()).unwrap();
+    return;
+  }
+
+  if let Some(path) = &args.replay {
+    std::process::exit(replay(path));
+  }
+
   let input: Box<dyn std::io::Read> = match args.file.clone() {
     None => Box::new(std::io::stdin()),
 
     Some(input_file) => Box::new(File::open(input_file).unwrap()),
   };
 
+  if let Some(path) = &args.record_repro {
+    if let Err(e) = record(&args, input, path) {
+      exit_with_error(e)
+    }
+    return;
+  }
+
   /*
   todo should you be able to supply output locations from the command line interface?
   Instead of builtin std::io::stdout()/std::io::stderr()
@@ -29,39 +46,122 @@ fn main() {
     args.text,
     args.file,
   ) {
-    eprintln!("error: {e}");
-    if let PositionalInterpError {
-      pos: Some(Position {
-        pos,
-        pos_end,
-        src: Some(src),
-      }),
-      ..
-    } = e
-    {
-      let mut f = String::new();
-      File::open(src).unwrap().read_to_string(&mut f).unwrap();
-
-      let mut lines = f.split('\n');
-
-      // print the first line
-      eprintln!("{}", lines.nth((pos.row - 1) as usize).unwrap());
-      eprintln!("{:>width$}", "^", width = pos.col as usize);
-
-      // Then check if there is more
-      if let Some(end) = pos_end {
-        if pos.row != end.row {
-          let mut row = pos.row + 1;
-          while row < end.row {
-            eprintln!("{}", lines.nth((row - 1) as usize).unwrap());
-            eprintln!("^");
-            row += 1;
-          }
-          eprintln!("{}", lines.nth((end.row - 1) as usize).unwrap());
-          eprintln!("{:>width$}", "^", width = end.col as usize);
+    exit_with_error(e)
+  }
+}
+
+// Runs the program like normal while keeping a copy of what it prints, then writes everything to a .brilrepro file at `path`
+fn record(args: &Cli, input: impl std::io::Read, path: &str) -> Result<(), PositionalInterpError> {
+  let program = brilirs::read_input(input, args.text, args.file.clone());
+  let mut stdout = Vec::new();
+  let result = brilirs::run_program(
+    program.clone(),
+    &mut stdout,
+    &args.args,
+    args.profile,
+    std::io::stderr(),
+    args.check,
+  );
+  std::io::stdout().write_all(&stdout).unwrap();
+
+  let repro = Repro {
+    program,
+    tool: "brilirs".to_string(),
+    options: args.repro_options(),
+    args: args.args.clone(),
+    stdin: None,
+    seed: None,
+    expected: Outcome {
+      stdout: String::from_utf8_lossy(&stdout).into_owned(),
+      exit_code: if result.is_ok() { 0 } else { 2 },
+    },
+  };
+  repro.write(File::create(path).unwrap()).unwrap();
+  result
+}
+
+// Runs the .brilrepro file at `path` and prints whether it did the same thing as when it was recorded. Returns the exit code for brilirs
+fn replay(path: &str) -> i32 {
+  let repro = Repro::from_read(File::open(path).unwrap()).unwrap();
+  if repro.tool != "brilirs" {
+    eprintln!(
+      "error: {path} was recorded with {}, not brilirs",
+      repro.tool
+    );
+    return 2;
+  }
+  if repro.stdin.is_some() || repro.seed.is_some() {
+    eprintln!("error: {path} needs stdin or a random seed, which brilirs does not support");
+    return 2;
+  }
+  let options = match Cli::try_parse_from(
+    std::iter::once("brilirs").chain(repro.options.iter().map(String::as_str)),
+  ) {
+    Ok(options) => options,
+    Err(e) => {
+      eprintln!("error: {path} has options that brilirs does not understand\n{e}");
+      return 2;
+    }
+  };
+
+  let mut stdout = Vec::new();
+  let result = brilirs::run_program(
+    repro.program.clone(),
+    &mut stdout,
+    &repro.args,
+    options.profile,
+    std::io::sink(),
+    options.check,
+  );
+  let actual = Outcome {
+    stdout: String::from_utf8_lossy(&stdout).into_owned(),
+    exit_code: if result.is_ok() { 0 } else { 2 },
+  };
+  match repro.check(&actual) {
+    Ok(()) => {
+      println!("pass");
+      0
+    }
+    Err(mismatch) => {
+      print!("fail\n{mismatch}");
+      1
+    }
+  }
+}
+
+fn exit_with_error(e: PositionalInterpError) -> ! {
+  eprintln!("error: {e}");
+  if let PositionalInterpError {
+    pos: Some(Position {
+      pos,
+      pos_end,
+      src: Some(src),
+    }),
+    ..
+  } = e
+  {
+    let mut f = String::new();
+    File::open(src).unwrap().read_to_string(&mut f).unwrap();
+
+    let mut lines = f.split('\n');
+
+    // print the first line
+    eprintln!("{}", lines.nth((pos.row - 1) as usize).unwrap());
+    eprintln!("{:>width$}", "^", width = pos.col as usize);
+
+    // Then check if there is more
+    if let Some(end) = pos_end {
+      if pos.row != end.row {
+        let mut row = pos.row + 1;
+        while row < end.row {
+          eprintln!("{}", lines.nth((row - 1) as usize).unwrap());
+          eprintln!("^");
+          row += 1;
         }
+        eprintln!("{}", lines.nth((end.row - 1) as usize).unwrap());
+        eprintln!("{:>width$}", "^", width = end.col as usize);
       }
     }
-    std::process::exit(2)
   }
+  std::process::exit(2)
 }
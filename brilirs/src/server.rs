@@ -0,0 +1,145 @@
+use std::io::{BufRead, Write};
+
+use bril_rs::{AbstractProgram, Position, Program};
+use serde::{Deserialize, Serialize};
+
+use crate::basic_block::BBProgram;
+use crate::error::PositionalInterpError;
+use crate::{check, interp};
+
+/// A single line of input to the server
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+pub enum Request {
+  /// Type check and then run ```program``` with ```args``` as the arguments to main
+  Run {
+    /// The program in Bril's JSON form
+    program: AbstractProgram,
+    /// Arguments for the main function
+    #[serde(default)]
+    args: Vec<String>,
+    /// Stop with an error after running this many instructions
+    #[serde(default)]
+    max_steps: Option<usize>,
+  },
+  /// Only type check ```program```
+  Check {
+    /// The program in Bril's JSON form
+    program: AbstractProgram,
+  },
+}
+
+/// An error along with where in the program it happened, if known
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Diagnostic {
+  /// A description of the error
+  pub message: String,
+  /// The position attached to the offending instruction or function, if the program included positions
+  pub pos: Option<Position>,
+}
+
+impl From<PositionalInterpError> for Diagnostic {
+  fn from(PositionalInterpError { e, pos }: PositionalInterpError) -> Self {
+    Self {
+      message: e.to_string(),
+      pos,
+    }
+  }
+}
+
+/// The single line of output for each [`Request`]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Response {
+  /// Whether the request completed without any diagnostics or errors
+  pub ok: bool,
+  /// Everything the program printed before it finished or stopped
+  pub stdout: String,
+  /// Problems found before running the program, like malformed or ill-typed instructions
+  pub diagnostics: Vec<Diagnostic>,
+  /// The number of instructions run, if the program ran to completion
+  pub total_dyn_inst: Option<usize>,
+  /// The error that stopped the program while it was running, or that made the request unreadable
+  pub error: Option<Diagnostic>,
+}
+
+// Everything that happens before the program is run
+fn prepare(program: AbstractProgram) -> Result<BBProgram, PositionalInterpError> {
+  let prog: Program = program.try_into()?;
+  let bbprog: BBProgram = prog.try_into()?;
+  check::type_check(&bbprog)?;
+  Ok(bbprog)
+}
+
+/// Handles a single request. Requests don't share any state, and errors in the program are reported in the [`Response`] instead of being returned
+#[must_use]
+pub fn handle_request(request: Request) -> Response {
+  match request {
+    Request::Check { program } => match prepare(program) {
+      Ok(_) => Response {
+        ok: true,
+        ..Response::default()
+      },
+      Err(e) => Response {
+        diagnostics: vec![e.into()],
+        ..Response::default()
+      },
+    },
+    Request::Run {
+      program,
+      args,
+      max_steps,
+    } => {
+      let bbprog = match prepare(program) {
+        Ok(bbprog) => bbprog,
+        Err(e) => {
+          return Response {
+            diagnostics: vec![e.into()],
+            ..Response::default()
+          }
+        }
+      };
+      let mut stdout = Vec::new();
+      let result = interp::execute_main_with_limit(&bbprog, &mut stdout, &args, max_steps);
+      let stdout = String::from_utf8_lossy(&stdout).into_owned();
+      match result {
+        Ok(count) => Response {
+          ok: true,
+          stdout,
+          total_dyn_inst: Some(count),
+          ..Response::default()
+        },
+        Err(e) => Response {
+          stdout,
+          error: Some(e.into()),
+          ..Response::default()
+        },
+      }
+    }
+  }
+}
+
+/// Reads newline-delimited JSON [`Request`]s from ```input``` until it is exhausted and writes a [`Response`] for each one to ```out```, also one per line. Blank lines are skipped.
+/// # Errors
+/// Only if reading from ```input``` or writing to ```out``` fails
+pub fn serve<R: BufRead, W: Write>(input: R, mut out: W) -> std::io::Result<()> {
+  for line in input.lines() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let response = match serde_json::from_str::<Request>(&line) {
+      Ok(request) => handle_request(request),
+      Err(e) => Response {
+        error: Some(Diagnostic {
+          message: format!("malformed request: {e}"),
+          pos: None,
+        }),
+        ..Response::default()
+      },
+    };
+    serde_json::to_writer(&mut out, &response)?;
+    writeln!(out)?;
+    out.flush()?;
+  }
+  Ok(())
+}
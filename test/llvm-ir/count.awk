@@ -0,0 +1,10 @@
+# Counts the float multiplies, adds and calls to intrinsics in the IR of a function
+/ = fmul / { counts["fmul"]++ }
+/ = fadd / { counts["fadd"]++ }
+match($0, /call [a-z0-9]+ @llvm\.[a-z0-9.]+/) {
+  split(substr($0, RSTART, RLENGTH), call, " ")
+  counts[call[3]]++
+}
+END {
+  for (name in counts) print name, counts[name]
+}
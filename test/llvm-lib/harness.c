@@ -0,0 +1,14 @@
+// Calls the functions of `gcd.bril`, compiled with `brillvm --library`, from C
+#include <stdint.h>
+#include <stdio.h>
+
+int64_t gcd(int64_t a, int64_t b);
+void show(int64_t x);
+
+int main(void) {
+    printf("%lld\n", (long long)gcd(48, 18));
+    printf("%lld\n", (long long)gcd(17, 5));
+    fflush(stdout);
+    show(gcd(100, 75));
+    return 0;
+}
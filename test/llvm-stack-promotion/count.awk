@@ -0,0 +1,7 @@
+# Counts the arrays allocated on the stack, and the calls to malloc and free, in the IR of a function
+/ = alloca [^,]+, i64 [0-9]+/ { counts["alloca"]++ }
+/call .*@malloc\(/ { counts["malloc"]++ }
+/call .*@free\(/ { counts["free"]++ }
+END {
+  for (name in counts) print name, counts[name]
+}
@@ -0,0 +1,9 @@
+// ARGS: --max-instrs 36
+// RETURN: 1
+fn main() {
+    let small: [i64; 2] = [1, 2];
+    let big: [i64; 5] = [1, 2, 3, 4, 5];
+    println!("{}", small);
+    drop(small);
+    drop(big);
+}
@@ -0,0 +1,8 @@
+// ARGS: --max-instrs 37 --report-size
+fn main() {
+    let small: [i64; 2] = [1, 2];
+    let big: [i64; 5] = [1, 2, 3, 4, 5];
+    println!("{}", small);
+    drop(small);
+    drop(big);
+}